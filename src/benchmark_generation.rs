@@ -0,0 +1,407 @@
+//! Contains functionality to generate synthetic benchmarks from fully specified (concrete) models.
+//!
+//! A benchmark is generated by erasing update functions of randomly chosen variables of the
+//! concrete model (or replacing them with unknown functions of a given arity), and by computing
+//! the attractor states (or fixed points) of the concrete model as the observed data. The result
+//! is written in the same layout as the benchmarks in the `benchmark_models` directory.
+
+use crate::benchmarks::*;
+use crate::errors::SketchError;
+use crate::explanation::NetworkDynamics;
+
+use biodivine_lib_param_bn::biodivine_std::traits::Set;
+use biodivine_lib_param_bn::symbolic_async_graph::SymbolicAsyncGraph;
+use biodivine_lib_param_bn::{BooleanNetwork, FnUpdate, RegulatoryGraph, VariableId};
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+
+use std::fs::{create_dir_all, write};
+use std::path::Path;
+
+/// Settings of the benchmark generator.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct GeneratorSettings {
+    /// Number of update functions to erase.
+    pub num_erased: usize,
+    /// If set, erased update functions are replaced with unknown functions of (at least) this
+    /// arity, instead of being fully erased (see `mk_partially_specified_network`).
+    pub arity: Option<usize>,
+    /// Compute fixed points instead of attractor states as the observed data.
+    pub fixed_points: bool,
+    /// Seed of the random generator, so that the benchmark can be reproduced.
+    pub seed: u64,
+}
+
+/// Summary of a generated benchmark.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct GeneratedBenchmark {
+    pub benchmark: Benchmark,
+    pub erased_variables: Vec<String>,
+    pub observed_states: Vec<String>,
+}
+
+/// Check that the network is fully specified - it has no parameters, and all its variables have
+/// explicit update functions.
+fn check_concrete_network(bn: &BooleanNetwork) -> Result<(), SketchError> {
+    if bn.num_parameters() > 0 {
+        return Err(SketchError::InvalidArgument(
+            "The model must not contain any uninterpreted functions.".to_string(),
+        ));
+    }
+    if let Some(var) = bn
+        .variables()
+        .find(|var| bn.get_update_function(*var).is_none())
+    {
+        return Err(SketchError::InvalidArgument(format!(
+            "The model must specify all update functions (missing for `{}`).",
+            bn.get_variable_name(var)
+        )));
+    }
+    Ok(())
+}
+
+/// Randomly choose `num_erased` variables whose update functions will be erased. The chosen
+/// variables are returned in the order of the network.
+pub fn select_erased_variables(
+    bn: &BooleanNetwork,
+    num_erased: usize,
+    rng: &mut StdRng,
+) -> Result<Vec<VariableId>, SketchError> {
+    let variables: Vec<VariableId> = bn.variables().collect();
+    if num_erased > variables.len() {
+        return Err(SketchError::InvalidArgument(format!(
+            "Cannot erase {num_erased} update functions of a model with {} variables.",
+            variables.len()
+        )));
+    }
+    let mut erased: Vec<VariableId> = variables
+        .choose_multiple(rng, num_erased)
+        .cloned()
+        .collect();
+    erased.sort();
+    Ok(erased)
+}
+
+/// Create a partially specified variant of the concrete network `bn`, where update functions of
+/// the `erased` variables are unknown.
+///
+/// Without `arity`, the erased functions are fully unspecified (implicit). Otherwise, each of them
+/// is replaced with an unknown function applied to all regulators its concrete function depends
+/// on, and to randomly chosen other regulators up to the given arity. The concrete network is thus
+/// always one of the candidates, and the unknown functions may have a larger arity than requested
+/// (or a smaller one, if the variable has fewer regulators). Regulations of the remaining
+/// regulators are made non-observable, since the function cannot depend on them. Functions of
+/// variables without regulators stay implicit (an implicit function of such variable is already
+/// an unknown constant). The `arity` must be positive.
+///
+/// The unknown functions are named `f_<var>`, with underscores appended if the name is already
+/// taken by a variable (or by another unknown function).
+pub fn mk_partially_specified_network(
+    bn: &BooleanNetwork,
+    erased: &[VariableId],
+    arity: Option<usize>,
+    rng: &mut StdRng,
+) -> Result<BooleanNetwork, SketchError> {
+    // choose arguments of the unknown functions first, as they affect the regulatory graph
+    let mut arguments: Vec<(VariableId, Vec<VariableId>)> = Vec::new();
+    if arity == Some(0) {
+        return Err(SketchError::InvalidArgument(
+            "Arity of the unknown functions must be positive.".to_string(),
+        ));
+    }
+    if let Some(arity) = arity {
+        for var in erased {
+            if bn.regulators(*var).is_empty() {
+                continue;
+            }
+            let mut chosen: Vec<VariableId> = bn
+                .get_update_function(*var)
+                .as_ref()
+                .map(|update_fn| update_fn.collect_arguments())
+                .unwrap_or_default();
+            let others: Vec<VariableId> = bn
+                .regulators(*var)
+                .into_iter()
+                .filter(|regulator| !chosen.contains(regulator))
+                .collect();
+            let num_others = arity.saturating_sub(chosen.len()).min(others.len());
+            chosen.extend(others.choose_multiple(rng, num_others).cloned());
+            chosen.sort();
+            arguments.push((*var, chosen));
+        }
+    }
+    let is_argument = |regulator: VariableId, target: VariableId| {
+        arguments
+            .iter()
+            .find(|(var, _)| *var == target)
+            .map(|(_, args)| args.contains(&regulator))
+            .unwrap_or(true)
+    };
+
+    let var_names = bn
+        .variables()
+        .map(|v| bn.get_variable_name(v).clone())
+        .collect();
+    let mut regulatory_graph = RegulatoryGraph::new(var_names);
+    for regulation in bn.as_graph().regulations() {
+        let (regulator, target) = (regulation.get_regulator(), regulation.get_target());
+        regulatory_graph
+            .add_regulation(
+                bn.get_variable_name(regulator),
+                bn.get_variable_name(target),
+                regulation.is_observable() && is_argument(regulator, target),
+                regulation.get_monotonicity(),
+            )
+            .map_err(SketchError::SymbolicEncoding)?;
+    }
+
+    let mut partial_bn = BooleanNetwork::new(regulatory_graph);
+    let mut unknown_functions: Vec<(VariableId, FnUpdate)> = Vec::new();
+    for (var, args) in &arguments {
+        let mut name = format!("f_{}", bn.get_variable_name(*var));
+        while partial_bn.find_variable(&name).is_some()
+            || partial_bn.find_parameter(&name).is_some()
+        {
+            name.push('_');
+        }
+        let parameter = partial_bn
+            .add_parameter(&name, args.len() as u32)
+            .map_err(SketchError::SymbolicEncoding)?;
+        let args = args.iter().map(|arg| FnUpdate::Var(*arg)).collect();
+        unknown_functions.push((*var, FnUpdate::Param(parameter, args)));
+    }
+    for var in bn.variables() {
+        let update_fn = if !erased.contains(&var) {
+            bn.get_update_function(var).clone()
+        } else {
+            unknown_functions
+                .iter()
+                .find(|(v, _)| *v == var)
+                .map(|(_, update_fn)| update_fn.clone())
+        };
+        partial_bn
+            .set_update_function(var, update_fn)
+            .map_err(SketchError::SymbolicEncoding)?;
+    }
+    Ok(partial_bn)
+}
+
+/// Compute the observed data of the concrete network `bn` - one state of each attractor, or all
+/// fixed points. States are given as conjunctions of literals of all variables, like in the
+/// attractor data of benchmarks.
+pub fn compute_observed_states(
+    bn: &BooleanNetwork,
+    fixed_points: bool,
+) -> Result<Vec<String>, SketchError> {
+    check_concrete_network(bn)?;
+    let dynamics = NetworkDynamics {
+        graph: SymbolicAsyncGraph::new(bn).map_err(SketchError::SymbolicEncoding)?,
+        network: bn,
+    };
+    let unit = dynamics.graph.unit_colored_vertices().clone();
+    let mut observed_states = Vec::new();
+
+    if fixed_points {
+        let mut remaining = unit.minus(&dynamics.graph.can_post(&unit));
+        while let Some((state, state_string)) = dynamics.pick_state(&remaining) {
+            observed_states.push(state_string);
+            remaining = remaining.minus(&state);
+        }
+        return Ok(observed_states);
+    }
+
+    // a state lies in an attractor iff all states reachable from it can reach it back; states
+    // reaching a state outside of attractors are not in attractors either
+    let mut remaining = unit;
+    let mut preferred = dynamics.graph.mk_empty_colored_vertices();
+    while let Some((state, state_string)) = dynamics
+        .pick_state(&preferred)
+        .or_else(|| dynamics.pick_state(&remaining))
+    {
+        let forward = dynamics.reach_forward(&state);
+        let backward = dynamics.reach_backward(&state);
+        if forward.is_subset(&backward) {
+            observed_states.push(state_string);
+        }
+        remaining = remaining.minus(&backward);
+        // continue towards the attractors reachable from the state
+        preferred = forward.minus(&backward).intersect(&remaining);
+    }
+    Ok(observed_states)
+}
+
+/// Generate a synthetic benchmark from the concrete model given by `aeon_string`, and write it to
+/// the `output_dir` (the concrete and partially specified models, the observed data, and the
+/// metadata). The `source` (such as the path to the concrete model) is recorded in the metadata.
+pub fn generate_benchmark(
+    aeon_string: &str,
+    source: &str,
+    output_dir: &Path,
+    settings: &GeneratorSettings,
+) -> Result<GeneratedBenchmark, SketchError> {
+    let bn = BooleanNetwork::try_from(aeon_string)
+        .map_err(|e| SketchError::parse_at(e, Some(source), None))?;
+    check_concrete_network(&bn)?;
+
+    let mut rng = StdRng::seed_from_u64(settings.seed);
+    let erased = select_erased_variables(&bn, settings.num_erased, &mut rng)?;
+    let partial_bn = mk_partially_specified_network(&bn, &erased, settings.arity, &mut rng)?;
+    let observed_states = compute_observed_states(&bn, settings.fixed_points)?;
+    let erased_variables: Vec<String> = erased
+        .iter()
+        .map(|var| bn.get_variable_name(*var).clone())
+        .collect();
+
+    let mut metadata = format!(
+        "Source: {source}\nGenerated: {} of {} update functions erased ({}), seed {}\n",
+        erased.len(),
+        bn.num_vars(),
+        erased_variables.join(", "),
+        settings.seed,
+    );
+    if let Some(arity) = settings.arity {
+        metadata.push_str(&format!("Unknown function arity: {arity}\n"));
+    }
+    let data_kind = if settings.fixed_points {
+        "fixed points"
+    } else {
+        "attractor states"
+    };
+    metadata.push_str(&format!("Data: {} {data_kind}\n", observed_states.len()));
+
+    let output_name = output_dir.display().to_string();
+    create_dir_all(output_dir).map_err(|e| SketchError::io(&output_name, e))?;
+    let files = [
+        (GOAL_MODEL_FILE, bn.to_string()),
+        (MODEL_FILE, partial_bn.to_string()),
+        (ATTRACTORS_FILE, observed_states.join("\n")),
+        (METADATA_FILE, metadata),
+    ];
+    for (file_name, content) in files {
+        let path = output_dir.join(file_name);
+        write(&path, content).map_err(|e| SketchError::io(&path.display().to_string(), e))?;
+    }
+
+    Ok(GeneratedBenchmark {
+        benchmark: Benchmark::load(output_dir)?,
+        erased_variables,
+        observed_states,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::benchmark_generation::{
+        compute_observed_states, generate_benchmark, GeneratorSettings,
+    };
+    use crate::benchmarks::*;
+    use biodivine_lib_param_bn::biodivine_std::traits::Set;
+    use biodivine_lib_param_bn::symbolic_async_graph::SymbolicAsyncGraph;
+    use biodivine_lib_param_bn::BooleanNetwork;
+    use std::fs::read_to_string;
+
+    const CONCRETE_MODEL: &str = r"
+a -> b
+b -> a
+c -| c
+b -> c
+$a: b
+$b: a
+$c: !c & b
+";
+
+    #[test]
+    /// Test computing attractor states and fixed points of a concrete network.
+    fn test_observed_states() {
+        let bn = BooleanNetwork::try_from(CONCRETE_MODEL).unwrap();
+        // fixed point `~a & ~b & ~c`, and a cyclic attractor with `a & b` (oscillating `c`)
+        let fixed_points = compute_observed_states(&bn, true).unwrap();
+        assert_eq!(fixed_points, vec!["~a & ~b & ~c".to_string()]);
+        let attractor_states = compute_observed_states(&bn, false).unwrap();
+        assert_eq!(attractor_states.len(), 2);
+        assert!(attractor_states.contains(&"~a & ~b & ~c".to_string()));
+
+        let partial_bn = BooleanNetwork::try_from("a -> b\n$b: a").unwrap();
+        assert!(compute_observed_states(&partial_bn, false).is_err());
+    }
+
+    #[test]
+    /// Test generating a benchmark, its layout and reproducibility.
+    fn test_generate_benchmark() {
+        let directory = tempfile::tempdir().unwrap();
+        let dir = directory.path();
+        let settings = GeneratorSettings {
+            num_erased: 2,
+            arity: None,
+            fixed_points: false,
+            seed: 42,
+        };
+        let generated = generate_benchmark(CONCRETE_MODEL, "test", dir, &settings).unwrap();
+        assert_eq!(generated.benchmark.num_vars, 3);
+        assert_eq!(generated.erased_variables.len(), 2);
+        assert_eq!(generated.observed_states.len(), 2);
+
+        // the partially specified model contains the concrete one
+        let partial_bn =
+            BooleanNetwork::try_from(read_to_string(dir.join(MODEL_FILE)).unwrap().as_str())
+                .unwrap();
+        let concrete_bn =
+            BooleanNetwork::try_from(read_to_string(dir.join(GOAL_MODEL_FILE)).unwrap().as_str())
+                .unwrap();
+        let graph = SymbolicAsyncGraph::new(&partial_bn).unwrap();
+        assert!(graph.unit_colors().approx_cardinality() > 1.0);
+        assert!(!graph
+            .mk_subnetwork_colors(&concrete_bn)
+            .unwrap()
+            .intersect(graph.unit_colors())
+            .is_empty());
+        let metadata = read_to_string(dir.join(METADATA_FILE)).unwrap();
+        assert!(metadata.contains("seed 42"));
+
+        // the same seed gives the same benchmark
+        let regenerated = generate_benchmark(CONCRETE_MODEL, "test", dir, &settings).unwrap();
+        assert_eq!(generated, regenerated);
+
+        // unknown functions of given arity
+        let settings = GeneratorSettings {
+            num_erased: 3,
+            arity: Some(1),
+            ..settings
+        };
+        generate_benchmark(CONCRETE_MODEL, "test", dir, &settings).unwrap();
+        let partial_bn =
+            BooleanNetwork::try_from(read_to_string(dir.join(MODEL_FILE)).unwrap().as_str())
+                .unwrap();
+        assert_eq!(partial_bn.num_parameters(), 3);
+        // function of `c` depends on both its regulators, so they are both kept
+        let f_c = partial_bn.find_parameter("f_c").unwrap();
+        assert_eq!(partial_bn.get_parameter(f_c).get_arity(), 2);
+
+        // names of unknown functions do not clash with variables
+        let clashing_model = "a -> f_a\nf_a -> a\n$a: f_a\n$f_a: a\n";
+        let settings = GeneratorSettings {
+            num_erased: 2,
+            ..settings
+        };
+        generate_benchmark(clashing_model, "test", dir, &settings).unwrap();
+        let partial_bn =
+            BooleanNetwork::try_from(read_to_string(dir.join(MODEL_FILE)).unwrap().as_str())
+                .unwrap();
+        assert!(partial_bn.find_parameter("f_a_").is_some());
+        assert!(partial_bn.find_parameter("f_f_a").is_some());
+
+        let settings = GeneratorSettings {
+            num_erased: 4,
+            ..settings
+        };
+        assert!(generate_benchmark(CONCRETE_MODEL, "test", dir, &settings).is_err());
+        let settings = GeneratorSettings {
+            num_erased: 1,
+            arity: Some(0),
+            ..settings
+        };
+        assert!(generate_benchmark(CONCRETE_MODEL, "test", dir, &settings).is_err());
+    }
+}
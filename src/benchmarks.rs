@@ -0,0 +1,590 @@
+//! Contains functionality to discover benchmark models, record results of benchmark runs (into
+//! CSV or JSON), and compare them against stored expectations.
+//!
+//! Each benchmark is a sub-folder of the benchmark directory with a parametrized model and
+//! attractor data (and optionally a concrete goal model and a file with the expected results),
+//! as in the `benchmark_models` directory.
+
+use crate::errors::SketchError;
+
+use biodivine_lib_param_bn::BooleanNetwork;
+
+use std::fmt;
+use std::fs::{read_dir, read_to_string, write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Name of the file with the parametrized model in each benchmark folder.
+pub const MODEL_FILE: &str = "model_parametrized.aeon";
+/// Name of the file with the fully specified (goal) model in each benchmark folder.
+pub const GOAL_MODEL_FILE: &str = "model_concrete.aeon";
+/// Name of the file with the attractor data in each benchmark folder.
+pub const ATTRACTORS_FILE: &str = "attractor_states.txt";
+/// Name of the file with the output of the original (attractor) inference in each benchmark
+/// folder, which is used as the expected result.
+pub const RESULTS_FILE: &str = "results.txt";
+/// Name of the file with the metadata (source and publication) in each benchmark folder.
+pub const METADATA_FILE: &str = "metadata.txt";
+
+/// Single benchmark - a folder with a parametrized model and attractor data.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Benchmark {
+    pub name: String,
+    pub dir: PathBuf,
+    pub num_vars: usize,
+}
+
+impl Benchmark {
+    /// Load the benchmark from the given folder (the parametrized model must be valid).
+    pub fn load(dir: &Path) -> Result<Benchmark, SketchError> {
+        let model_path = dir.join(MODEL_FILE);
+        let model_name = model_path.display().to_string();
+        let aeon_string =
+            read_to_string(&model_path).map_err(|e| SketchError::io(&model_name, e))?;
+        let bn = BooleanNetwork::try_from(aeon_string.as_str())
+            .map_err(|e| SketchError::parse_at(e, Some(&model_name), None))?;
+        let name = dir
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| dir.display().to_string());
+        Ok(Benchmark {
+            name,
+            dir: dir.to_path_buf(),
+            num_vars: bn.num_vars(),
+        })
+    }
+
+    pub fn model_path(&self) -> PathBuf {
+        self.dir.join(MODEL_FILE)
+    }
+
+    pub fn attractors_path(&self) -> PathBuf {
+        self.dir.join(ATTRACTORS_FILE)
+    }
+
+    /// Path to the goal model, if the benchmark has one.
+    pub fn goal_model_path(&self) -> Option<PathBuf> {
+        Some(self.dir.join(GOAL_MODEL_FILE)).filter(|path| path.is_file())
+    }
+
+    /// Read the expected result of the `Attractors` inference mode from the results file of the
+    /// benchmark, if there is one.
+    pub fn read_expectation(&self) -> Result<Option<Expectation>, SketchError> {
+        let path = self.dir.join(RESULTS_FILE);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let results =
+            read_to_string(&path).map_err(|e| SketchError::io(&path.display().to_string(), e))?;
+        Ok(parse_results_output(&results))
+    }
+}
+
+/// Find all benchmarks in the sub-folders of `bench_dir` (folders containing both the
+/// parametrized model and the attractor data).
+///
+/// Benchmarks are sorted by the number of variables of their models (and then by name).
+pub fn discover_benchmarks(bench_dir: &Path) -> Result<Vec<Benchmark>, SketchError> {
+    let dir_name = bench_dir.display().to_string();
+    let entries = read_dir(bench_dir).map_err(|e| SketchError::io(&dir_name, e))?;
+    let mut benchmarks = Vec::new();
+    for entry in entries {
+        let path = entry.map_err(|e| SketchError::io(&dir_name, e))?.path();
+        if path.join(MODEL_FILE).is_file() && path.join(ATTRACTORS_FILE).is_file() {
+            benchmarks.push(Benchmark::load(&path)?);
+        }
+    }
+    benchmarks.sort_by(|x, y| (x.num_vars, &x.name).cmp(&(y.num_vars, &y.name)));
+    Ok(benchmarks)
+}
+
+/// Enum of inference modes the benchmarks can be run in (corresponding to options of the
+/// `inference-with-attractors` binary).
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum InferenceMode {
+    /// General attractors, additional attractors are forbidden.
+    Attractors,
+    /// Fixed points, additional fixed points are forbidden.
+    FixedPoints,
+    /// General attractors, additional attractors are allowed.
+    AttractorsAllowExtra,
+    /// Fixed points, additional fixed points are allowed.
+    FixedPointsAllowExtra,
+}
+
+impl InferenceMode {
+    /// Check whether the mode addresses fixed points only.
+    pub fn fixed_points(&self) -> bool {
+        matches!(
+            self,
+            InferenceMode::FixedPoints | InferenceMode::FixedPointsAllowExtra
+        )
+    }
+
+    /// Check whether the mode forbids additional attractors.
+    pub fn forbid_extra_attrs(&self) -> bool {
+        matches!(self, InferenceMode::Attractors | InferenceMode::FixedPoints)
+    }
+}
+
+impl fmt::Display for InferenceMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            InferenceMode::Attractors => "attractors",
+            InferenceMode::FixedPoints => "fixed-points",
+            InferenceMode::AttractorsAllowExtra => "attractors-allow-extra",
+            InferenceMode::FixedPointsAllowExtra => "fixed-points-allow-extra",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl TryFrom<&str> for InferenceMode {
+    type Error = SketchError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "attractors" => Ok(InferenceMode::Attractors),
+            "fixed-points" => Ok(InferenceMode::FixedPoints),
+            "attractors-allow-extra" => Ok(InferenceMode::AttractorsAllowExtra),
+            "fixed-points-allow-extra" => Ok(InferenceMode::FixedPointsAllowExtra),
+            _ => Err(SketchError::InvalidArgument(format!(
+                "Unknown inference mode `{value}`."
+            ))),
+        }
+    }
+}
+
+/// Enum of possible outcomes of a single benchmark run.
+#[derive(Clone, Debug, PartialEq)]
+pub enum BenchmarkStatus {
+    Finished,
+    Timeout,
+    Failed(String),
+}
+
+impl fmt::Display for BenchmarkStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BenchmarkStatus::Finished => write!(f, "finished"),
+            BenchmarkStatus::Timeout => write!(f, "timeout"),
+            BenchmarkStatus::Failed(_) => write!(f, "failed"),
+        }
+    }
+}
+
+/// Record of a single benchmark run (a benchmark in an inference mode).
+#[derive(Clone, Debug, PartialEq)]
+pub struct BenchmarkRecord {
+    pub benchmark: String,
+    pub mode: InferenceMode,
+    pub status: BenchmarkStatus,
+    pub time: Duration,
+    pub peak_memory_kb: Option<u64>,
+    pub num_candidates: Option<f64>,
+    pub goal_included: Option<bool>,
+}
+
+/// Header of the CSV file with benchmark records.
+pub const CSV_HEADER: &str =
+    "benchmark,mode,status,time_ms,peak_memory_kb,num_candidates,goal_included";
+
+/// Format an optional value for CSV (missing values are empty).
+fn csv_value<T: fmt::Display>(value: &Option<T>) -> String {
+    value.as_ref().map(|v| v.to_string()).unwrap_or_default()
+}
+
+/// Format a string as a CSV field. Strings with separators, quotes, line breaks, or surrounding
+/// whitespace are quoted (with inner quotes doubled).
+fn csv_string(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) || value.trim() != value {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Split the CSV `contents` into rows of fields, each with the number of the line it starts on.
+/// Unquoted fields are trimmed. Quoted fields are taken verbatim (doubled quotes stand for
+/// a single quote), and they may contain separators and line breaks.
+fn split_csv_rows(contents: &str) -> Result<Vec<(usize, Vec<String>)>, SketchError> {
+    let mut rows = Vec::new();
+    let mut row: Vec<String> = Vec::new();
+    let mut field = String::new();
+    let (mut line, mut row_line) = (1, 1);
+    // whether we are inside quotes, and whether the current field was quoted
+    let (mut in_quotes, mut quoted) = (false, false);
+    let finish_field = |field: &mut String, quoted: &mut bool| {
+        let value = if *quoted {
+            field.clone()
+        } else {
+            field.trim().to_string()
+        };
+        field.clear();
+        *quoted = false;
+        value
+    };
+
+    let mut chars = contents.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes => {
+                if chars.peek() == Some(&'"') {
+                    chars.next();
+                    field.push('"');
+                } else {
+                    in_quotes = false;
+                }
+            }
+            '"' if !quoted && field.trim().is_empty() => {
+                field.clear();
+                in_quotes = true;
+                quoted = true;
+            }
+            ',' if !in_quotes => row.push(finish_field(&mut field, &mut quoted)),
+            '\r' if !in_quotes && chars.peek() == Some(&'\n') => {}
+            '\n' if !in_quotes => {
+                row.push(finish_field(&mut field, &mut quoted));
+                rows.push((row_line, std::mem::take(&mut row)));
+                line += 1;
+                row_line = line;
+            }
+            c => {
+                if c == '\n' {
+                    line += 1;
+                }
+                field.push(c);
+            }
+        }
+    }
+    if in_quotes {
+        return Err(SketchError::parse(format!(
+            "Quoted value on line {row_line} is not terminated."
+        )));
+    }
+    if quoted || !field.is_empty() || !row.is_empty() {
+        row.push(finish_field(&mut field, &mut quoted));
+        rows.push((row_line, row));
+    }
+    Ok(rows)
+}
+
+/// Format an optional value for JSON (missing values are `null`).
+fn json_value<T: fmt::Display>(value: &Option<T>) -> String {
+    value
+        .as_ref()
+        .map(|v| v.to_string())
+        .unwrap_or_else(|| "null".to_string())
+}
+
+/// Format a string as a JSON string literal.
+fn json_string(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len() + 2);
+    escaped.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+impl BenchmarkRecord {
+    /// Format the record as a CSV row (see `CSV_HEADER`). Error messages of failed runs are not
+    /// included.
+    pub fn to_csv_row(&self) -> String {
+        format!(
+            "{},{},{},{},{},{},{}",
+            csv_string(&self.benchmark),
+            self.mode,
+            self.status,
+            self.time.as_millis(),
+            csv_value(&self.peak_memory_kb),
+            csv_value(&self.num_candidates),
+            csv_value(&self.goal_included),
+        )
+    }
+
+    /// Parse a record from a CSV row (see `CSV_HEADER`).
+    pub fn from_csv_row(row: &str) -> Result<BenchmarkRecord, SketchError> {
+        let mut rows = split_csv_rows(row)?;
+        if rows.len() != 1 {
+            return Err(SketchError::parse(format!(
+                "Expected a single benchmark record, got `{row}`."
+            )));
+        }
+        let (_, fields) = rows.remove(0);
+        BenchmarkRecord::from_csv_fields(&fields, row)
+    }
+
+    /// Parse a record from the fields of a CSV row (see `CSV_HEADER`). The `row` is only used in
+    /// error messages.
+    fn from_csv_fields(fields: &[String], row: &str) -> Result<BenchmarkRecord, SketchError> {
+        let fields: Vec<&str> = fields.iter().map(|field| field.as_str()).collect();
+        let [benchmark, mode, status, time, memory, candidates, goal] = fields[..] else {
+            return Err(SketchError::parse(format!(
+                "Expected 7 values in a benchmark record, got `{row}`."
+            )));
+        };
+        let invalid =
+            |field: &str| SketchError::parse(format!("Invalid value `{field}` in `{row}`."));
+        let status = match status {
+            "finished" => BenchmarkStatus::Finished,
+            "timeout" => BenchmarkStatus::Timeout,
+            "failed" => BenchmarkStatus::Failed(String::new()),
+            _ => return Err(invalid(status)),
+        };
+        Ok(BenchmarkRecord {
+            benchmark: benchmark.to_string(),
+            mode: InferenceMode::try_from(mode)?,
+            status,
+            time: Duration::from_millis(time.parse().map_err(|_| invalid(time))?),
+            peak_memory_kb: Some(memory)
+                .filter(|m| !m.is_empty())
+                .map(|m| m.parse().map_err(|_| invalid(m)))
+                .transpose()?,
+            num_candidates: Some(candidates)
+                .filter(|c| !c.is_empty())
+                .map(|c| c.parse().map_err(|_| invalid(c)))
+                .transpose()?,
+            goal_included: Some(goal)
+                .filter(|g| !g.is_empty())
+                .map(|g| g.parse().map_err(|_| invalid(g)))
+                .transpose()?,
+        })
+    }
+
+    /// Format the record as a JSON object.
+    pub fn to_json(&self) -> String {
+        let error = match &self.status {
+            BenchmarkStatus::Failed(message) => json_string(message),
+            _ => "null".to_string(),
+        };
+        format!(
+            "{{\"benchmark\": {}, \"mode\": \"{}\", \"status\": \"{}\", \"error\": {error}, \"time_ms\": {}, \"peak_memory_kb\": {}, \"num_candidates\": {}, \"goal_included\": {}}}",
+            json_string(&self.benchmark),
+            self.mode,
+            self.status,
+            self.time.as_millis(),
+            json_value(&self.peak_memory_kb),
+            json_value(&self.num_candidates),
+            json_value(&self.goal_included),
+        )
+    }
+
+    /// Use the result of the record as an expected result of future runs (only records with
+    /// known candidate counts can be used).
+    pub fn to_expectation(&self) -> Option<Expectation> {
+        self.num_candidates.map(|num_candidates| Expectation {
+            num_candidates,
+            goal_included: self.goal_included,
+        })
+    }
+
+    /// Compare the record with the expected result. Returns a description of the differences, or
+    /// `None` if the record matches (only finished runs can match).
+    pub fn compare_with(&self, expectation: &Expectation) -> Option<String> {
+        if self.status != BenchmarkStatus::Finished {
+            return Some(format!("run did not finish ({})", self.status));
+        }
+        let mut differences = Vec::new();
+        if let Some(num_candidates) = self.num_candidates {
+            // candidate counts are approximate, allow for rounding errors of large numbers
+            let tolerance = 1e-9 * expectation.num_candidates.abs().max(1.);
+            if (num_candidates - expectation.num_candidates).abs() > tolerance {
+                differences.push(format!(
+                    "{num_candidates} candidates instead of {}",
+                    expectation.num_candidates
+                ));
+            }
+        }
+        if let (Some(included), Some(expected)) = (self.goal_included, expectation.goal_included) {
+            if included != expected {
+                differences.push(format!(
+                    "goal network {} instead of {}",
+                    if included { "included" } else { "excluded" },
+                    if expected { "included" } else { "excluded" },
+                ));
+            }
+        }
+        Some(differences.join(", ")).filter(|d| !d.is_empty())
+    }
+}
+
+/// Expected result of a benchmark run.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Expectation {
+    pub num_candidates: f64,
+    pub goal_included: Option<bool>,
+}
+
+/// Parse the expected result from the text output of the `inference-with-attractors` binary
+/// (as stored in the results files of the benchmarks).
+pub fn parse_results_output(output: &str) -> Option<Expectation> {
+    let num_candidates = output.lines().find_map(|line| {
+        line.strip_suffix(" consistent candidate networks found in total")
+            .and_then(|count| count.trim().parse::<f64>().ok())
+    })?;
+    let goal_included = output.lines().find_map(|line| {
+        if line.starts_with("OK - goal network") {
+            Some(true)
+        } else if line.starts_with("NOK - goal network") {
+            Some(false)
+        } else {
+            None
+        }
+    });
+    Some(Expectation {
+        num_candidates,
+        goal_included,
+    })
+}
+
+/// Read benchmark records from a CSV file (as written by `write_records_csv`).
+pub fn read_records_csv(path: &Path) -> Result<Vec<BenchmarkRecord>, SketchError> {
+    let path_name = path.display().to_string();
+    let contents = read_to_string(path).map_err(|e| SketchError::io(&path_name, e))?;
+    let rows = split_csv_rows(&contents).map_err(|e| e.with_location(Some(&path_name), None))?;
+    rows.into_iter()
+        .skip(1)
+        .filter(|(_, fields)| fields.iter().any(|field| !field.is_empty()))
+        .map(|(line, fields)| {
+            BenchmarkRecord::from_csv_fields(&fields, &fields.join(","))
+                .map_err(|e| e.with_location(Some(&path_name), Some(line)))
+        })
+        .collect()
+}
+
+/// Write benchmark records into a CSV file.
+pub fn write_records_csv(path: &Path, records: &[BenchmarkRecord]) -> Result<(), SketchError> {
+    let mut lines = vec![CSV_HEADER.to_string()];
+    lines.extend(records.iter().map(|record| record.to_csv_row()));
+    write(path, lines.join("\n") + "\n")
+        .map_err(|e| SketchError::io(&path.display().to_string(), e))
+}
+
+/// Write benchmark records into a JSON file (as an array of objects).
+pub fn write_records_json(path: &Path, records: &[BenchmarkRecord]) -> Result<(), SketchError> {
+    let objects: Vec<String> = records
+        .iter()
+        .map(|record| format!("  {}", record.to_json()))
+        .collect();
+    write(path, format!("[\n{}\n]\n", objects.join(",\n")))
+        .map_err(|e| SketchError::io(&path.display().to_string(), e))
+}
+
+/// Get the peak memory usage (resident set size) of the current process in kB.
+/// Only available on Linux (returns `None` elsewhere).
+pub fn peak_memory_kb() -> Option<u64> {
+    let status = read_to_string("/proc/self/status").ok()?;
+    status.lines().find_map(|line| {
+        line.strip_prefix("VmHWM:")?
+            .trim()
+            .strip_suffix("kB")?
+            .trim()
+            .parse()
+            .ok()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::benchmarks::{
+        discover_benchmarks, parse_results_output, read_records_csv, write_records_csv,
+        BenchmarkRecord, BenchmarkStatus, Expectation, InferenceMode, ATTRACTORS_FILE, MODEL_FILE,
+    };
+    use std::time::Duration;
+
+    #[test]
+    /// Test discovering benchmark folders and reading their expected results.
+    fn test_discover_benchmarks() {
+        let directory = tempfile::tempdir().unwrap();
+        let bench_dir = directory.path();
+        for (name, model) in [
+            ("b_2v", "a -> b\nb -| a\n"),
+            ("a_3v", "a -> b\nb -> c\nc -| a\n"),
+        ] {
+            let dir = bench_dir.join(name);
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::write(dir.join(MODEL_FILE), model).unwrap();
+            std::fs::write(dir.join(ATTRACTORS_FILE), "").unwrap();
+        }
+        // folder without attractor data is not a benchmark
+        std::fs::create_dir_all(bench_dir.join("other")).unwrap();
+        std::fs::write(bench_dir.join("a_3v").join("results.txt"), "5 consistent candidate networks found in total\nNOK - goal network is NOT included in the candidate set.\n").unwrap();
+
+        let benchmarks = discover_benchmarks(bench_dir).unwrap();
+        let names: Vec<&str> = benchmarks.iter().map(|b| b.name.as_str()).collect();
+        assert_eq!(names, vec!["b_2v", "a_3v"]);
+        assert!(benchmarks[0].goal_model_path().is_none());
+        assert_eq!(benchmarks[0].read_expectation().unwrap(), None);
+        assert_eq!(
+            benchmarks[1].read_expectation().unwrap(),
+            Some(Expectation {
+                num_candidates: 5.,
+                goal_included: Some(false),
+            })
+        );
+    }
+
+    #[test]
+    /// Test writing and reading benchmark records, and comparing them with expectations.
+    fn test_benchmark_records() {
+        let output = "14088 consistent candidate networks found in total\nOK - goal network is included in the candidate set.\nElapsed time: 189ms";
+        let expectation = parse_results_output(output).unwrap();
+        assert_eq!(expectation.goal_included, Some(true));
+
+        let record = BenchmarkRecord {
+            benchmark: "celldivb_9v".to_string(),
+            mode: InferenceMode::Attractors,
+            status: BenchmarkStatus::Finished,
+            time: Duration::from_millis(189),
+            peak_memory_kb: Some(1024),
+            num_candidates: Some(14088.),
+            goal_included: Some(true),
+        };
+        assert!(record.compare_with(&expectation).is_none());
+        let timeout = BenchmarkRecord {
+            mode: InferenceMode::FixedPointsAllowExtra,
+            status: BenchmarkStatus::Timeout,
+            peak_memory_kb: None,
+            num_candidates: None,
+            goal_included: None,
+            ..record.clone()
+        };
+        assert!(timeout.compare_with(&expectation).is_some());
+        let wrong = BenchmarkRecord {
+            num_candidates: Some(14000.),
+            ..record.clone()
+        };
+        assert_eq!(
+            wrong.compare_with(&expectation),
+            Some("14000 candidates instead of 14088".to_string())
+        );
+        assert!(timeout.to_json().contains("\"num_candidates\": null"));
+
+        // names with separators, quotes, or line breaks are quoted
+        let odd_name = BenchmarkRecord {
+            benchmark: "model, \"v2\"\nvariant".to_string(),
+            ..record.clone()
+        };
+        assert!(odd_name
+            .to_csv_row()
+            .starts_with("\"model, \"\"v2\"\"\nvariant\","));
+        assert_eq!(
+            BenchmarkRecord::from_csv_row(&record.to_csv_row()).unwrap(),
+            record
+        );
+
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("records.csv");
+        let records = vec![record, odd_name, timeout];
+        write_records_csv(&path, &records).unwrap();
+        assert_eq!(read_records_csv(&path).unwrap(), records);
+    }
+}
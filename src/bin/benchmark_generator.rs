@@ -0,0 +1,76 @@
+use clap::Parser;
+
+use boolean_network_sketches::benchmark_generation::{generate_benchmark, GeneratorSettings};
+use boolean_network_sketches::errors::SketchError;
+
+use std::fs::read_to_string;
+use std::path::Path;
+
+/// Structure to collect CLI arguments
+#[derive(Parser)]
+#[clap(
+    author = "Ondřej Huvar",
+    about = "Generate a synthetic benchmark from a concrete model by erasing its update functions."
+)]
+struct Arguments {
+    /// Path to a file with a fully specified model in aeon format.
+    model_path: String,
+
+    /// Path to a directory to write the benchmark to.
+    output_dir: String,
+
+    /// Number of (randomly chosen) update functions to erase.
+    #[clap(short, long)]
+    erase: usize,
+
+    /// Replace erased update functions with unknown functions of (at least) this arity, instead of
+    /// erasing them completely. Regulators used by the concrete functions are always kept as
+    /// arguments.
+    #[clap(short, long)]
+    arity: Option<usize>,
+
+    /// Use fixed points instead of attractor states as the data.
+    #[clap(short, long)]
+    fixed_points: bool,
+
+    /// Seed of the random generator.
+    #[clap(short, long, default_value = "0")]
+    seed: u64,
+}
+
+/// Generate the benchmark folder with the partially specified model, the concrete model, the
+/// observed data, and metadata.
+fn generate(args: Arguments) -> Result<(), SketchError> {
+    let aeon_string =
+        read_to_string(&args.model_path).map_err(|e| SketchError::io(&args.model_path, e))?;
+    let settings = GeneratorSettings {
+        num_erased: args.erase,
+        arity: args.arity,
+        fixed_points: args.fixed_points,
+        seed: args.seed,
+    };
+    let generated = generate_benchmark(
+        aeon_string.as_str(),
+        &args.model_path,
+        Path::new(&args.output_dir),
+        &settings,
+    )?;
+
+    println!(
+        "Benchmark `{}` generated in `{}`.",
+        generated.benchmark.name, args.output_dir
+    );
+    println!(
+        "Erased update functions: {}",
+        generated.erased_variables.join(", ")
+    );
+    println!("Observed states: {}", generated.observed_states.len());
+    Ok(())
+}
+
+fn main() {
+    let args = Arguments::parse();
+    if let Err(e) = generate(args) {
+        println!("Error during computation: {e}")
+    }
+}
@@ -0,0 +1,353 @@
+use clap::Parser;
+
+use biodivine_lib_param_bn::BooleanNetwork;
+
+use boolean_network_sketches::benchmarks::*;
+use boolean_network_sketches::errors::SketchError;
+use boolean_network_sketches::inference_attractor_data::perform_inference_with_attractors_specific;
+use boolean_network_sketches::utils::check_if_result_contains_goal_unsafe;
+
+use biodivine_hctl_model_checker::mc_utils::get_extended_symbolic_graph;
+use std::collections::HashMap;
+use std::env::current_exe;
+use std::fs::read_to_string;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Prefix of the line with results printed by a worker process.
+const RESULT_PREFIX: &str = "BENCHMARK RESULT:";
+
+/// Structure to collect CLI arguments
+#[derive(Parser)]
+#[clap(
+    author = "Ondřej Huvar",
+    about = "Run the inference on all benchmarks and record timing, memory, and results."
+)]
+struct Arguments {
+    /// Path to a directory with benchmarks (each in its own sub-folder).
+    #[clap(default_value = "benchmark_models")]
+    bench_dir: String,
+
+    /// Comma-separated list of inference modes to run - `attractors`, `fixed-points`,
+    /// `attractors-allow-extra`, `fixed-points-allow-extra`.
+    #[clap(short, long, default_value = "attractors")]
+    modes: String,
+
+    /// Only run benchmarks with the given names (comma-separated).
+    #[clap(short, long)]
+    only: Option<String>,
+
+    /// Time limit for a single benchmark run (in seconds).
+    #[clap(short, long)]
+    timeout: Option<f64>,
+
+    /// Path to a CSV file to write the results to.
+    #[clap(long, default_value = "benchmark_results.csv")]
+    csv: String,
+
+    /// Path to a JSON file to write the results to.
+    #[clap(long)]
+    json: Option<String>,
+
+    /// Path to a CSV file with expected results (such as results of a previous run). Without it,
+    /// results of the `attractors` mode are compared with the `results.txt` files of benchmarks.
+    #[clap(short, long)]
+    expected: Option<String>,
+
+    /// Internal - run a single benchmark (given by its folder) in the current process, and print
+    /// its results.
+    #[clap(long, hide = true)]
+    worker: Option<String>,
+}
+
+/// Run the inference on the benchmark in the given mode, in the current process, and print the
+/// results (number of candidates, goal inclusion, and peak memory) on a single line.
+/// Errors (including a goal model that cannot be encoded in the candidates' colour space) are
+/// returned, so that the run is recorded as failed.
+fn run_worker(benchmark_dir: &Path, mode: InferenceMode) -> Result<(), SketchError> {
+    let benchmark = Benchmark::load(benchmark_dir)?;
+    let attractors_path = benchmark.attractors_path();
+    let data: Vec<String> = read_to_string(&attractors_path)
+        .map_err(|e| SketchError::io(&attractors_path.display().to_string(), e))?
+        .lines()
+        .map(|line| line.to_string())
+        .collect();
+    let aeon_string = read_to_string(benchmark.model_path())
+        .map_err(|e| SketchError::io(&benchmark.model_path().display().to_string(), e))?;
+    let bn = BooleanNetwork::try_from(aeon_string.as_str()).map_err(SketchError::parse)?;
+    let graph = get_extended_symbolic_graph(&bn, 1).map_err(SketchError::SymbolicEncoding)?;
+
+    let inferred_colors = perform_inference_with_attractors_specific(
+        data,
+        graph.clone(),
+        mode.fixed_points(),
+        mode.forbid_extra_attrs(),
+    )?;
+    let goal_included = match benchmark.goal_model_path() {
+        Some(goal_path) => {
+            let goal_aeon_string = read_to_string(&goal_path)
+                .map_err(|e| SketchError::io(&goal_path.display().to_string(), e))?;
+            let included = check_if_result_contains_goal_unsafe(
+                graph,
+                goal_aeon_string,
+                inferred_colors.clone(),
+            )?;
+            Some(included)
+        }
+        None => None,
+    };
+    println!(
+        "{RESULT_PREFIX} {} {} {}",
+        inferred_colors.approx_cardinality(),
+        goal_included
+            .map(|g| g.to_string())
+            .unwrap_or("-".to_string()),
+        peak_memory_kb()
+            .map(|m| m.to_string())
+            .unwrap_or("-".to_string()),
+    );
+    Ok(())
+}
+
+/// Parse the results line printed by a worker, and fill them in the `record`.
+fn parse_worker_output(output: &str, record: &mut BenchmarkRecord) -> Option<()> {
+    let line = output.lines().find_map(|l| l.strip_prefix(RESULT_PREFIX))?;
+    let values: Vec<&str> = line.split_whitespace().collect();
+    let [candidates, goal, memory] = values[..] else {
+        return None;
+    };
+    record.num_candidates = Some(candidates.parse().ok()?);
+    record.goal_included = goal.parse().ok();
+    record.peak_memory_kb = memory.parse().ok();
+    Some(())
+}
+
+/// Run the benchmark in the given mode in a separate worker process (so that its memory can be
+/// measured, and it can be killed after the `timeout`).
+fn run_benchmark(
+    benchmark: &Benchmark,
+    mode: InferenceMode,
+    timeout: Option<Duration>,
+) -> BenchmarkRecord {
+    let mut record = BenchmarkRecord {
+        benchmark: benchmark.name.clone(),
+        mode,
+        status: BenchmarkStatus::Finished,
+        time: Duration::ZERO,
+        peak_memory_kb: None,
+        num_candidates: None,
+        goal_included: None,
+    };
+
+    let start = Instant::now();
+    let executable = match current_exe() {
+        Ok(executable) => executable,
+        Err(e) => {
+            record.status = BenchmarkStatus::Failed(e.to_string());
+            return record;
+        }
+    };
+    let child = Command::new(executable)
+        .arg("--worker")
+        .arg(&benchmark.dir)
+        .arg("--modes")
+        .arg(mode.to_string())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn();
+    let mut child = match child {
+        Ok(child) => child,
+        Err(e) => {
+            record.status = BenchmarkStatus::Failed(e.to_string());
+            return record;
+        }
+    };
+    // read the outputs on separate threads, so that the worker never blocks on a full pipe
+    let (Some(mut stdout), Some(mut stderr)) = (child.stdout.take(), child.stderr.take()) else {
+        let _ = child.kill();
+        let _ = child.wait();
+        record.status = BenchmarkStatus::Failed("cannot read the worker outputs".to_string());
+        return record;
+    };
+    let stdout_reader = thread::spawn(move || {
+        let mut output = String::new();
+        let _ = stdout.read_to_string(&mut output);
+        output
+    });
+    let stderr_reader = thread::spawn(move || {
+        let mut output = String::new();
+        let _ = stderr.read_to_string(&mut output);
+        output
+    });
+
+    let exit_status = loop {
+        match child.try_wait() {
+            Ok(Some(exit_status)) => break Some(exit_status),
+            Ok(None) if timeout.is_some_and(|t| start.elapsed() > t) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                break None;
+            }
+            Ok(None) => thread::sleep(Duration::from_millis(10)),
+            Err(e) => {
+                record.status = BenchmarkStatus::Failed(e.to_string());
+                return record;
+            }
+        }
+    };
+    record.time = start.elapsed();
+    let stdout = stdout_reader.join().unwrap_or_default();
+    let stderr = stderr_reader.join().unwrap_or_default();
+
+    match exit_status {
+        None => record.status = BenchmarkStatus::Timeout,
+        Some(exit_status) if !exit_status.success() => {
+            // skip the backtrace hint printed after panic messages
+            let message = stderr
+                .lines()
+                .rev()
+                .find(|line| !line.trim().is_empty() && !line.starts_with("note:"))
+                .unwrap_or("worker process failed");
+            record.status = BenchmarkStatus::Failed(message.to_string());
+        }
+        Some(_) => {
+            if parse_worker_output(&stdout, &mut record).is_none() {
+                record.status =
+                    BenchmarkStatus::Failed("worker did not report any results".to_string());
+            }
+        }
+    }
+    record
+}
+
+/// Run the selected benchmarks in all selected inference modes, one by one (from the smallest
+/// models to the largest), record the results into CSV/JSON files, and compare them against the
+/// expected results. Returns the number of runs not matching the expected results.
+fn run_benchmarks(args: Arguments, modes: Vec<InferenceMode>) -> Result<usize, SketchError> {
+    let timeout = match args.timeout {
+        Some(t) if !t.is_finite() || t <= 0. => {
+            return Err(SketchError::InvalidArgument(format!(
+                "Time limit must be a positive number of seconds, got `{t}`."
+            )))
+        }
+        t => t.map(Duration::from_secs_f64),
+    };
+    let mut benchmarks = discover_benchmarks(Path::new(&args.bench_dir))?;
+    if let Some(only) = args.only {
+        let selected: Vec<&str> = only.split(',').map(|name| name.trim()).collect();
+        benchmarks.retain(|benchmark| selected.contains(&benchmark.name.as_str()));
+    }
+    println!(
+        "Found {} benchmarks: {}",
+        benchmarks.len(),
+        benchmarks
+            .iter()
+            .map(|b| b.name.clone())
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+
+    // expected results - either from a CSV file, or from the `results.txt` files
+    let mut expectations: HashMap<(String, InferenceMode), Expectation> = HashMap::new();
+    if let Some(expected_path) = args.expected {
+        for record in read_records_csv(Path::new(&expected_path))? {
+            if let Some(expectation) = record.to_expectation() {
+                expectations.insert((record.benchmark, record.mode), expectation);
+            }
+        }
+    } else {
+        for benchmark in &benchmarks {
+            if let Some(expectation) = benchmark.read_expectation()? {
+                expectations.insert(
+                    (benchmark.name.clone(), InferenceMode::Attractors),
+                    expectation,
+                );
+            }
+        }
+    }
+
+    let mut records = Vec::new();
+    let mut num_mismatches = 0;
+    for benchmark in &benchmarks {
+        for mode in &modes {
+            let record = run_benchmark(benchmark, *mode, timeout);
+            let memory = record
+                .peak_memory_kb
+                .map(|m| format!("{m}kB"))
+                .unwrap_or("-".to_string());
+            let candidates = record
+                .num_candidates
+                .map(|c| c.to_string())
+                .unwrap_or("-".to_string());
+            print!(
+                "{} ({mode}): {} in {}ms, memory {memory}, {candidates} candidates",
+                benchmark.name,
+                record.status,
+                record.time.as_millis(),
+            );
+            if let BenchmarkStatus::Failed(message) = &record.status {
+                print!(" ({message})");
+            }
+            match expectations.get(&(benchmark.name.clone(), *mode)) {
+                Some(expectation) => match record.compare_with(expectation) {
+                    Some(differences) => {
+                        num_mismatches += 1;
+                        println!(" - MISMATCH: {differences}");
+                    }
+                    None => println!(" - OK"),
+                },
+                None => println!(),
+            }
+            records.push(record);
+        }
+    }
+
+    write_records_csv(&PathBuf::from(&args.csv), &records)?;
+    println!("Results written to `{}`.", args.csv);
+    if let Some(json_path) = args.json {
+        write_records_json(&PathBuf::from(&json_path), &records)?;
+        println!("Results written to `{json_path}`.");
+    }
+    Ok(num_mismatches)
+}
+
+fn main() {
+    let args = Arguments::parse();
+    let modes: Result<Vec<InferenceMode>, SketchError> = args
+        .modes
+        .split(',')
+        .map(|mode| InferenceMode::try_from(mode.trim()))
+        .collect();
+    let modes = match modes {
+        Ok(modes) => modes,
+        Err(e) => {
+            println!("Error during computation: {e}");
+            std::process::exit(1);
+        }
+    };
+
+    // worker mode - run a single benchmark and print its results (errors are reported to the
+    // parent process through the standard error output)
+    if let Some(benchmark_dir) = &args.worker {
+        if let Err(e) = run_worker(Path::new(benchmark_dir), modes[0]) {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    match run_benchmarks(args, modes) {
+        Ok(0) => {}
+        Ok(num_mismatches) => {
+            println!("{num_mismatches} runs do not match the expected results.");
+            std::process::exit(1);
+        }
+        Err(e) => {
+            println!("Error during computation: {e}");
+            std::process::exit(1);
+        }
+    }
+}
@@ -1,9 +1,12 @@
 use clap::Parser;
 
+use biodivine_lib_param_bn::biodivine_std::traits::Set;
 use biodivine_lib_param_bn::BooleanNetwork;
 
+use boolean_network_sketches::errors::SketchError;
+use boolean_network_sketches::explanation::explain_network_colors_exclusion;
 use boolean_network_sketches::inference_attractor_data::*;
-use boolean_network_sketches::utils::check_if_result_contains_goal;
+use boolean_network_sketches::update_semantics::UpdateSemantics;
 
 use biodivine_hctl_model_checker::mc_utils::get_extended_symbolic_graph;
 use std::fs::{read_to_string, File};
@@ -35,48 +38,65 @@ struct Arguments {
     /// Path to a fully specified BN model to look for in the resulting set of candidates.
     #[clap(short, long)]
     goal_model: Option<String>,
+
+    /// Update semantics - `asynchronous`, `synchronous`, or `most_permissive`.
+    #[clap(short, long, default_value = "asynchronous")]
+    semantics: String,
 }
 
 /// Infer Boolean network using network sketches with attractor data.
 /// Sketch is given by 4 components: influence graph, PSBN, static and dynamic properties.
 /// The first three are given using aeon model format.
 /// Only dynamic properties allowed are attractor data.
-fn main() {
+fn run_inference(args: Arguments) -> Result<(), SketchError> {
     let start = SystemTime::now();
 
-    let args = Arguments::parse();
+    let semantics = UpdateSemantics::try_from(args.semantics.as_str())?;
     let goal_aeon_string: Option<String> = args
         .goal_model
-        .map(|file_name| read_to_string(file_name).unwrap());
+        .as_ref()
+        .map(|file_name| read_to_string(file_name).map_err(|e| SketchError::io(file_name, e)))
+        .transpose()?;
     println!(
-        "MODE: fixed point attrs only: {}; other attrs allowed: {}; goal model supplied: {}",
+        "MODE: fixed point attrs only: {}; other attrs allowed: {}; goal model supplied: {}; semantics: {}",
         args.fixed_points,
         args.allow_extra_attrs,
         goal_aeon_string.is_some(),
+        semantics,
     );
 
-    let data_file = File::open(Path::new(args.attractor_data_path.as_str())).unwrap();
+    let data_path = args.attractor_data_path.as_str();
+    let data_file = File::open(Path::new(data_path)).map_err(|e| SketchError::io(data_path, e))?;
     let reader = BufReader::new(&data_file);
-    let data: Vec<String> = reader.lines().collect::<Result<_, _>>().unwrap();
-    let aeon_string = read_to_string(args.model_path).unwrap();
-
-    let bn = BooleanNetwork::try_from(aeon_string.as_str()).unwrap();
+    let data: Vec<String> = reader
+        .lines()
+        .collect::<Result<_, _>>()
+        .map_err(|e| SketchError::io(data_path, e))?;
+    let data_copy = data.clone();
+    let model_path = args.model_path.as_str();
+    let aeon_string = read_to_string(model_path).map_err(|e| SketchError::io(model_path, e))?;
+
+    let bn = BooleanNetwork::try_from(aeon_string.as_str())
+        .map_err(|e| SketchError::parse_at(e, Some(model_path), None))?;
     println!("Loaded BN model with {} components.", bn.num_vars());
 
-    // Create extended graph object with 1 HCTL var (we dont need more)
-    let graph = get_extended_symbolic_graph(&bn, 1).unwrap();
+    // Create extended graph object with 1 HCTL var (we dont need more), and the variables needed
+    // for the update semantics
+    let graph = get_extended_symbolic_graph(&bn, 1 + semantics.num_extra_state_variables())
+        .map_err(SketchError::SymbolicEncoding)?;
     println!(
         "Model has {} symbolic parameters.",
         graph.symbolic_context().num_parameter_variables()
     );
     println!("-------");
 
-    let inferred_colors = perform_inference_with_attractors_specific(
+    let inferred_colors = perform_inference_with_attractors_under_semantics(
         data,
         graph.clone(),
         args.fixed_points,
         !args.allow_extra_attrs,
-    );
+        semantics,
+    )?;
     println!("-------");
 
     println!(
@@ -85,7 +105,56 @@ fn main() {
     );
 
     // check whether goal network (if supplied) is part of the solution set
-    check_if_result_contains_goal(graph, goal_aeon_string, inferred_colors);
+    if let Some(goal_aeon_string) = goal_aeon_string {
+        let goal_bn =
+            BooleanNetwork::try_from(goal_aeon_string.as_str()).map_err(SketchError::parse)?;
+        let goal_colors = graph
+            .mk_subnetwork_colors(&goal_bn)
+            .map_err(SketchError::SymbolicEncoding)?;
+        let included = goal_colors.minus(&inferred_colors).is_empty();
+        if included {
+            println!("OK - goal network is included in the candidate set.")
+        } else {
+            println!("NOK - goal network is NOT included in the candidate set.")
+        }
+
+        // if the goal network is excluded, explain which properties it violates (counterexamples
+        // are only computed for the asynchronous semantics)
+        if !included && semantics == UpdateSemantics::Asynchronous {
+            let (attractor_formulae, forbid_formula) = mk_attractor_data_formulae(
+                data_copy,
+                &bn,
+                args.fixed_points,
+                !args.allow_extra_attrs,
+            )?;
+            let property_kind = if args.fixed_points {
+                "fixed_point"
+            } else {
+                "attractor"
+            };
+            let mut named_formulae: Vec<(String, String)> = attractor_formulae
+                .into_iter()
+                .enumerate()
+                .map(|(i, formula)| (format!("{property_kind}_{}", i + 1), formula))
+                .collect();
+            if let Some(formula) = forbid_formula {
+                named_formulae.push((format!("forbid_other_{property_kind}s"), formula));
+            }
+            let report =
+                explain_network_colors_exclusion(&graph, &goal_bn, &goal_colors, &named_formulae)?;
+            println!("{report}");
+        }
+    } else {
+        println!("Goal network not provided.")
+    }
 
     println!("Elapsed time: {}ms", start.elapsed().unwrap().as_millis());
+    Ok(())
+}
+
+fn main() {
+    let args = Arguments::parse();
+    if let Err(e) = run_inference(args) {
+        println!("Error during computation: {e}")
+    }
 }
@@ -1,3 +1,4 @@
+use boolean_network_sketches::errors::SketchError;
 #[allow(unused_imports)]
 use boolean_network_sketches::inference_attractor_data::perform_inference_with_attractors_specific;
 use boolean_network_sketches::utils::summarize_candidates_naively;
@@ -29,15 +30,20 @@ struct Arguments {
 
 /// Analysis of the A. thaliana Sepal Primordium Polarity.
 /// Infers BNs from sketch containing attractor data.
-fn case_study(fixed_point_version: bool, prohibit_extra_attrs: bool, summarize: bool) {
+fn case_study(
+    fixed_point_version: bool,
+    prohibit_extra_attrs: bool,
+    summarize: bool,
+) -> Result<(), SketchError> {
     // parse BN object
-    let aeon_string =
-        read_to_string("benchmark_models/case_study_arabidopsis/arabidopsis.aeon").unwrap();
-    let bn = BooleanNetwork::try_from(aeon_string.as_str()).unwrap();
+    let model_path = "benchmark_models/case_study_arabidopsis/arabidopsis.aeon";
+    let aeon_string = read_to_string(model_path).map_err(|e| SketchError::io(model_path, e))?;
+    let bn = BooleanNetwork::try_from(aeon_string.as_str())
+        .map_err(|e| SketchError::parse_at(e, Some(model_path), None))?;
     println!("Loaded BN model with {} components.", bn.num_vars());
 
     // Create extended symbolic graph object with 1 HCTL var (we dont need more)
-    let graph = get_extended_symbolic_graph(&bn, 1).unwrap();
+    let graph = get_extended_symbolic_graph(&bn, 1).map_err(SketchError::SymbolicEncoding)?;
     println!(
         "Model has {} symbolic parameters.",
         graph.symbolic_context().num_parameter_variables()
@@ -54,7 +60,7 @@ fn case_study(fixed_point_version: bool, prohibit_extra_attrs: bool, summarize:
         graph.clone(),
         fixed_point_version,
         prohibit_extra_attrs,
-    );
+    )?;
 
     println!(
         "{} consistent candidate networks found in total",
@@ -64,8 +70,9 @@ fn case_study(fixed_point_version: bool, prohibit_extra_attrs: bool, summarize:
 
     if summarize {
         // summarize which update functions are unique for all candidates and which vary
-        summarize_candidates_naively(&graph, inferred_colors, false);
+        summarize_candidates_naively(&graph, inferred_colors, false)?;
     }
+    Ok(())
 }
 
 /// Run the case study regarding inference of A. Thaliana model.
@@ -81,10 +88,14 @@ fn main() {
     println!("MODE: {sketch_mode}");
 
     // run the variant of the case study specified by the user
-    if args.modified_sketch_variant {
-        case_study(false, true, args.summarize_candidates);
+    let result = if args.modified_sketch_variant {
+        case_study(false, true, args.summarize_candidates)
     } else {
-        case_study(true, false, args.summarize_candidates);
+        case_study(true, false, args.summarize_candidates)
+    };
+    if let Err(e) = result {
+        println!("Error during computation: {e}");
+        return;
     }
 
     println!(
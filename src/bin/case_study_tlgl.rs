@@ -10,6 +10,8 @@ use biodivine_hctl_model_checker::model_checking::model_check_formula_dirty;
 use biodivine_lib_param_bn::biodivine_std::traits::Set;
 use biodivine_lib_param_bn::BooleanNetwork;
 
+use boolean_network_sketches::errors::SketchError;
+
 use clap::Parser;
 
 use std::fs::read_to_string;
@@ -34,16 +36,16 @@ struct Arguments {
 /// First part of the case study regarding the initial version of the sketch.
 /// Analyses model with fully unspecified update logic and experimental data.
 /// At the end, analyses candidates by computing attractors and checking for unwanted patterns.
-fn case_study_part_1() {
+fn case_study_part_1() -> Result<(), SketchError> {
     let start = SystemTime::now();
-    let aeon_string =
-        read_to_string("benchmark_models/case_study_TLGL/TLGL_reduced_unknown_updates.aeon")
-            .unwrap();
+    let model_path = "benchmark_models/case_study_TLGL/TLGL_reduced_unknown_updates.aeon";
+    let aeon_string = read_to_string(model_path).map_err(|e| SketchError::io(model_path, e))?;
 
     // create the partially specified BN
-    let bn = BooleanNetwork::try_from(aeon_string.as_str()).unwrap();
+    let bn = BooleanNetwork::try_from(aeon_string.as_str())
+        .map_err(|e| SketchError::parse_at(e, Some(model_path), None))?;
     println!("Loaded BN model with {} components.", bn.num_vars());
-    let mut graph = get_extended_symbolic_graph(&bn, 2).unwrap();
+    let mut graph = get_extended_symbolic_graph(&bn, 2).map_err(SketchError::SymbolicEncoding)?;
     println!(
         "Model has {} symbolic parameters.",
         graph.symbolic_context().num_parameter_variables()
@@ -63,10 +65,10 @@ fn case_study_part_1() {
 
     // define data observation and corresponding dynamic property
     let diseased_attractor = "~Apoptosis_ & S1P & sFas & ~Fas & ~Ceramide_ & ~Caspase & MCL1 & ~BID_ & ~DISC_ & FLIP_ & ~IFNG_ & GPCR_";
-    let formulae: Vec<String> = vec![mk_formula_attractor(diseased_attractor.to_string())];
+    let formulae: Vec<String> = vec![mk_formula_attractor(diseased_attractor.to_string())?];
 
     // apply dynamic constraints
-    graph = apply_constraints_and_restrict(formulae, graph, "attractor property ensured");
+    graph = apply_constraints_and_restrict(formulae, graph, "attractor property ensured")?;
     println!(
         "{} consistent candidate networks found in total.",
         graph.mk_unit_colors().approx_cardinality(), // graph has restricted unit colors to satisfying ones
@@ -81,7 +83,8 @@ fn case_study_part_1() {
     println!("Analysing candidate set...");
 
     // compute attractors symbolically
-    let attrs_all_candidates = model_check_formula_dirty("!{x}: AG EF {x}", &graph).unwrap();
+    let attrs_all_candidates =
+        model_check_formula_dirty("!{x}: AG EF {x}", &graph).map_err(SketchError::ModelChecking)?;
     println!("Attractors for all candidates computed");
     println!(
         "Elapsed time from the start of this computation: {}ms",
@@ -91,7 +94,8 @@ fn case_study_part_1() {
 
     // check for candidates without attractor for programmed cell death
     let programmed_cell_death_formula = "Apoptosis_ & ~S1P & ~sFas & ~Fas & ~Ceramide_ & ~Caspase & ~MCL1 & ~BID_ & ~DISC_ & ~FLIP_ & ~CTLA4_ & ~TCR & ~IFNG_ & ~CREB & ~P2 & ~SMAD_ & ~GPCR_ & ~IAP_";
-    let pcd = model_check_formula_dirty(programmed_cell_death_formula, &graph).unwrap();
+    let pcd = model_check_formula_dirty(programmed_cell_death_formula, &graph)
+        .map_err(SketchError::ModelChecking)?;
     let colors_not_pcd = graph
         .mk_unit_colors()
         .minus(&attrs_all_candidates.intersect(&pcd).colors());
@@ -101,13 +105,17 @@ fn case_study_part_1() {
     );
     print!(
         "{}",
-        graph.pick_witness(&colors_not_pcd).to_bnet(false).unwrap()
+        graph
+            .pick_witness(&colors_not_pcd)
+            .to_bnet(false)
+            .map_err(SketchError::SymbolicEncoding)?
     );
     println!("-------");
 
     // check for candidates with unwanted attractor states
     let unwanted_state_formula = "Apoptosis_ & (S1P | sFas | Fas | Ceramide_ | Caspase  | MCL1 | BID_ | DISC_  | FLIP_ | CTLA4_ | TCR | IFNG_ | CREB  | P2 | SMAD_ | GPCR_ | IAP_)";
-    let unwanted_states = model_check_formula_dirty(unwanted_state_formula, &graph).unwrap();
+    let unwanted_states = model_check_formula_dirty(unwanted_state_formula, &graph)
+        .map_err(SketchError::ModelChecking)?;
     let colors_with_unwanted_states = attrs_all_candidates.intersect(&unwanted_states).colors();
     println!(
         "{} candidates have unwanted states in attractors, such as:\n",
@@ -118,7 +126,7 @@ fn case_study_part_1() {
         graph
             .pick_witness(&colors_with_unwanted_states)
             .to_bnet(false)
-            .unwrap()
+            .map_err(SketchError::SymbolicEncoding)?
     );
     println!("-------");
 
@@ -126,23 +134,24 @@ fn case_study_part_1() {
         "Elapsed time from the start of this computation: {}ms",
         start.elapsed().unwrap().as_millis()
     );
+    Ok(())
 }
 
 /// Second part of the case study regarding the refined version of the sketch.
 /// Extends previous sketch with partially specified update logic and hypotheses regarding
 /// additional attractors.
 /// At the end, prints a witness candidate, and summarizes all candidates.
-fn case_study_part_2(summarize_candidates: bool) {
+fn case_study_part_2(summarize_candidates: bool) -> Result<(), SketchError> {
     let start = SystemTime::now();
-    let aeon_string =
-        read_to_string("benchmark_models/case_study_TLGL/TLGL_reduced_partial_updates.aeon")
-            .unwrap();
+    let model_path = "benchmark_models/case_study_TLGL/TLGL_reduced_partial_updates.aeon";
+    let aeon_string = read_to_string(model_path).map_err(|e| SketchError::io(model_path, e))?;
 
     // create the partially specified BN object
-    let bn = BooleanNetwork::try_from(aeon_string.as_str()).unwrap();
+    let bn = BooleanNetwork::try_from(aeon_string.as_str())
+        .map_err(|e| SketchError::parse_at(e, Some(model_path), None))?;
     println!("Loaded BN model with {} components.", bn.num_vars());
     // create the STG object
-    let mut graph = get_extended_symbolic_graph(&bn, 2).unwrap();
+    let mut graph = get_extended_symbolic_graph(&bn, 2).map_err(SketchError::SymbolicEncoding)?;
     println!(
         "Model has {} symbolic parameters.",
         graph.symbolic_context().num_parameter_variables()
@@ -160,12 +169,12 @@ fn case_study_part_2(summarize_candidates: bool) {
     let diseased_attractor = "~Apoptosis_ & S1P & sFas & ~Fas & ~Ceramide_ & ~Caspase & MCL1 & ~BID_ & ~DISC_ & FLIP_ & ~IFNG_ & GPCR_";
     let healthy_attractor = "Apoptosis_ & ~S1P & ~sFas & ~Fas & ~Ceramide_ & ~Caspase & ~MCL1 & ~BID_ & ~DISC_ & ~FLIP_ & ~CTLA4_ & ~TCR & ~IFNG_ & ~CREB & ~P2 & ~SMAD_ & ~GPCR_ & ~IAP_";
     let formulae: Vec<String> = vec![
-        mk_formula_fixed_point_specific(healthy_attractor.to_string()),
-        mk_formula_attractor(diseased_attractor.to_string()),
+        mk_formula_fixed_point_specific(healthy_attractor.to_string())?,
+        mk_formula_attractor(diseased_attractor.to_string())?,
     ];
 
     // first ensure attractor existence
-    graph = apply_constraints_and_restrict(formulae, graph, "attractor property ensured");
+    graph = apply_constraints_and_restrict(formulae, graph, "attractor property ensured")?;
     println!(
         "After ensuring both properties regarding attractor presence, {} candidates remain.",
         graph.mk_unit_colors().approx_cardinality(),
@@ -176,9 +185,9 @@ fn case_study_part_2(summarize_candidates: bool) {
         healthy_attractor.to_string(),
         diseased_attractor.to_string(),
     ];
-    let formula = mk_formula_forbid_other_attractors(attr_set);
+    let formula = mk_formula_forbid_other_attractors(attr_set)?;
     let inferred_colors = model_check_formula_dirty(&formula, &graph)
-        .unwrap()
+        .map_err(SketchError::ModelChecking)?
         .colors();
     println!(
         "{} consistent candidate networks found in total",
@@ -194,15 +203,19 @@ fn case_study_part_2(summarize_candidates: bool) {
     println!("ONE OF THE CANDIDATE NETWORKS:\n");
     print!(
         "{}",
-        graph.pick_witness(&inferred_colors).to_bnet(false).unwrap()
+        graph
+            .pick_witness(&inferred_colors)
+            .to_bnet(false)
+            .map_err(SketchError::SymbolicEncoding)?
     );
     println!("----------");
 
     // summarize differences and similarities between candidates
     println!("SUMMARIZATION OF CANDIDATES' UPDATE FUNCTIONS:\n");
     if summarize_candidates {
-        summarize_candidates_naively(&graph, inferred_colors, false);
+        summarize_candidates_naively(&graph, inferred_colors, false)?;
     }
+    Ok(())
 }
 
 /// Run the case study regarding inference of T-LGL model.
@@ -216,10 +229,13 @@ fn main() {
     };
     println!("MODE: {sketch_mode}");
 
-    if args.refined_sketch_variant {
-        case_study_part_2(true);
+    let result = if args.refined_sketch_variant {
+        case_study_part_2(true)
     } else {
         case_study_part_1()
+    };
+    if let Err(e) = result {
+        println!("Error during computation: {e}")
     }
 }
 
@@ -250,18 +266,18 @@ mod tests {
         let healthy_attractor = "Apoptosis_ & ~S1P & ~sFas & ~Fas & ~Ceramide_ & ~Caspase & ~MCL1 & ~BID_ & ~DISC_ & ~FLIP_ & ~CTLA4_ & ~TCR & ~IFNG_ & ~CREB & ~P2 & ~SMAD_ & ~GPCR_ & ~IAP_";
 
         let formulae: Vec<String> = vec![
-            mk_formula_fixed_point_specific(healthy_attractor.to_string()),
-            mk_formula_attractor(diseased_attractor.to_string()),
+            mk_formula_fixed_point_specific(healthy_attractor.to_string()).unwrap(),
+            mk_formula_attractor(diseased_attractor.to_string()).unwrap(),
         ];
 
         // first ensure attractor existence
-        graph = apply_constraints_and_restrict(formulae, graph, "attractor ensured");
+        graph = apply_constraints_and_restrict(formulae, graph, "attractor ensured").unwrap();
         // prohibit all other attractors
         let attr_set = vec![
             healthy_attractor.to_string(),
             diseased_attractor.to_string(),
         ];
-        let formula = mk_formula_forbid_other_attractors(attr_set);
+        let formula = mk_formula_forbid_other_attractors(attr_set).unwrap();
         let inferred_colors = model_check_formula(&formula, &graph).unwrap().colors();
         assert_eq!(inferred_colors.approx_cardinality(), 378.);
     }
@@ -0,0 +1,100 @@
+use clap::Parser;
+
+use biodivine_lib_param_bn::{BooleanNetwork, ModelAnnotation};
+
+use boolean_network_sketches::candidate_analysis::comparison::{
+    compare_sketches, print_comparison_summary,
+};
+use boolean_network_sketches::errors::SketchError;
+use boolean_network_sketches::monitoring::{CancellationToken, InferenceMonitor};
+use boolean_network_sketches::partitioning::PartitionStore;
+
+use std::fs::read_to_string;
+use std::path::Path;
+use std::time::SystemTime;
+
+/// Structure to collect CLI arguments
+#[derive(Parser)]
+#[clap(
+    author = "Ondřej Huvar",
+    about = "Compare the sets of candidates of two variants of a sketch (with the same variables)."
+)]
+struct Arguments {
+    /// Path to a file with the first sketch (annotated model in aeon format).
+    sketch_a: String,
+
+    /// Path to a file with the second sketch (annotated model in aeon format).
+    sketch_b: String,
+
+    /// Maximal number of listed update functions (per variable) present in only one set.
+    #[clap(short, long, default_value = "5")]
+    max_functions: usize,
+
+    /// Directory where the candidates of the first sketch are stored. When the comparison is
+    /// repeated, the stored candidates are loaded instead of being computed again.
+    #[clap(long)]
+    store_a: Option<String>,
+
+    /// Directory where the candidates of the second sketch are stored (see `store-a`).
+    #[clap(long)]
+    store_b: Option<String>,
+}
+
+/// Load the PSBN and the annotations of a sketch (and return its model string as well).
+fn load_sketch(path: &str) -> Result<(BooleanNetwork, ModelAnnotation, String), SketchError> {
+    let aeon_string = read_to_string(path).map_err(|e| SketchError::io(path, e))?;
+    let bn = BooleanNetwork::try_from(aeon_string.as_str())
+        .map_err(|e| SketchError::parse_at(e, Some(path), None))?;
+    let annotations = ModelAnnotation::from_model_string(aeon_string.as_str());
+    Ok((bn, annotations, aeon_string))
+}
+
+/// Open the store of candidates of one of the compared sketches (given by its `role`). The
+/// encoding of candidates depends on both sketches, so the store is bound to both of them.
+fn open_store(
+    directory: &Option<String>,
+    role: &str,
+    aeon_a: &str,
+    aeon_b: &str,
+) -> Result<Option<PartitionStore>, SketchError> {
+    let Some(directory) = directory else {
+        return Ok(None);
+    };
+    let fingerprint = format!("sketch {role} of comparison\n{aeon_a}\n{aeon_b}");
+    PartitionStore::open(Path::new(directory), 1, &fingerprint).map(Some)
+}
+
+/// Compute the candidates of both sketches and summarize their differences.
+fn run_comparison(args: Arguments) -> Result<(), SketchError> {
+    let start = SystemTime::now();
+    let (bn_a, annotations_a, aeon_a) = load_sketch(&args.sketch_a)?;
+    let (bn_b, annotations_b, aeon_b) = load_sketch(&args.sketch_b)?;
+    let store_a = open_store(&args.store_a, "A", &aeon_a, &aeon_b)?;
+    let store_b = open_store(&args.store_b, "B", &aeon_a, &aeon_b)?;
+    println!("Sketch A: `{}`", args.sketch_a);
+    println!("Sketch B: `{}`", args.sketch_b);
+    println!("Computing candidates of both sketches...\n");
+
+    let monitor = InferenceMonitor::new(CancellationToken::new());
+    let comparison = compare_sketches(
+        &bn_a,
+        &annotations_a,
+        &bn_b,
+        &annotations_b,
+        [store_a.as_ref(), store_b.as_ref()],
+        &monitor,
+    )?;
+    print_comparison_summary(&comparison, args.max_functions)?;
+    println!(
+        "\nTotal elapsed time: {}ms",
+        start.elapsed().unwrap().as_millis()
+    );
+    Ok(())
+}
+
+fn main() {
+    let args = Arguments::parse();
+    if let Err(e) = run_comparison(args) {
+        println!("Error during computation: {e}")
+    }
+}
@@ -3,20 +3,23 @@ use biodivine_hctl_model_checker::model_checking::model_check_formula;
 
 use biodivine_lib_param_bn::BooleanNetwork;
 
+use boolean_network_sketches::errors::SketchError;
+
 use std::fs::read_to_string;
 use std::time::SystemTime;
 
 /// Run the computation regarding the small example from the paper.
-fn main() {
+fn run_small_example() -> Result<(), SketchError> {
     let start = SystemTime::now();
 
     let model_name = "benchmark_models/small_example/model-small-example.aeon";
 
-    let aeon_string = read_to_string(model_name).unwrap();
-    let bn = BooleanNetwork::try_from(aeon_string.as_str()).unwrap();
+    let aeon_string = read_to_string(model_name).map_err(|e| SketchError::io(model_name, e))?;
+    let bn = BooleanNetwork::try_from(aeon_string.as_str())
+        .map_err(|e| SketchError::parse_at(e, Some(model_name), None))?;
     println!("Loaded model with {} vars.", bn.num_vars());
 
-    let graph = get_extended_symbolic_graph(&bn, 3).unwrap();
+    let graph = get_extended_symbolic_graph(&bn, 3).map_err(SketchError::SymbolicEncoding)?;
     println!(
         "Model has {} symbolic parameters.",
         graph.symbolic_context().num_parameter_variables()
@@ -29,7 +32,8 @@ fn main() {
 
     // prior-knowledge dynamic properties only
     let prior_formula = "3{a}: (3{b}: (3{c}: (@{c}: ((EF {a}) & (EF {b}) & (@{a}: AG EF {a}) & (@{b}: (AG EF {b} & ~ EF {a}))))))";
-    let intermediate_result = model_check_formula(prior_formula, &graph).unwrap();
+    let intermediate_result =
+        model_check_formula(prior_formula, &graph).map_err(SketchError::ModelChecking)?;
     println!(
         "After applying prior-knowledge-based dynamic constraints, {} candidates remain.",
         intermediate_result.colors().approx_cardinality(),
@@ -37,7 +41,7 @@ fn main() {
 
     // properties regarding both prior knowledge and data
     let whole_formula = "3{a}: (3{b}: (3{c}: (@{c}: ((EF {a}) & (EF {b}) & (@{a}: AG EF {a}) & (@{b}: (AG EF {b} & ~ EF {a})))))) & (3{x}:@{x}: ~v_1 & ~v_2 & v_3 & AG EF {x}) & (3{y}:@{y}: v_1 & v_2 & ~v_3 & AG EF {y})";
-    let result = model_check_formula(whole_formula, &graph).unwrap();
+    let result = model_check_formula(whole_formula, &graph).map_err(SketchError::ModelChecking)?;
     let res_color = result.colors();
 
     println!(
@@ -54,6 +58,13 @@ fn main() {
     let witness_bn = graph.pick_witness(&res_color);
     println!("RESULTING NETWORK:");
     println!("{witness_bn}");
+    Ok(())
+}
+
+fn main() {
+    if let Err(e) = run_small_example() {
+        println!("Error during computation: {e}")
+    }
 }
 
 #[cfg(test)]
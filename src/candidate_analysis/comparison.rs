@@ -0,0 +1,551 @@
+//! Contains functionality to compare the sets of candidates of two variants of a sketch.
+//!
+//! The variants must have the same variables, but they can differ in regulations, update logic,
+//! and properties. To compare the candidates symbolically, both variants are first converted to
+//! "comparable" PSBNs (see `mk_comparable_networks`) that share the same list of parameters, and
+//! thus the same symbolic encoding. Two candidates (one of each variant) are then considered equal
+//! if all their update functions are the same.
+//!
+//! The candidates of a variant can be stored in a `PartitionStore` (as a single partition), so
+//! that they are not recomputed when the comparison is repeated. Since the encoding depends on
+//! both variants, the store must be opened for the pair of compared sketches.
+
+use crate::errors::SketchError;
+use crate::monitoring::InferenceMonitor;
+use crate::partitioning::PartitionStore;
+use crate::sketch_inference::{
+    count_required_extra_variables, infer_sketch_candidates, SketchComponents,
+};
+use crate::utils::{enumerate_update_functions, mk_restricted_graph, mk_update_fn_true};
+
+use biodivine_lib_bdd::{Bdd, BddVariable};
+use biodivine_lib_param_bn::biodivine_std::traits::Set;
+use biodivine_lib_param_bn::symbolic_async_graph::{GraphColors, SymbolicAsyncGraph};
+use biodivine_lib_param_bn::{BooleanNetwork, FnUpdate, ModelAnnotation, ParameterId, VariableId};
+
+use std::collections::{HashMap, HashSet};
+
+/// Result of the comparison of candidate sets of two sketch variants `A` and `B`.
+///
+/// Colors in `common` and `only_a` are valid in `graph_a`, colors in `only_b` in `graph_b`.
+pub struct CandidateComparison {
+    pub graph_a: SymbolicAsyncGraph,
+    pub graph_b: SymbolicAsyncGraph,
+    pub common: GraphColors,
+    pub only_a: GraphColors,
+    pub only_b: GraphColors,
+}
+
+/// Update functions of a single variable that appear only in one of the compared candidate sets.
+#[derive(Clone, Debug)]
+pub struct VariableDifference {
+    pub variable: VariableId,
+    /// Functions that appear among candidates of `A`, but not `B` (as strings).
+    pub only_a: Vec<String>,
+    /// Functions that appear among candidates of `B`, but not `A` (as strings).
+    pub only_b: Vec<String>,
+}
+
+/// Recursively replace the parameters of the update function according to `mapping`.
+fn remap_parameters(update_fn: &FnUpdate, mapping: &HashMap<ParameterId, ParameterId>) -> FnUpdate {
+    match update_fn {
+        FnUpdate::Const(value) => FnUpdate::Const(*value),
+        FnUpdate::Var(var) => FnUpdate::Var(*var),
+        FnUpdate::Param(param, args) => FnUpdate::Param(
+            mapping[param],
+            args.iter().map(|a| remap_parameters(a, mapping)).collect(),
+        ),
+        FnUpdate::Not(inner) => FnUpdate::Not(Box::new(remap_parameters(inner, mapping))),
+        FnUpdate::Binary(op, left, right) => FnUpdate::Binary(
+            *op,
+            Box::new(remap_parameters(left, mapping)),
+            Box::new(remap_parameters(right, mapping)),
+        ),
+    }
+}
+
+/// Collect the parameters of `bn` as `(name, arity)` pairs, with names prefixed by `prefix`.
+/// Unspecified update functions are represented by additional parameters (named after their
+/// variable) applied to the regulators.
+fn collect_parameters(bn: &BooleanNetwork, prefix: &str) -> Vec<(String, u32)> {
+    let mut parameters: Vec<(String, u32)> = bn
+        .parameters()
+        .map(|p| {
+            let parameter = bn.get_parameter(p);
+            (
+                format!("{prefix}param_{}", parameter.get_name()),
+                parameter.get_arity(),
+            )
+        })
+        .collect();
+    for var in bn.variables() {
+        if bn.get_update_function(var).is_none() {
+            let name = format!("{prefix}fn_{}", bn.get_variable_name(var));
+            parameters.push((name, bn.regulators(var).len() as u32));
+        }
+    }
+    parameters
+}
+
+/// Create a copy of `bn` with all given `parameters`, in which (renamed) parameters of `bn` are
+/// used in the update functions. Parameters of `bn` must be the prefix of the `own` slice of
+/// `parameters`, followed by the parameters representing its unspecified functions.
+fn mk_comparable_network(
+    bn: &BooleanNetwork,
+    parameters: &[(String, u32)],
+    own: std::ops::Range<usize>,
+) -> Result<BooleanNetwork, SketchError> {
+    let mut comparable = BooleanNetwork::new(bn.as_graph().clone());
+    let mut new_ids = Vec::with_capacity(parameters.len());
+    for (name, arity) in parameters {
+        let id = comparable
+            .add_parameter(name, *arity)
+            .map_err(SketchError::SymbolicEncoding)?;
+        new_ids.push(id);
+    }
+    let own_ids = &new_ids[own];
+    let mapping: HashMap<ParameterId, ParameterId> =
+        bn.parameters().zip(own_ids.iter().cloned()).collect();
+
+    let mut implicit_ids = own_ids[mapping.len()..].iter();
+    for var in bn.variables() {
+        let update_fn = match bn.get_update_function(var) {
+            Some(update_fn) => remap_parameters(update_fn, &mapping),
+            None => {
+                let Some(param) = implicit_ids.next() else {
+                    return Err(SketchError::SymbolicEncoding(format!(
+                        "No parameter represents the unspecified update function of `{}`.",
+                        bn.get_variable_name(var)
+                    )));
+                };
+                let args = bn.regulators(var).into_iter().map(FnUpdate::Var).collect();
+                FnUpdate::Param(*param, args)
+            }
+        };
+        comparable
+            .set_update_function(var, Some(update_fn))
+            .map_err(SketchError::SymbolicEncoding)?;
+    }
+    Ok(comparable)
+}
+
+/// Convert two PSBNs to "comparable" ones - they have the same variables (and regulations and
+/// update logic as the originals), and the same list of parameters: first those of `bn_a`, then
+/// those of `bn_b`. Unspecified update functions are replaced by explicit parameters. Therefore,
+/// both networks have the same symbolic encoding.
+///
+/// Returns an error if the two networks have different variables.
+pub fn mk_comparable_networks(
+    bn_a: &BooleanNetwork,
+    bn_b: &BooleanNetwork,
+) -> Result<(BooleanNetwork, BooleanNetwork), SketchError> {
+    let names_a: Vec<&String> = bn_a
+        .variables()
+        .map(|v| bn_a.get_variable_name(v))
+        .collect();
+    let names_b: Vec<&String> = bn_b
+        .variables()
+        .map(|v| bn_b.get_variable_name(v))
+        .collect();
+    if names_a != names_b {
+        return Err(SketchError::InvalidArgument(
+            "Compared sketches must have the same variables (in the same order).".to_string(),
+        ));
+    }
+
+    let mut parameters = collect_parameters(bn_a, "a_");
+    let num_params_a = parameters.len();
+    parameters.extend(collect_parameters(bn_b, "b_"));
+    let comparable_a = mk_comparable_network(bn_a, &parameters, 0..num_params_a)?;
+    let comparable_b = mk_comparable_network(bn_b, &parameters, num_params_a..parameters.len())?;
+    Ok((comparable_a, comparable_b))
+}
+
+/// Collect the symbolic parameter variables the update functions of the graph depend on.
+fn collect_used_parameter_vars(
+    graph: &SymbolicAsyncGraph,
+) -> Result<HashSet<BddVariable>, SketchError> {
+    let param_vars: HashSet<BddVariable> = graph
+        .symbolic_context()
+        .parameter_variables()
+        .iter()
+        .cloned()
+        .collect();
+    let mut used = HashSet::new();
+    for var in graph.variables() {
+        let fn_true = mk_update_fn_true(graph, var)?;
+        used.extend(fn_true.support_set().intersection(&param_vars));
+    }
+    Ok(used)
+}
+
+/// Restrict the colors of the graph by fixing all parameter variables that its update functions
+/// do not depend on (these belong to the other compared network) to `false`. Without it, each
+/// candidate would be represented by many colors.
+fn fix_unused_parameters(graph: &SymbolicAsyncGraph) -> Result<SymbolicAsyncGraph, SketchError> {
+    let used = collect_used_parameter_vars(graph)?;
+    let context = graph.symbolic_context();
+    let mut colors_bdd = graph.unit_colors().as_bdd().clone();
+    for param_var in context.parameter_variables() {
+        if !used.contains(param_var) {
+            colors_bdd = colors_bdd.and(&context.bdd_variable_set().mk_literal(*param_var, false));
+        }
+    }
+    mk_restricted_graph(graph, &graph.mk_unit_colors().copy(colors_bdd))
+}
+
+/// Create a BDD over the parameters of both networks, which is true if the update functions of
+/// `var` are the same in the candidates of both graphs.
+fn mk_function_equality(
+    graph_a: &SymbolicAsyncGraph,
+    graph_b: &SymbolicAsyncGraph,
+    var: VariableId,
+) -> Result<Bdd, SketchError> {
+    let state_vars = graph_a.symbolic_context().state_variables();
+    let fn_a = mk_update_fn_true(graph_a, var)?;
+    let fn_b = mk_update_fn_true(graph_b, var)?;
+    Ok(fn_a.iff(&fn_b).for_all(state_vars))
+}
+
+/// Compute the colors of `graph_x` for which there is no color of `graph_y` satisfying the
+/// given `equality` (which relates parameters of both graphs).
+///
+/// Colors of `graph_y` have the parameters of `graph_x` fixed (see `fix_unused_parameters`), so
+/// these are quantified out before the colors are related by the `equality`.
+fn mk_unmatched_colors(
+    graph_x: &SymbolicAsyncGraph,
+    graph_y: &SymbolicAsyncGraph,
+    equality: &Bdd,
+) -> Result<GraphColors, SketchError> {
+    let own_vars = collect_used_parameter_vars(graph_x)?;
+    let (own_vars, other_vars): (Vec<BddVariable>, Vec<BddVariable>) = graph_y
+        .symbolic_context()
+        .parameter_variables()
+        .iter()
+        .partition(|v| own_vars.contains(v));
+    let matched = graph_y
+        .unit_colors()
+        .as_bdd()
+        .exists(&own_vars)
+        .and(equality)
+        .exists(&other_vars);
+    let unmatched = graph_x.unit_colors().as_bdd().and_not(&matched);
+    Ok(graph_x.mk_unit_colors().copy(unmatched))
+}
+
+/// Compare the candidates of two graphs created from comparable networks (see
+/// `mk_comparable_networks`), with the same number of extra state variables.
+pub fn compare_candidates(
+    graph_a: SymbolicAsyncGraph,
+    graph_b: SymbolicAsyncGraph,
+) -> Result<CandidateComparison, SketchError> {
+    let graph_a = fix_unused_parameters(&graph_a)?;
+    let graph_b = fix_unused_parameters(&graph_b)?;
+
+    let mut equality = graph_a.symbolic_context().mk_constant(true);
+    for var in graph_a.variables() {
+        equality = equality.and(&mk_function_equality(&graph_a, &graph_b, var)?);
+    }
+    let only_a = mk_unmatched_colors(&graph_a, &graph_b, &equality)?;
+    let only_b = mk_unmatched_colors(&graph_b, &graph_a, &equality)?;
+    let common = graph_a.mk_unit_colors().minus(&only_a);
+    Ok(CandidateComparison {
+        graph_a,
+        graph_b,
+        common,
+        only_a,
+        only_b,
+    })
+}
+
+/// Load the candidates of a comparable sketch variant from the `store` (its only partition), or
+/// compute them (see `infer_sketch_candidates`) and save them to the `store`.
+fn load_or_infer_candidates(
+    bn: &BooleanNetwork,
+    annotations: &ModelAnnotation,
+    num_extra_vars: u16,
+    store: Option<&PartitionStore>,
+    monitor: &InferenceMonitor,
+) -> Result<SymbolicAsyncGraph, SketchError> {
+    let Some(store) = store else {
+        return infer_sketch_candidates(bn, annotations, num_extra_vars, monitor);
+    };
+    let graph = SketchComponents::read(bn, annotations)?.mk_graph(bn, num_extra_vars)?;
+    if let Some(colors) = store.load_result(0, &graph)? {
+        return mk_restricted_graph(&graph, &colors);
+    }
+    let graph = infer_sketch_candidates(bn, annotations, num_extra_vars, monitor)?;
+    store.save_result(0, &graph.mk_unit_colors())?;
+    Ok(graph)
+}
+
+/// Compute the candidates of two sketch variants (each given by a PSBN and the annotations of
+/// its model), and compare them. Both inferences are nested runs of the `monitor`.
+///
+/// If a store is given for a variant, its candidates are loaded from the store (if they were
+/// saved before), or saved there once computed. Each store must be opened for a single partition
+/// and the pair of compared sketches (see the module documentation).
+pub fn compare_sketches(
+    bn_a: &BooleanNetwork,
+    annotations_a: &ModelAnnotation,
+    bn_b: &BooleanNetwork,
+    annotations_b: &ModelAnnotation,
+    stores: [Option<&PartitionStore>; 2],
+    monitor: &InferenceMonitor,
+) -> Result<CandidateComparison, SketchError> {
+    let (comparable_a, comparable_b) = mk_comparable_networks(bn_a, bn_b)?;
+    let num_extra_vars = count_required_extra_variables(&comparable_a, annotations_a)?.max(
+        count_required_extra_variables(&comparable_b, annotations_b)?,
+    );
+    let [store_a, store_b] = stores;
+    let graph_a = load_or_infer_candidates(
+        &comparable_a,
+        annotations_a,
+        num_extra_vars,
+        store_a,
+        monitor,
+    )?;
+    let graph_b = load_or_infer_candidates(
+        &comparable_b,
+        annotations_b,
+        num_extra_vars,
+        store_b,
+        monitor,
+    )?;
+    compare_candidates(graph_a, graph_b)
+}
+
+/// For each variable, compute (at most `limit`) update functions that appear only among the
+/// candidates of one of the compared sketch variants. Variables for which both sets of functions
+/// are the same are omitted.
+pub fn compute_variable_differences(
+    comparison: &CandidateComparison,
+    limit: usize,
+) -> Result<Vec<VariableDifference>, SketchError> {
+    let (graph_a, graph_b) = (&comparison.graph_a, &comparison.graph_b);
+    let mut differences = Vec::new();
+    for var in graph_a.variables() {
+        let equality = mk_function_equality(graph_a, graph_b, var)?;
+        let only_a = mk_unmatched_colors(graph_a, graph_b, &equality)?;
+        let only_b = mk_unmatched_colors(graph_b, graph_a, &equality)?;
+        if only_a.is_empty() && only_b.is_empty() {
+            continue;
+        }
+        differences.push(VariableDifference {
+            variable: var,
+            only_a: enumerate_update_functions(graph_a, &only_a, var, limit)?,
+            only_b: enumerate_update_functions(graph_b, &only_b, var, limit)?,
+        })
+    }
+    Ok(differences)
+}
+
+/// Print the comparison of the candidate sets: sizes of their intersection and differences,
+/// update functions present only in one set (at most `limit` per variable), and a witness
+/// network of each non-empty difference.
+pub fn print_comparison_summary(
+    comparison: &CandidateComparison,
+    limit: usize,
+) -> Result<(), SketchError> {
+    println!(
+        "Candidates of A: {}, candidates of B: {}",
+        comparison.graph_a.mk_unit_colors().approx_cardinality(),
+        comparison.graph_b.mk_unit_colors().approx_cardinality(),
+    );
+    println!(
+        "Common candidates: {}",
+        comparison.common.approx_cardinality()
+    );
+    println!("Only in A: {}", comparison.only_a.approx_cardinality());
+    println!("Only in B: {}", comparison.only_b.approx_cardinality());
+
+    let differences = compute_variable_differences(comparison, limit)?;
+    if !differences.is_empty() {
+        println!("\nUpdate functions present in only one set:");
+    }
+    for difference in differences {
+        let name = comparison.graph_a.get_variable_name(difference.variable);
+        for update_fn in difference.only_a {
+            println!("{name} (only A): {update_fn}");
+        }
+        for update_fn in difference.only_b {
+            println!("{name} (only B): {update_fn}");
+        }
+    }
+
+    for (label, graph, colors) in [
+        ("A", &comparison.graph_a, &comparison.only_a),
+        ("B", &comparison.graph_b, &comparison.only_b),
+    ] {
+        if !colors.is_empty() {
+            let witness = graph.pick_witness(&colors.pick_singleton());
+            println!("\nWitness network only in {label}:\n{witness}");
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::candidate_analysis::comparison::{
+        compare_sketches, compute_variable_differences, mk_comparable_networks,
+    };
+    use crate::errors::SketchError;
+    use crate::monitoring::{CancellationToken, InferenceMonitor};
+    use crate::partitioning::PartitionStore;
+    use biodivine_lib_param_bn::{BooleanNetwork, ModelAnnotation};
+
+    #[test]
+    /// Test comparison of two sketch variants, one with a fixed update function of `v_1`, and
+    /// the other with an additional fixed point property.
+    fn test_compare_sketches() {
+        let sketch_a = r"
+v_1 -> v_2
+v_2 -? v_1
+$v_1: v_2
+";
+        let sketch_b = r"
+#! dynamic_property: p: #`3{x}: @{x}: (~v_1 & v_2 & (AX {x}))`#
+v_1 -? v_2
+v_2 -? v_1
+";
+        let bn_a = BooleanNetwork::try_from(sketch_a).unwrap();
+        let bn_b = BooleanNetwork::try_from(sketch_b).unwrap();
+        let (comparable_a, comparable_b) = mk_comparable_networks(&bn_a, &bn_b).unwrap();
+        assert_eq!(comparable_a.num_parameters(), 3);
+        assert_eq!(comparable_b.num_parameters(), 3);
+
+        let monitor = InferenceMonitor::new(CancellationToken::new());
+        // A has 1 candidate (v_2 = v_1), B has 1 candidate (v_1 = !v_2, v_2 = !v_1)
+        let comparison = compare_sketches(
+            &bn_a,
+            &ModelAnnotation::from_model_string(sketch_a),
+            &bn_b,
+            &ModelAnnotation::from_model_string(sketch_b),
+            [None, None],
+            &monitor,
+        )
+        .unwrap();
+        assert_eq!(comparison.graph_a.mk_unit_colors().approx_cardinality(), 1.);
+        assert_eq!(comparison.graph_b.mk_unit_colors().approx_cardinality(), 1.);
+        assert_eq!(comparison.common.approx_cardinality(), 0.);
+        assert_eq!(comparison.only_a.approx_cardinality(), 1.);
+        assert_eq!(comparison.only_b.approx_cardinality(), 1.);
+
+        let differences = compute_variable_differences(&comparison, 5).unwrap();
+        assert_eq!(differences.len(), 2);
+        assert!(differences.iter().all(|d| d.only_a.len() == 1));
+        assert!(differences.iter().all(|d| d.only_b.len() == 1));
+
+        // a sketch compared to itself only has common candidates
+        let comparison = compare_sketches(
+            &bn_b,
+            &ModelAnnotation::from_model_string(sketch_b),
+            &bn_b,
+            &ModelAnnotation::from_model_string(sketch_b),
+            [None, None],
+            &monitor,
+        )
+        .unwrap();
+        assert_eq!(comparison.common.approx_cardinality(), 1.);
+        assert_eq!(comparison.only_a.approx_cardinality(), 0.);
+        assert_eq!(comparison.only_b.approx_cardinality(), 0.);
+        assert!(compute_variable_differences(&comparison, 5)
+            .unwrap()
+            .is_empty());
+
+        // without the property, B has 4 candidates (each function is either a copy or
+        // a negation of its regulator), one of which is the candidate of the original B
+        let sketch_c = "v_1 -? v_2\nv_2 -? v_1\n";
+        let comparison = compare_sketches(
+            &bn_b,
+            &ModelAnnotation::from_model_string(sketch_b),
+            &BooleanNetwork::try_from(sketch_c).unwrap(),
+            &ModelAnnotation::new(),
+            [None, None],
+            &monitor,
+        )
+        .unwrap();
+        assert_eq!(comparison.common.approx_cardinality(), 1.);
+        assert_eq!(comparison.only_a.approx_cardinality(), 0.);
+        assert_eq!(comparison.only_b.approx_cardinality(), 3.);
+    }
+
+    #[test]
+    /// Test that stored candidates are loaded instead of being computed again, and that a store
+    /// of a different comparison is rejected.
+    fn test_compare_stored_sketches() {
+        let sketch_a = "v_1 -? v_2\nv_2 -? v_1\n";
+        let sketch_b = r"
+#! dynamic_property: p: #`3{x}: @{x}: (~v_1 & v_2 & (AX {x}))`#
+v_1 -? v_2
+v_2 -? v_1
+";
+        let bn_a = BooleanNetwork::try_from(sketch_a).unwrap();
+        let bn_b = BooleanNetwork::try_from(sketch_b).unwrap();
+        let annotations_a = ModelAnnotation::from_model_string(sketch_a);
+        let annotations_b = ModelAnnotation::from_model_string(sketch_b);
+        let directory = tempfile::tempdir().unwrap();
+        let open_store = |name: &str, sketch: &str| {
+            let fingerprint = format!("{name}\n{sketch_a}\n{sketch_b}\n{sketch}");
+            PartitionStore::open(&directory.path().join(name), 1, &fingerprint)
+        };
+        let store_a = open_store("a", sketch_a).unwrap();
+        let store_b = open_store("b", sketch_b).unwrap();
+
+        let monitor = InferenceMonitor::new(CancellationToken::new());
+        let computed = compare_sketches(
+            &bn_a,
+            &annotations_a,
+            &bn_b,
+            &annotations_b,
+            [Some(&store_a), Some(&store_b)],
+            &monitor,
+        )
+        .unwrap();
+
+        // a cancelled run can't compute the candidates, so they must be loaded
+        let token = CancellationToken::new();
+        let cancelled_monitor = InferenceMonitor::new(token.clone());
+        token.cancel();
+        let loaded = compare_sketches(
+            &bn_a,
+            &annotations_a,
+            &bn_b,
+            &annotations_b,
+            [Some(&store_a), Some(&store_b)],
+            &cancelled_monitor,
+        )
+        .unwrap();
+        assert_eq!(
+            loaded.graph_a.mk_unit_colors(),
+            computed.graph_a.mk_unit_colors()
+        );
+        assert_eq!(
+            loaded.graph_b.mk_unit_colors(),
+            computed.graph_b.mk_unit_colors()
+        );
+        assert_eq!(loaded.only_b.approx_cardinality(), 0.);
+        assert_eq!(loaded.only_a.approx_cardinality(), 3.);
+        let result = compare_sketches(
+            &bn_a,
+            &annotations_a,
+            &bn_b,
+            &annotations_b,
+            [None, Some(&store_b)],
+            &cancelled_monitor,
+        );
+        assert!(matches!(result, Err(SketchError::Interrupted(_))));
+
+        // a store of another comparison is rejected
+        assert!(open_store("a", sketch_b).is_err());
+    }
+
+    #[test]
+    /// Test that sketches with different variables can't be compared.
+    fn test_compare_incompatible_sketches() {
+        let bn_a = BooleanNetwork::try_from("a -> b\n").unwrap();
+        let bn_b = BooleanNetwork::try_from("a -> c\n").unwrap();
+        assert!(mk_comparable_networks(&bn_a, &bn_b).is_err());
+    }
+}
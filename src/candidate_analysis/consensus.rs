@@ -0,0 +1,190 @@
+//! Contains functionality to derive the update logic shared by all candidates, and to build a
+//! consensus (partially specified) network out of it.
+
+use crate::errors::SketchError;
+use crate::utils::{get_network, mk_update_fn_true};
+
+use biodivine_lib_bdd::Bdd;
+use biodivine_lib_param_bn::biodivine_std::traits::Set;
+use biodivine_lib_param_bn::symbolic_async_graph::{GraphColors, SymbolicAsyncGraph};
+use biodivine_lib_param_bn::{BooleanNetwork, FnUpdate, VariableId};
+
+/// Structure to represent the update logic of a single variable shared by a set of candidates.
+///
+/// Both BDDs are over the state variables (regulators of the variable). The `always_true` BDD
+/// is the intersection of the sets of satisfying input combinations across all candidates, and
+/// `possibly_true` is their union. If the two coincide, the function is the same in all
+/// candidates and `determined_fn` contains it.
+#[derive(Clone, Debug)]
+pub struct ConsensusFunction {
+    pub variable: VariableId,
+    pub num_regulators: usize,
+    pub always_true: Bdd,
+    pub possibly_true: Bdd,
+    pub determined_fn: Option<FnUpdate>,
+}
+
+impl ConsensusFunction {
+    /// Number of input combinations on which all candidates evaluate to true.
+    pub fn num_always_true(&self) -> f64 {
+        self.count_input_combinations(&self.always_true)
+    }
+
+    /// Number of input combinations on which at least one candidate evaluates to true.
+    pub fn num_possibly_true(&self) -> f64 {
+        self.count_input_combinations(&self.possibly_true)
+    }
+
+    /// Number of all input combinations of the function.
+    pub fn num_input_combinations(&self) -> f64 {
+        2f64.powi(self.num_regulators as i32)
+    }
+
+    /// Count satisfying input combinations of a BDD that only depends on the regulators.
+    fn count_input_combinations(&self, bdd: &Bdd) -> f64 {
+        let num_other_vars = bdd.num_vars() as i32 - self.num_regulators as i32;
+        bdd.cardinality() / 2f64.powi(num_other_vars)
+    }
+}
+
+/// Compute the update logic of variable `var` shared by all candidates in `colors`.
+fn compute_consensus_function(
+    graph: &SymbolicAsyncGraph,
+    colors: &GraphColors,
+    var: VariableId,
+) -> Result<ConsensusFunction, SketchError> {
+    let context = graph.symbolic_context();
+    let bn = get_network(graph)?;
+    let fn_true = mk_update_fn_true(graph, var)?;
+    let param_vars = context.parameter_variables();
+
+    // input is "always true" if every candidate implies the function is true for it
+    let always_true = colors.as_bdd().imp(&fn_true).for_all(param_vars);
+    let possibly_true = colors.as_bdd().and(&fn_true).exists(param_vars);
+
+    let determined_fn = if always_true == possibly_true {
+        Some(FnUpdate::build_from_bdd(context, &always_true))
+    } else {
+        None
+    };
+    Ok(ConsensusFunction {
+        variable: var,
+        num_regulators: bn.regulators(var).len(),
+        always_true,
+        possibly_true,
+        determined_fn,
+    })
+}
+
+/// Derive the consensus of the candidates given by `colors`.
+///
+/// For each variable, compute the intersection and union of satisfying input combinations of its
+/// update functions across all candidates. Return these per-variable results, together with a
+/// consensus network, in which update functions that are the same in all candidates are written
+/// out explicitly, and the remaining ones are kept as in the original (partially specified)
+/// network. The consensus network can serve as a refined PSBN for further sketch design.
+pub fn compute_consensus(
+    graph: &SymbolicAsyncGraph,
+    colors: &GraphColors,
+) -> Result<(BooleanNetwork, Vec<ConsensusFunction>), SketchError> {
+    let bn = get_network(graph)?;
+    if colors.is_empty() {
+        return Err(SketchError::InvalidArgument(
+            "Cannot compute consensus of an empty set of candidates.".to_string(),
+        ));
+    }
+
+    let mut consensus_bn = bn.clone();
+    let mut consensus_functions = Vec::new();
+    for var in bn.variables() {
+        let consensus_fn = compute_consensus_function(graph, colors, var)?;
+        if let Some(update_fn) = &consensus_fn.determined_fn {
+            consensus_bn
+                .set_update_function(var, Some(update_fn.clone()))
+                .map_err(SketchError::SymbolicEncoding)?;
+        }
+        consensus_functions.push(consensus_fn);
+    }
+    Ok((consensus_bn, consensus_functions))
+}
+
+/// Print the per-variable consensus of candidates, as computed by `compute_consensus`.
+pub fn print_consensus_summary(
+    graph: &SymbolicAsyncGraph,
+    consensus_functions: &[ConsensusFunction],
+) -> Result<(), SketchError> {
+    let bn = get_network(graph)?;
+    let context = graph.symbolic_context();
+    for consensus_fn in consensus_functions {
+        let var_name = bn.get_variable_name(consensus_fn.variable);
+        match &consensus_fn.determined_fn {
+            Some(update_fn) => {
+                println!("{var_name}: determined as `{}`", update_fn.to_string(bn));
+            }
+            None => {
+                // lower and upper bound of the function (as an intersection and union)
+                let lower_bound = FnUpdate::build_from_bdd(context, &consensus_fn.always_true);
+                let upper_bound = FnUpdate::build_from_bdd(context, &consensus_fn.possibly_true);
+                println!(
+                    "{var_name}: undetermined, true on {}/{} inputs in all candidates (`{}`), on {}/{} inputs in some candidate (`{}`)",
+                    consensus_fn.num_always_true(),
+                    consensus_fn.num_input_combinations(),
+                    lower_bound.to_string(bn),
+                    consensus_fn.num_possibly_true(),
+                    consensus_fn.num_input_combinations(),
+                    upper_bound.to_string(bn),
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::candidate_analysis::consensus::compute_consensus;
+    use biodivine_hctl_model_checker::mc_utils::get_extended_symbolic_graph;
+    use biodivine_lib_param_bn::BooleanNetwork;
+
+    const TEST_MODEL: &str = r"
+v_1 -> v_2
+v_2 -| v_1
+v_2 -> v_3
+v_3 -? v_3
+v_3 -| v_4
+v_4 -? v_4
+v_1 -?? v_4
+";
+
+    #[test]
+    /// Test computing the consensus of all candidates of a simple PSBN.
+    fn test_consensus() {
+        let bn = BooleanNetwork::try_from(TEST_MODEL).unwrap();
+        let graph = get_extended_symbolic_graph(&bn, 1).unwrap();
+        let context = graph.symbolic_context();
+        let (consensus_bn, consensus_fns) =
+            compute_consensus(&graph, &graph.mk_unit_colors()).unwrap();
+        assert_eq!(consensus_fns.len(), 4);
+
+        // functions of `v_1` and `v_2` have a single regulator with a fixed sign
+        let v_1 = bn.find_variable("v_1").unwrap();
+        let v_1_fn = consensus_bn.get_update_function(v_1).clone().unwrap();
+        assert_eq!(
+            context.mk_fn_update_true(&v_1_fn),
+            consensus_fns[0].always_true
+        );
+        assert_eq!(consensus_fns[0].num_always_true(), 1.);
+        assert!(consensus_fns[1].determined_fn.is_some());
+
+        // `v_3` can be any of `v_2 & v_3`, `v_2 | v_3`, `v_2 & !v_3`, `v_2 | !v_3`
+        let v_3 = bn.find_variable("v_3").unwrap();
+        assert!(consensus_bn.get_update_function(v_3).is_none());
+        assert!(consensus_fns[2].determined_fn.is_none());
+        assert_eq!(consensus_fns[2].num_always_true(), 0.);
+        assert_eq!(consensus_fns[2].num_possibly_true(), 4.);
+        assert_eq!(consensus_fns[2].num_input_combinations(), 4.);
+
+        let empty_colors = graph.mk_empty_colors();
+        assert!(compute_consensus(&graph, &empty_colors).is_err());
+    }
+}
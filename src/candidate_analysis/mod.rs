@@ -0,0 +1,8 @@
+//! Code regarding the analysis of the set of consistent candidate networks. That includes, for
+//! instance, the summary of regulations, the update logic shared by all candidates, or the
+//! ranking of candidates by their complexity.
+
+pub mod comparison;
+pub mod consensus;
+pub mod parsimony;
+pub mod regulations;
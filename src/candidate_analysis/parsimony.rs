@@ -0,0 +1,321 @@
+//! Contains functionality to rank candidates by the complexity of their update functions, and to
+//! select the most parsimonious ones.
+//!
+//! The score of a candidate is the sum of complexities of its update functions. Candidates are
+//! partitioned by their score symbolically - for each variable, the colors are split by the
+//! complexity of its update function, and these partitions are then combined variable by variable.
+
+use crate::errors::SketchError;
+use crate::utils::{get_network, mk_update_fn_rows};
+
+use biodivine_lib_bdd::{Bdd, BddPartialValuation, BddVariable};
+use biodivine_lib_param_bn::biodivine_std::traits::Set;
+use biodivine_lib_param_bn::symbolic_async_graph::{
+    GraphColors, SymbolicAsyncGraph, SymbolicContext,
+};
+
+use std::fmt;
+
+/// Enum of supported complexity metrics of update functions.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum ComplexityMetric {
+    /// Number of essential regulators of the function.
+    EssentialRegulators,
+    /// Number of clauses in the (optimized) DNF of the function.
+    DnfClauses,
+    /// Number of literals in the (optimized) DNF of the function.
+    DnfLiterals,
+}
+
+impl fmt::Display for ComplexityMetric {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ComplexityMetric::EssentialRegulators => write!(f, "essential-regulators"),
+            ComplexityMetric::DnfClauses => write!(f, "dnf-clauses"),
+            ComplexityMetric::DnfLiterals => write!(f, "dnf-literals"),
+        }
+    }
+}
+
+impl TryFrom<&str> for ComplexityMetric {
+    type Error = SketchError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "essential-regulators" => Ok(ComplexityMetric::EssentialRegulators),
+            "dnf-clauses" => Ok(ComplexityMetric::DnfClauses),
+            "dnf-literals" => Ok(ComplexityMetric::DnfLiterals),
+            _ => Err(SketchError::parse(format!(
+                "Invalid complexity metric `{value}`."
+            ))),
+        }
+    }
+}
+
+/// Partition `colors` by the number of essential regulators of the update function of `var`.
+/// Returns a vector where the `i`-th item is the BDD of colors with score `i`.
+fn partition_by_essential_regulators(
+    rows: &[Bdd],
+    num_regulators: usize,
+    colors: &Bdd,
+) -> Vec<Bdd> {
+    let mut partition = vec![colors.clone()];
+    for i in 0..num_regulators {
+        // colors where the regulator can change the output of the function
+        let mut essential = Bdd::mk_false(colors.num_vars());
+        for row in (0..rows.len()).filter(|row| row & (1 << i) == 0) {
+            essential = essential.or(&rows[row].xor(&rows[row | (1 << i)]));
+        }
+        partition = shift_partition(&partition, &essential);
+    }
+    partition
+}
+
+/// Add an indicator (given by a BDD) to the score of colors in the `partition`.
+fn shift_partition(partition: &[Bdd], indicator: &Bdd) -> Vec<Bdd> {
+    let mut shifted: Vec<Bdd> = partition.iter().map(|bdd| bdd.and_not(indicator)).collect();
+    shifted.push(Bdd::mk_false(partition[0].num_vars()));
+    for (score, bdd) in partition.iter().enumerate() {
+        shifted[score + 1] = shifted[score + 1].or(&bdd.and(indicator));
+    }
+    shifted
+}
+
+/// Compute the DNF complexity (number of clauses and literals) of a function given by its truth
+/// table (row index `r` assigns to the `i`-th regulator the `i`-th bit of `r`). The regulators
+/// are given by their symbolic state variables.
+///
+/// The DNF is optimized by `Bdd::to_optimized_dnf`, which does not guarantee the minimal one.
+/// Constant functions have complexity zero.
+fn dnf_complexity(
+    context: &SymbolicContext,
+    regulator_vars: &[BddVariable],
+    table: &[bool],
+) -> (usize, usize) {
+    if table.iter().all(|value| *value) || table.iter().all(|value| !*value) {
+        return (0, 0);
+    }
+
+    let satisfying_rows: Vec<BddPartialValuation> = (0..table.len())
+        .filter(|row| table[*row])
+        .map(|row| {
+            let values: Vec<_> = regulator_vars
+                .iter()
+                .enumerate()
+                .map(|(i, bdd_var)| (*bdd_var, row & (1 << i) != 0))
+                .collect();
+            BddPartialValuation::from_values(&values)
+        })
+        .collect();
+    let function_bdd = context.bdd_variable_set().mk_dnf(&satisfying_rows);
+    let dnf = function_bdd.to_optimized_dnf();
+    let num_literals = dnf.iter().map(|clause| clause.cardinality() as usize).sum();
+    (dnf.len(), num_literals)
+}
+
+/// Partition `colors` by the DNF complexity of the update function given by `rows`, by going
+/// through all distinct functions of the variable present in `colors`.
+/// Returns a vector where the `i`-th item is the BDD of colors with score `i`.
+fn partition_by_dnf(
+    graph: &SymbolicAsyncGraph,
+    regulator_vars: &[BddVariable],
+    rows: &[Bdd],
+    colors: &Bdd,
+    metric: ComplexityMetric,
+) -> Vec<Bdd> {
+    let mut partition: Vec<Bdd> = Vec::new();
+    let mut remaining = colors.clone();
+    while !remaining.is_false() {
+        // pick some candidate, and take all remaining candidates with the same function
+        let witness = graph
+            .mk_empty_colors()
+            .copy(remaining.clone())
+            .pick_singleton();
+        let table: Vec<bool> = rows
+            .iter()
+            .map(|row| !row.and(witness.as_bdd()).is_false())
+            .collect();
+        let mut same_function = remaining.clone();
+        for (row_bdd, value) in rows.iter().zip(&table) {
+            same_function = if *value {
+                same_function.and(row_bdd)
+            } else {
+                same_function.and_not(row_bdd)
+            };
+        }
+        remaining = remaining.and_not(&same_function);
+
+        let (num_clauses, num_literals) =
+            dnf_complexity(graph.symbolic_context(), regulator_vars, &table);
+        let score = match metric {
+            ComplexityMetric::DnfLiterals => num_literals,
+            _ => num_clauses,
+        };
+        while partition.len() <= score {
+            partition.push(Bdd::mk_false(colors.num_vars()));
+        }
+        partition[score] = partition[score].or(&same_function);
+    }
+    partition
+}
+
+/// Partition candidates given by `colors` by their complexity score (sum of complexities of their
+/// update functions w.r.t. the `metric`). The computation is symbolic, only the DNF metrics
+/// enumerate the distinct update functions of each variable (not the whole candidates).
+///
+/// Returns pairs of score and colors with that score, sorted by the score (empty classes are
+/// omitted).
+pub fn partition_by_complexity(
+    graph: &SymbolicAsyncGraph,
+    colors: &GraphColors,
+    metric: ComplexityMetric,
+) -> Result<Vec<(usize, GraphColors)>, SketchError> {
+    let bn = get_network(graph)?;
+    let empty = graph.mk_empty_colors().as_bdd().clone();
+
+    // combine partitions of individual variables one by one
+    let mut partition = vec![colors.as_bdd().clone()];
+    for var in bn.variables() {
+        let rows = mk_update_fn_rows(graph, var)?;
+        let regulator_vars: Vec<BddVariable> = bn
+            .regulators(var)
+            .into_iter()
+            .map(|r| graph.symbolic_context().get_state_variable(r))
+            .collect();
+        let var_partition = match metric {
+            ComplexityMetric::EssentialRegulators => {
+                partition_by_essential_regulators(&rows, regulator_vars.len(), colors.as_bdd())
+            }
+            _ => partition_by_dnf(graph, &regulator_vars, &rows, colors.as_bdd(), metric),
+        };
+
+        let mut combined = vec![empty.clone(); partition.len() + var_partition.len()];
+        for (score, bdd) in partition.iter().enumerate() {
+            for (var_score, var_bdd) in var_partition.iter().enumerate() {
+                let both = bdd.and(var_bdd);
+                if !both.is_false() {
+                    combined[score + var_score] = combined[score + var_score].or(&both);
+                }
+            }
+        }
+        partition = combined;
+    }
+
+    Ok(partition
+        .into_iter()
+        .enumerate()
+        .filter(|(_, bdd)| !bdd.is_false())
+        .map(|(score, bdd)| (score, colors.copy(bdd)))
+        .collect())
+}
+
+/// Select the `k` most parsimonious candidates from `colors` w.r.t. the `metric`. If `k` is zero,
+/// all candidates with the optimal score are selected.
+///
+/// Returns pairs of score and selected colors with that score, sorted by the score.
+pub fn select_most_parsimonious(
+    graph: &SymbolicAsyncGraph,
+    colors: &GraphColors,
+    metric: ComplexityMetric,
+    k: usize,
+) -> Result<Vec<(usize, GraphColors)>, SketchError> {
+    let partition = partition_by_complexity(graph, colors, metric)?;
+    if k == 0 {
+        return Ok(partition.into_iter().take(1).collect());
+    }
+
+    let mut selected = Vec::new();
+    let mut num_selected = 0.;
+    for (score, score_colors) in partition {
+        if num_selected >= k as f64 {
+            break;
+        }
+        let num_missing = k as f64 - num_selected;
+        if score_colors.approx_cardinality() <= num_missing {
+            num_selected += score_colors.approx_cardinality();
+            selected.push((score, score_colors));
+            continue;
+        }
+        // only part of the candidates with this score fits, pick them one by one
+        let mut remaining = score_colors;
+        let mut picked = graph.mk_empty_colors();
+        while num_selected < k as f64 {
+            let singleton = remaining.pick_singleton();
+            remaining = remaining.minus(&singleton);
+            picked = picked.union(&singleton);
+            num_selected += 1.;
+        }
+        selected.push((score, picked));
+    }
+    Ok(selected)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::candidate_analysis::parsimony::{
+        partition_by_complexity, select_most_parsimonious, ComplexityMetric,
+    };
+    use biodivine_hctl_model_checker::mc_utils::get_extended_symbolic_graph;
+    use biodivine_lib_param_bn::BooleanNetwork;
+
+    #[test]
+    /// Test ranking candidates by the number of essential regulators.
+    fn test_essential_regulators_ranking() {
+        let model = r"
+v_1 -> v_2
+v_2 -| v_1
+v_2 -> v_3
+v_3 -? v_3
+v_3 -| v_4
+v_4 -? v_4
+v_1 -?? v_4
+";
+        let bn = BooleanNetwork::try_from(model).unwrap();
+        let graph = get_extended_symbolic_graph(&bn, 1).unwrap();
+        let colors = graph.mk_unit_colors();
+        let metric = ComplexityMetric::EssentialRegulators;
+
+        // `v_1 -?? v_4` is not essential in 16 of 240 candidates
+        let partition = partition_by_complexity(&graph, &colors, metric).unwrap();
+        assert_eq!(partition.len(), 2);
+        assert_eq!(partition[0].0, 6);
+        assert_eq!(partition[0].1.approx_cardinality(), 16.);
+        assert_eq!(partition[1].0, 7);
+        assert_eq!(partition[1].1.approx_cardinality(), 224.);
+
+        let optimal = select_most_parsimonious(&graph, &colors, metric, 0).unwrap();
+        assert_eq!(optimal.len(), 1);
+        assert_eq!(optimal[0].1.approx_cardinality(), 16.);
+
+        let top_k = select_most_parsimonious(&graph, &colors, metric, 20).unwrap();
+        assert_eq!(top_k.len(), 2);
+        assert_eq!(top_k[0].1.approx_cardinality(), 16.);
+        assert_eq!(top_k[1].1.approx_cardinality(), 4.);
+    }
+
+    #[test]
+    /// Test ranking candidates by the complexity of DNFs of update functions.
+    fn test_dnf_ranking() {
+        // `t` is either `a & b` or `a | b`, constant functions of `a` and `b` have zero complexity
+        let model = r"
+a -> t
+b -> t
+";
+        let bn = BooleanNetwork::try_from(model).unwrap();
+        let graph = get_extended_symbolic_graph(&bn, 1).unwrap();
+        let colors = graph.mk_unit_colors();
+        assert_eq!(colors.approx_cardinality(), 8.);
+
+        let partition =
+            partition_by_complexity(&graph, &colors, ComplexityMetric::DnfClauses).unwrap();
+        assert_eq!(partition.len(), 2);
+        assert_eq!((partition[0].0, partition[1].0), (1, 2));
+        assert_eq!(partition[0].1.approx_cardinality(), 4.);
+
+        let partition =
+            partition_by_complexity(&graph, &colors, ComplexityMetric::DnfLiterals).unwrap();
+        assert_eq!(partition.len(), 1);
+        assert_eq!(partition[0].0, 2);
+        assert_eq!(partition[0].1.approx_cardinality(), 8.);
+    }
+}
@@ -0,0 +1,200 @@
+//! Contains functionality to summarize the regulations of the influence graph across all
+//! candidates - whether they are functional and what is their sign.
+
+use crate::errors::SketchError;
+use crate::utils::{get_network, mk_update_fn_true};
+
+use biodivine_lib_param_bn::symbolic_async_graph::{GraphColors, SymbolicAsyncGraph};
+use biodivine_lib_param_bn::{Monotonicity, Regulation};
+
+/// Structure to represent the summary of a single regulation across a set of candidates.
+/// Contains the regulation as specified in the sketch and the numbers of candidates in which it
+/// is functional (essential), and in which it is activating, inhibiting, or dual (both).
+#[derive(Clone, Debug, PartialEq)]
+pub struct RegulationSummary {
+    pub regulator: String,
+    pub target: String,
+    pub observable: bool,
+    pub monotonicity: Option<Monotonicity>,
+    pub num_candidates: f64,
+    pub functional: f64,
+    pub activating: f64,
+    pub inhibiting: f64,
+    pub dual: f64,
+}
+
+impl RegulationSummary {
+    /// True if the sign or the essentiality of the regulation is not fixed in the sketch
+    /// (regulations such as `-?` or `-??` in the aeon format).
+    pub fn is_unspecified(&self) -> bool {
+        !self.observable || self.monotonicity.is_none()
+    }
+
+    /// Arrow representing the regulation in the aeon format.
+    pub fn aeon_arrow(&self) -> &str {
+        match (self.monotonicity, self.observable) {
+            (Some(Monotonicity::Activation), true) => "->",
+            (Some(Monotonicity::Inhibition), true) => "-|",
+            (None, true) => "-?",
+            (Some(Monotonicity::Activation), false) => "->?",
+            (Some(Monotonicity::Inhibition), false) => "-|?",
+            (None, false) => "-??",
+        }
+    }
+}
+
+/// Compute the summary of the given regulation across candidates in `colors`.
+fn summarize_regulation(
+    graph: &SymbolicAsyncGraph,
+    colors: &GraphColors,
+    regulation: &Regulation,
+) -> Result<RegulationSummary, SketchError> {
+    let context = graph.symbolic_context();
+    let bn = get_network(graph)?;
+    let regulator_var = context.get_state_variable(regulation.get_regulator());
+
+    // evaluate the update function of the target with the regulator fixed to 0 and 1
+    let fn_true = mk_update_fn_true(graph, regulation.get_target())?;
+    let fn_with_inactive = fn_true.var_restrict(regulator_var, false);
+    let fn_with_active = fn_true.var_restrict(regulator_var, true);
+
+    // colors where there is an input on which activating the regulator increases (or decreases)
+    // the output of the update function
+    let state_vars = context.state_variables();
+    let can_increase = fn_with_active
+        .and_not(&fn_with_inactive)
+        .exists(state_vars)
+        .and(colors.as_bdd());
+    let can_decrease = fn_with_inactive
+        .and_not(&fn_with_active)
+        .exists(state_vars)
+        .and(colors.as_bdd());
+
+    let count = |bdd: &biodivine_lib_bdd::Bdd| colors.copy(bdd.clone()).approx_cardinality();
+    Ok(RegulationSummary {
+        regulator: bn.get_variable_name(regulation.get_regulator()).clone(),
+        target: bn.get_variable_name(regulation.get_target()).clone(),
+        observable: regulation.is_observable(),
+        monotonicity: regulation.get_monotonicity(),
+        num_candidates: colors.approx_cardinality(),
+        functional: count(&can_increase.or(&can_decrease)),
+        activating: count(&can_increase.and_not(&can_decrease)),
+        inhibiting: count(&can_decrease.and_not(&can_increase)),
+        dual: count(&can_increase.and(&can_decrease)),
+    })
+}
+
+/// Summarize all regulations of the influence graph across candidates given by `colors`.
+/// For each regulation, compute in how many candidates it is functional, activating, inhibiting,
+/// or dual. This is done symbolically, without enumerating the candidates.
+///
+/// Summaries are sorted by the names of the target and the regulator.
+pub fn summarize_regulations(
+    graph: &SymbolicAsyncGraph,
+    colors: &GraphColors,
+) -> Result<Vec<RegulationSummary>, SketchError> {
+    let bn = get_network(graph)?;
+    let mut summaries = bn
+        .as_graph()
+        .regulations()
+        .map(|regulation| summarize_regulation(graph, colors, regulation))
+        .collect::<Result<Vec<RegulationSummary>, SketchError>>()?;
+    summaries.sort_by(|x, y| (&x.target, &x.regulator).cmp(&(&y.target, &y.regulator)));
+    Ok(summaries)
+}
+
+/// Print the summary of all regulations across candidates given by `colors`.
+/// Regulations unspecified in the sketch (unknown sign or essentiality) are listed first.
+pub fn print_regulation_summary(
+    graph: &SymbolicAsyncGraph,
+    colors: &GraphColors,
+) -> Result<(), SketchError> {
+    let summaries = summarize_regulations(graph, colors)?;
+    let (unspecified, specified): (Vec<_>, Vec<_>) =
+        summaries.into_iter().partition(|s| s.is_unspecified());
+
+    let print_summary = |summary: &RegulationSummary| {
+        let percent = |x: f64| {
+            if summary.num_candidates == 0. {
+                0.
+            } else {
+                100. * x / summary.num_candidates
+            }
+        };
+        println!(
+            "{} {} {}: functional {:.1}% (activating {:.1}%, inhibiting {:.1}%, dual {:.1}%)",
+            summary.regulator,
+            summary.aeon_arrow(),
+            summary.target,
+            percent(summary.functional),
+            percent(summary.activating),
+            percent(summary.inhibiting),
+            percent(summary.dual),
+        );
+    };
+
+    println!(
+        "{} regulations unspecified in the sketch:",
+        unspecified.len()
+    );
+    unspecified.iter().for_each(print_summary);
+    println!();
+    println!("{} regulations specified in the sketch:", specified.len());
+    specified.iter().for_each(print_summary);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::candidate_analysis::regulations::summarize_regulations;
+    use biodivine_hctl_model_checker::mc_utils::get_extended_symbolic_graph;
+    use biodivine_lib_param_bn::BooleanNetwork;
+
+    const TEST_MODEL: &str = r"
+v_1 -> v_2
+v_2 -| v_1
+v_2 -> v_3
+v_3 -? v_3
+v_3 -| v_4
+v_4 -? v_4
+v_1 -?? v_4
+";
+
+    #[test]
+    /// Test summarizing regulations across all candidates (results computed by enumeration).
+    fn test_summarize_regulations() {
+        let bn = BooleanNetwork::try_from(TEST_MODEL).unwrap();
+        let graph = get_extended_symbolic_graph(&bn, 1).unwrap();
+        let colors = graph.mk_unit_colors();
+        assert_eq!(colors.approx_cardinality(), 240.);
+
+        let summaries = summarize_regulations(&graph, &colors).unwrap();
+        assert_eq!(summaries.len(), 7);
+
+        // regulations are sorted by target, so `v_3 -? v_3` is the fourth one
+        let self_loop = &summaries[3];
+        assert_eq!(
+            (self_loop.regulator.as_str(), self_loop.target.as_str()),
+            ("v_3", "v_3")
+        );
+        assert_eq!(self_loop.aeon_arrow(), "-?");
+        assert_eq!(self_loop.functional, 240.);
+        assert_eq!(self_loop.activating, 120.);
+        assert_eq!(self_loop.inhibiting, 120.);
+        assert_eq!(self_loop.dual, 0.);
+
+        let non_observable = &summaries[4];
+        assert_eq!(non_observable.regulator, "v_1");
+        assert_eq!(non_observable.aeon_arrow(), "-??");
+        assert!(non_observable.is_unspecified());
+        assert_eq!(non_observable.functional, 224.);
+        assert_eq!(non_observable.activating, 80.);
+        assert_eq!(non_observable.inhibiting, 80.);
+        assert_eq!(non_observable.dual, 64.);
+
+        let inhibition = &summaries[5];
+        assert_eq!(inhibition.regulator, "v_3");
+        assert!(!inhibition.is_unspecified());
+        assert_eq!(inhibition.inhibiting, 240.);
+    }
+}
@@ -3,14 +3,177 @@
 //!
 //! Many properties can be encoded in more than a one way, so we include more variants. Some of
 //! them are created in a way that model-checking computation can be optimised.
+//!
+//! All generators validate their inputs (see `validate_state_formula`) and return an error
+//! instead of producing a malformed formula. Propositions can be additionally checked against
+//! the network by `validate_state_propositions`.
+
+use crate::errors::SketchError;
+
+use std::collections::HashMap;
+
+/// HCTL keywords that can appear in state formulae as alphanumeric tokens (and thus must not be
+/// treated as propositions).
+const HCTL_KEYWORDS: [&str; 16] = [
+    "true", "True", "false", "False", "EX", "AX", "EF", "AF", "EG", "AG", "EU", "AU", "EW", "AW",
+    "E", "A",
+];
+
+/// Split `formula` by the `separator` char, ignoring separators nested in parentheses.
+pub(crate) fn split_top_level(formula: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in formula.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ if c == separator && depth == 0 => {
+                parts.push(&formula[start..i]);
+                start = i + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    parts.push(&formula[start..]);
+    parts
+}
+
+/// Remove whitespace and parentheses enclosing the whole `formula`.
+pub(crate) fn strip_enclosing_parentheses(formula: &str) -> &str {
+    let mut formula = formula.trim();
+    while formula.starts_with('(') && formula.ends_with(')') {
+        // the first parenthesis must be closed by the last char
+        let mut depth = 0;
+        let closing_idx = formula.char_indices().find_map(|(i, c)| {
+            match c {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                _ => {}
+            }
+            (depth == 0).then_some(i)
+        });
+        if closing_idx != Some(formula.len() - 1) {
+            break;
+        }
+        formula = formula[1..formula.len() - 1].trim();
+    }
+    formula
+}
+
+/// If the `formula` is a literal (possibly negated proposition), return the proposition and
+/// its polarity.
+pub(crate) fn parse_literal(formula: &str) -> Option<(&str, bool)> {
+    let formula = strip_enclosing_parentheses(formula);
+    let (prop, positive) = match formula.strip_prefix('~') {
+        Some(negated) => (strip_enclosing_parentheses(negated), false),
+        None => (formula, true),
+    };
+    let is_identifier = !prop.is_empty()
+        && prop.chars().all(|c| c.is_alphanumeric() || c == '_')
+        && !HCTL_KEYWORDS.contains(&prop);
+    if is_identifier {
+        Some((prop, positive))
+    } else {
+        None
+    }
+}
+
+/// Collect all propositions in a `formula` (alphanumeric tokens that are not HCTL keywords,
+/// HCTL variables in `{}`, or wild-card propositions in `%%`).
+fn collect_propositions(formula: &str) -> Vec<&str> {
+    let mut propositions = Vec::new();
+    let mut token_start: Option<usize> = None;
+    let mut closing_char: Option<char> = None;
+    for (i, c) in formula.char_indices().chain([(formula.len(), ' ')]) {
+        if let Some(closing) = closing_char {
+            if c == closing {
+                closing_char = None;
+            }
+            continue;
+        }
+        if c.is_alphanumeric() || c == '_' {
+            token_start.get_or_insert(i);
+            continue;
+        }
+        if let Some(start) = token_start.take() {
+            let token = &formula[start..i];
+            let is_number = token.chars().all(|c| c.is_ascii_digit());
+            if !is_number && !HCTL_KEYWORDS.contains(&token) {
+                propositions.push(token);
+            }
+        }
+        match c {
+            '{' => closing_char = Some('}'),
+            '%' => closing_char = Some('%'),
+            _ => {}
+        }
+    }
+    propositions
+}
+
+/// Check that `state` is a valid formula describing a (partial) state - it must be non-empty
+/// and, if it is a conjunction, it must not contain conflicting literals (such as `a & ~a`).
+pub fn validate_state_formula(state: &str) -> Result<(), SketchError> {
+    if strip_enclosing_parentheses(state).is_empty() {
+        return Err(SketchError::InvalidFormula(
+            "State formula can't be empty.".to_string(),
+        ));
+    }
+
+    let mut literals: HashMap<&str, bool> = HashMap::new();
+    for conjunct in split_top_level(strip_enclosing_parentheses(state), '&') {
+        if strip_enclosing_parentheses(conjunct).is_empty() {
+            return Err(SketchError::InvalidFormula(format!(
+                "State formula `{state}` contains an empty conjunct."
+            )));
+        }
+        if let Some((prop, positive)) = parse_literal(conjunct) {
+            if literals
+                .insert(prop, positive)
+                .is_some_and(|p| p != positive)
+            {
+                return Err(SketchError::InvalidFormula(format!(
+                    "State formula `{state}` contains conflicting literals of `{prop}`."
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Check that all propositions used in the `state` formula are among `prop_names`.
+pub fn validate_state_propositions(state: &str, prop_names: &[String]) -> Result<(), SketchError> {
+    match collect_propositions(state)
+        .into_iter()
+        .find(|prop| !prop_names.iter().any(|name| name == prop))
+    {
+        Some(prop) => Err(SketchError::UnknownVariable(prop.to_string())),
+        None => Ok(()),
+    }
+}
+
+/// Validate a non-empty set of state formulae (each one by `validate_state_formula`).
+fn validate_state_formula_set(states: &[String]) -> Result<(), SketchError> {
+    if states.is_empty() {
+        return Err(SketchError::InvalidFormula(
+            "Set of state formulae can't be empty.".to_string(),
+        ));
+    }
+    states
+        .iter()
+        .try_for_each(|state| validate_state_formula(state))
+}
 
 /// Create a formula describing the existence of a attractor containing specific state.
 ///
 /// Works only for FULLY described state (conjunction of literals for each proposition).
 /// Param `attractor_state` is a formula describing a state in a desired attractor.
-pub fn mk_formula_attractor_specific(attractor_state: String) -> String {
-    assert!(!attractor_state.is_empty());
-    format!("(3{{x}}: (@{{x}}: ({attractor_state} & (AG EF ({attractor_state})))))")
+pub fn mk_formula_attractor_specific(attractor_state: String) -> Result<String, SketchError> {
+    validate_state_formula(&attractor_state)?;
+    Ok(format!(
+        "(3{{x}}: (@{{x}}: ({attractor_state} & (AG EF ({attractor_state})))))"
+    ))
 }
 
 /// Create a formula describing the existence of a attractor containing partially specified state.
@@ -21,9 +184,11 @@ pub fn mk_formula_attractor_specific(attractor_state: String) -> String {
 /// computation.
 ///
 /// Param `attractor_state` is a formula describing a (partial) state in a desired attractor.
-pub fn mk_formula_attractor_aeon(attractor_state: String) -> String {
-    assert!(!attractor_state.is_empty());
-    format!("(3{{x}}: (@{{x}}: ({attractor_state} & (!{{y}}: AG EF {{y}}))))")
+pub fn mk_formula_attractor_aeon(attractor_state: String) -> Result<String, SketchError> {
+    validate_state_formula(&attractor_state)?;
+    Ok(format!(
+        "(3{{x}}: (@{{x}}: ({attractor_state} & (!{{y}}: AG EF {{y}}))))"
+    ))
 }
 
 /// Create a formula describing the existence of a attractor containing partially specified state.
@@ -32,100 +197,104 @@ pub fn mk_formula_attractor_aeon(attractor_state: String) -> String {
 /// recommend using `mk_attractor_formula_specific`).
 ///
 /// Param `attractor_state` is a formula describing a (partial) state in a desired attractor.
-pub fn mk_formula_attractor(attractor_state: String) -> String {
-    assert!(!attractor_state.is_empty());
-    format!("(3{{x}}: (@{{x}}: ({attractor_state} & (AG EF ({attractor_state} & {{x}})))))")
+pub fn mk_formula_attractor(attractor_state: String) -> Result<String, SketchError> {
+    validate_state_formula(&attractor_state)?;
+    Ok(format!(
+        "(3{{x}}: (@{{x}}: ({attractor_state} & (AG EF ({attractor_state} & {{x}})))))"
+    ))
 }
 
 /// Create a formula ensuring the existence of a set of attractor states.
-pub fn mk_formula_attractor_set(attractor_state_set: Vec<String>) -> String {
-    assert!(!attractor_state_set.is_empty());
-    let mut formula = String::new();
-    formula.push('(');
-    for attractor_state in attractor_state_set {
-        formula.push_str(mk_formula_attractor(attractor_state).as_str());
-        formula.push_str(" & ");
-    }
-    formula = formula.strip_suffix(" & ").unwrap().to_string();
-    formula.push(')');
-    formula
+pub fn mk_formula_attractor_set(attractor_state_set: Vec<String>) -> Result<String, SketchError> {
+    validate_state_formula_set(&attractor_state_set)?;
+    let attractor_formulae = attractor_state_set
+        .into_iter()
+        .map(mk_formula_attractor)
+        .collect::<Result<Vec<String>, SketchError>>()?;
+    Ok(format!("({})", attractor_formulae.join(" & ")))
 }
 
 /// Create a formula prohibiting all attractors that do not contain specified states.
 ///
 /// Param `attractor_state_set` is a vector of formulae, each describing a state in particular
 /// allowed attractor (conjunction of literals).
-pub fn mk_formula_forbid_other_attractors(attractor_state_set: Vec<String>) -> String {
-    assert!(!attractor_state_set.is_empty());
-    let mut formula = String::new();
-    formula.push_str("~(3{x}: (@{x}: ~(AG EF (");
-    for attractor_state in attractor_state_set {
-        assert!(!attractor_state.is_empty());
-        formula.push_str(format!("({attractor_state}) | ").as_str())
-    }
-    formula = formula.strip_suffix(" | ").unwrap().to_string();
-    formula.push_str("))))");
-    formula
+pub fn mk_formula_forbid_other_attractors(
+    attractor_state_set: Vec<String>,
+) -> Result<String, SketchError> {
+    validate_state_formula_set(&attractor_state_set)?;
+    let allowed_states: Vec<String> = attractor_state_set
+        .iter()
+        .map(|attractor_state| format!("({attractor_state})"))
+        .collect();
+    Ok(format!(
+        "~(3{{x}}: (@{{x}}: ~(AG EF ({}))))",
+        allowed_states.join(" | ")
+    ))
 }
 
 /// Create a formula ensuring the existence of a set of attractor states and prohibiting any
 /// other attractors not containing these states.
-pub fn mk_formula_exclusive_attractors(attractor_state_set: Vec<String>) -> String {
+pub fn mk_formula_exclusive_attractors(
+    attractor_state_set: Vec<String>,
+) -> Result<String, SketchError> {
     // part which ensures attractor states
     let mut formula = String::new();
     for attractor_state in attractor_state_set.clone() {
-        formula.push_str(mk_formula_attractor(attractor_state).as_str());
+        formula.push_str(mk_formula_attractor(attractor_state)?.as_str());
         formula.push_str(" & ");
     }
 
     // append the sub-formula which forbids additional attractor states
-    formula.push_str(mk_formula_forbid_other_attractors(attractor_state_set).as_str());
-    formula
+    formula.push_str(mk_formula_forbid_other_attractors(attractor_state_set)?.as_str());
+    Ok(formula)
 }
 
 /// Create a formula describing the existence of a specific steady-state.
 ///
 /// Works only for FULLY described states (conjunction with a literal for each proposition).
 /// Param `steady_state` is a formula describing that particular state.
-pub fn mk_formula_fixed_point_specific(steady_state: String) -> String {
-    assert!(!steady_state.is_empty());
-    format!("(3{{x}}: (@{{x}}: ({steady_state} & (AX ({steady_state})))))")
+pub fn mk_formula_fixed_point_specific(steady_state: String) -> Result<String, SketchError> {
+    validate_state_formula(&steady_state)?;
+    Ok(format!(
+        "(3{{x}}: (@{{x}}: ({steady_state} & (AX ({steady_state})))))"
+    ))
 }
 
 /// Create a formula describing the existence of a (partially specified) steady-state.
 ///
 /// Works for both fully or partially specified described states.
 /// Param `steady_state` is a formula describing that particular state.
-pub fn mk_formula_fixed_point(steady_state: String) -> String {
-    assert!(!steady_state.is_empty());
-    format!("(3{{x}}: (@{{x}}: ({steady_state} & (AX ({steady_state} & {{x}})))))")
+pub fn mk_formula_fixed_point(steady_state: String) -> Result<String, SketchError> {
+    validate_state_formula(&steady_state)?;
+    Ok(format!(
+        "(3{{x}}: (@{{x}}: ({steady_state} & (AX ({steady_state} & {{x}})))))"
+    ))
 }
 
 /// Create a formula ensuring the existence of a set of fixed points.
-pub fn mk_formula_fixed_point_set(steady_state_set: Vec<String>) -> String {
-    let mut formula = String::new();
-    formula.push('(');
-    for steady_state in steady_state_set {
-        formula.push_str(mk_formula_fixed_point(steady_state).as_str());
-        formula.push_str(" & ");
-    }
-    formula = formula.strip_suffix(" & ").unwrap().to_string();
-    formula.push(')');
-    formula
+pub fn mk_formula_fixed_point_set(steady_state_set: Vec<String>) -> Result<String, SketchError> {
+    validate_state_formula_set(&steady_state_set)?;
+    let fixed_point_formulae = steady_state_set
+        .into_iter()
+        .map(mk_formula_fixed_point)
+        .collect::<Result<Vec<String>, SketchError>>()?;
+    Ok(format!("({})", fixed_point_formulae.join(" & ")))
 }
 
 /// Create a formula prohibiting all but the given states to be fixed-points.
 ///
 /// Param `steady_state_set` is a vector of formulae, each describing particular allowed state.
-pub fn mk_formula_forbid_other_fixed_points(steady_state_set: Vec<String>) -> String {
+pub fn mk_formula_forbid_other_fixed_points(
+    steady_state_set: Vec<String>,
+) -> Result<String, SketchError> {
+    validate_state_formula_set(&steady_state_set)?;
     let mut formula = String::new();
     formula.push_str("~(3{x}: (@{x}: ");
     for steady_state in steady_state_set {
-        assert!(!steady_state.is_empty());
         formula.push_str(format!("~({steady_state}) & ").as_str())
     }
     formula.push_str("(AX {x})))");
-    formula
+    Ok(formula)
 }
 
 /// Create a formula ensuring the existence of a set of fixed points and prohibiting all other
@@ -133,17 +302,19 @@ pub fn mk_formula_forbid_other_fixed_points(steady_state_set: Vec<String>) -> St
 ///
 /// This formula is build in a way that uses advantage of model-checkers cashing (for "AX x").
 /// Param `steady_state_set` is a vector of formulae, each describing one state.
-pub fn mk_formula_exclusive_fixed_points(steady_state_set: Vec<String>) -> String {
+pub fn mk_formula_exclusive_fixed_points(
+    steady_state_set: Vec<String>,
+) -> Result<String, SketchError> {
     // part which ensures steady states
     let mut formula = String::new();
     for steady_state in steady_state_set.clone() {
-        formula.push_str(mk_formula_fixed_point(steady_state).as_str());
+        formula.push_str(mk_formula_fixed_point(steady_state)?.as_str());
         formula.push_str(" & ");
     }
 
     // append the sub-formula which forbids additional steady states
-    formula.push_str(mk_formula_forbid_other_fixed_points(steady_state_set).as_str());
-    formula
+    formula.push_str(mk_formula_forbid_other_fixed_points(steady_state_set)?.as_str());
+    Ok(formula)
 }
 
 /// Create a formula describing the (non)existence of reachability between two (partial) states.
@@ -154,44 +325,42 @@ pub fn mk_formula_reachability_pair(
     from_state: String,
     to_state: String,
     is_negative: bool,
-) -> String {
-    assert!(!to_state.is_empty() && !from_state.is_empty());
+) -> Result<String, SketchError> {
+    validate_state_formula(&from_state)?;
+    validate_state_formula(&to_state)?;
     if is_negative {
-        return format!("(3{{x}}: (@{{x}}: {from_state} & (~EF ({to_state}))))");
+        return Ok(format!(
+            "(3{{x}}: (@{{x}}: {from_state} & (~EF ({to_state}))))"
+        ));
     }
-    format!("(3{{x}}: (@{{x}}: {from_state} & EF ({to_state})))")
+    Ok(format!(
+        "(3{{x}}: (@{{x}}: {from_state} & EF ({to_state})))"
+    ))
 }
 
 /// Create a formula describing the existence of reachability between every two consecutive states
 /// from the `states_sequence`, starting with the first one.
 ///
 /// Basically can be used to describe a time series s0 -> s1 -> ... -> sN
-pub fn mk_formula_reachability_chain(states_sequence: Vec<String>) -> String {
+pub fn mk_formula_reachability_chain(states_sequence: Vec<String>) -> Result<String, SketchError> {
+    validate_state_formula_set(&states_sequence)?;
+    let num_states = states_sequence.len();
     let mut formula = String::new();
     formula.push_str("(3{x}: (@{x}: ");
-    let num_states = states_sequence.len();
-    for (n, state) in states_sequence.iter().enumerate() {
-        assert!(!state.is_empty());
-        if n == num_states - 1 {
-            break;
-        }
+    for state in &states_sequence[..num_states - 1] {
         formula.push_str(format!("({state}) & EF (").as_str())
     }
 
     // add the last state and all the closing parentheses
-    formula.push_str(states_sequence[num_states - 1].to_string().as_str());
-    formula.push_str(
-        (0..num_states + 1)
-            .map(|_| ")")
-            .collect::<String>()
-            .as_str(),
-    );
-    formula
+    formula.push_str(states_sequence[num_states - 1].as_str());
+    formula.push_str(")".repeat(num_states + 1).as_str());
+    Ok(formula)
 }
 
 #[cfg(test)]
 mod tests {
     use crate::data_processing::create_inference_formulae::*;
+    use crate::errors::SketchError;
 
     #[test]
     /// Test generating of different kinds of general attractor formulae.
@@ -199,27 +368,27 @@ mod tests {
         let attr_states = vec!["a & b & ~c".to_string(), "a & b & c".to_string()];
 
         assert_eq!(
-            mk_formula_attractor_specific(attr_states[0].clone()),
+            mk_formula_attractor_specific(attr_states[0].clone()).unwrap(),
             "(3{x}: (@{x}: (a & b & ~c & (AG EF (a & b & ~c)))))".to_string(),
         );
         assert_eq!(
-            mk_formula_attractor_aeon(attr_states[0].clone()),
+            mk_formula_attractor_aeon(attr_states[0].clone()).unwrap(),
             "(3{x}: (@{x}: (a & b & ~c & (!{y}: AG EF {y}))))".to_string(),
         );
         assert_eq!(
-            mk_formula_attractor(attr_states[0].clone()),
+            mk_formula_attractor(attr_states[0].clone()).unwrap(),
             "(3{x}: (@{x}: (a & b & ~c & (AG EF (a & b & ~c & {x})))))".to_string(),
         );
         assert_eq!(
-            mk_formula_forbid_other_attractors(attr_states.clone()),
+            mk_formula_forbid_other_attractors(attr_states.clone()).unwrap(),
             "~(3{x}: (@{x}: ~(AG EF ((a & b & ~c) | (a & b & c)))))".to_string(),
         );
         assert_eq!(
-            mk_formula_attractor_set(attr_states.clone()),
+            mk_formula_attractor_set(attr_states.clone()).unwrap(),
             "((3{x}: (@{x}: (a & b & ~c & (AG EF (a & b & ~c & {x}))))) & (3{x}: (@{x}: (a & b & c & (AG EF (a & b & c & {x}))))))".to_string(),
         );
         assert_eq!(
-            mk_formula_exclusive_attractors(attr_states.clone()),
+            mk_formula_exclusive_attractors(attr_states.clone()).unwrap(),
             "(3{x}: (@{x}: (a & b & ~c & (AG EF (a & b & ~c & {x}))))) & (3{x}: (@{x}: (a & b & c & (AG EF (a & b & c & {x}))))) & ~(3{x}: (@{x}: ~(AG EF ((a & b & ~c) | (a & b & c)))))".to_string(),
         );
     }
@@ -230,23 +399,23 @@ mod tests {
         let attr_states = vec!["a & b & ~c".to_string(), "a & b & c".to_string()];
 
         assert_eq!(
-            mk_formula_fixed_point_specific(attr_states[0].clone()),
+            mk_formula_fixed_point_specific(attr_states[0].clone()).unwrap(),
             "(3{x}: (@{x}: (a & b & ~c & (AX (a & b & ~c)))))".to_string(),
         );
         assert_eq!(
-            mk_formula_fixed_point(attr_states[0].clone()),
+            mk_formula_fixed_point(attr_states[0].clone()).unwrap(),
             "(3{x}: (@{x}: (a & b & ~c & (AX (a & b & ~c & {x})))))".to_string(),
         );
         assert_eq!(
-            mk_formula_forbid_other_fixed_points(attr_states.clone()),
+            mk_formula_forbid_other_fixed_points(attr_states.clone()).unwrap(),
             "~(3{x}: (@{x}: ~(a & b & ~c) & ~(a & b & c) & (AX {x})))".to_string(),
         );
         assert_eq!(
-            mk_formula_fixed_point_set(attr_states.clone()),
+            mk_formula_fixed_point_set(attr_states.clone()).unwrap(),
             "((3{x}: (@{x}: (a & b & ~c & (AX (a & b & ~c & {x}))))) & (3{x}: (@{x}: (a & b & c & (AX (a & b & c & {x}))))))".to_string(),
         );
         assert_eq!(
-            mk_formula_exclusive_fixed_points(attr_states.clone()),
+            mk_formula_exclusive_fixed_points(attr_states.clone()).unwrap(),
             "(3{x}: (@{x}: (a & b & ~c & (AX (a & b & ~c & {x}))))) & (3{x}: (@{x}: (a & b & c & (AX (a & b & c & {x}))))) & ~(3{x}: (@{x}: ~(a & b & ~c) & ~(a & b & c) & (AX {x})))".to_string(),
         );
     }
@@ -261,16 +430,52 @@ mod tests {
         ];
 
         assert_eq!(
-            mk_formula_reachability_pair(states[0].clone(), states[1].clone(), true),
+            mk_formula_reachability_pair(states[0].clone(), states[1].clone(), true).unwrap(),
             "(3{x}: (@{x}: a & b & ~c & (~EF (a & b & c))))".to_string(),
         );
         assert_eq!(
-            mk_formula_reachability_pair(states[0].clone(), states[1].clone(), false),
+            mk_formula_reachability_pair(states[0].clone(), states[1].clone(), false).unwrap(),
             "(3{x}: (@{x}: a & b & ~c & EF (a & b & c)))".to_string(),
         );
         assert_eq!(
-            mk_formula_reachability_chain(states),
+            mk_formula_reachability_chain(states).unwrap(),
             "(3{x}: (@{x}: (a & b & ~c) & EF ((a & b & c) & EF (~a & b & c))))".to_string(),
         );
     }
+
+    #[test]
+    /// Test that generators return errors for invalid inputs (instead of panicking).
+    fn test_invalid_inputs() {
+        assert!(mk_formula_reachability_chain(vec![]).is_err());
+        assert!(mk_formula_attractor_set(vec![]).is_err());
+        assert!(mk_formula_forbid_other_fixed_points(vec![]).is_err());
+        assert!(mk_formula_attractor_specific("".to_string()).is_err());
+        assert!(mk_formula_fixed_point("( )".to_string()).is_err());
+        assert!(mk_formula_attractor("a & & b".to_string()).is_err());
+
+        // conflicting literals
+        let conflicting_state = "(a & b & ~a)".to_string();
+        assert!(mk_formula_attractor(conflicting_state.clone()).is_err());
+        let states = vec!["a & b".to_string(), conflicting_state];
+        assert!(mk_formula_forbid_other_attractors(states.clone()).is_err());
+        assert!(mk_formula_reachability_chain(states).is_err());
+
+        // formulae that are not conjunctions of literals are allowed
+        assert!(validate_state_formula("(a | ~a) & EF (b & c)").is_ok());
+        assert!(validate_state_formula("a & ~(b & ~b)").is_ok());
+        assert!(mk_formula_reachability_chain(vec!["~a".to_string()]).is_ok());
+    }
+
+    #[test]
+    /// Test validating propositions of state formulae against the network variables.
+    fn test_validate_propositions() {
+        let prop_names = vec!["a".to_string(), "b_1".to_string()];
+        assert!(validate_state_propositions("a & ~b_1", &prop_names).is_ok());
+        assert!(validate_state_propositions("(3{x}: @{x}: AG EF (a & {x}))", &prop_names).is_ok());
+        assert!(validate_state_propositions("true & %a_s% & ~(b_1 | a)", &prop_names).is_ok());
+        assert_eq!(
+            validate_state_propositions("a & ~b_2", &prop_names),
+            Err(SketchError::UnknownVariable("b_2".to_string()))
+        );
+    }
 }
@@ -1,29 +1,30 @@
 use crate::data_processing::create_inference_formulae::*;
+use crate::data_processing::data_validation::{validate_observations, UnknownColumns};
 use crate::data_processing::observations::*;
+use crate::errors::SketchError;
+
+use biodivine_lib_param_bn::BooleanNetwork;
 
 /// Encode binarized observation with a formula depicting the corresponding state/sub-space.
 /// Using binarized values and proposition names, creates a conjunction of literals
 /// describing that observation.
 pub fn encode_observation(observation: &Observation, prop_names: &[String]) -> String {
-    let mut formula = String::new();
-    formula.push('(');
-
-    for (i, prop) in prop_names.iter().enumerate() {
-        match observation.values[i] {
-            VarValue::True => formula.push_str(format!("{prop} & ").as_str()),
-            VarValue::False => formula.push_str(format!("~{prop} & ").as_str()),
-            VarValue::Any => (),
-        }
-    }
+    let literals: Vec<String> = prop_names
+        .iter()
+        .zip(&observation.values)
+        .filter_map(|(prop, value)| match value {
+            VarValue::True => Some(prop.clone()),
+            VarValue::False => Some(format!("~{prop}")),
+            VarValue::Any => None,
+        })
+        .collect();
 
     // formula might be 'empty' if all props can have arbitrary values - corresponding to 'true'
-    if formula.len() == 1 {
-        formula.push_str("true");
+    if literals.is_empty() {
+        "(true)".to_string()
     } else {
-        formula = formula.strip_suffix(" & ").unwrap().to_string();
+        format!("({})", literals.join(" & "))
     }
-    formula.push(')');
-    formula
 }
 
 /// Encode several observation vectors with conjunction formulae, one by one.
@@ -37,25 +38,81 @@ fn encode_multiple_observations(
         .collect()
 }
 
+/// Check that proposition names are unique and valid identifiers (so that they can be used in
+/// HCTL formulae).
+fn validate_prop_names(prop_names: &[String]) -> Result<(), SketchError> {
+    if prop_names.is_empty() {
+        return Err(SketchError::InvalidArgument(
+            "Observations must contain at least one variable.".to_string(),
+        ));
+    }
+    for (i, prop) in prop_names.iter().enumerate() {
+        if prop.is_empty() || !prop.chars().all(|c| c.is_alphanumeric() || c == '_') {
+            return Err(SketchError::InvalidFormula(format!(
+                "`{prop}` is not a valid proposition name."
+            )));
+        }
+        if prop_names[..i].contains(prop) {
+            return Err(SketchError::InvalidFormula(format!(
+                "Proposition `{prop}` is used more than once."
+            )));
+        }
+    }
+    Ok(())
+}
+
 /// Encode (ordered) set of observations to a single HCTL formula. The particular formula
 /// template is chosen depending on the type of data.
-pub fn encode_observation_list_hctl(observation_list: ObservationList) -> Result<String, String> {
+pub fn encode_observation_list_hctl(
+    observation_list: ObservationList,
+) -> Result<String, SketchError> {
+    let num_vars = observation_list.var_names.len();
+    if let Some(observation) = observation_list
+        .observations
+        .iter()
+        .find(|o| o.values.len() != num_vars)
+    {
+        return Err(SketchError::parse(format!(
+            "Observation '{observation}' has invalid length."
+        )));
+    }
+    validate_prop_names(&observation_list.var_names)?;
+
     let encoded_observations =
         encode_multiple_observations(&observation_list.observations, &observation_list.var_names);
     match observation_list.data_type {
-        ObservationType::Attractor => Ok(mk_formula_attractor_set(encoded_observations)),
-        ObservationType::FixedPoint => Ok(mk_formula_fixed_point_set(encoded_observations)),
-        ObservationType::TimeSeries => Ok(mk_formula_reachability_chain(encoded_observations)),
-        ObservationType::Unspecified => Err("Cannot encode data with unspecified type".to_string()),
+        ObservationType::Attractor => mk_formula_attractor_set(encoded_observations),
+        ObservationType::FixedPoint => mk_formula_fixed_point_set(encoded_observations),
+        ObservationType::TimeSeries => mk_formula_reachability_chain(encoded_observations),
+        ObservationType::Unspecified => Err(SketchError::InvalidArgument(
+            "Cannot encode data with unspecified type".to_string(),
+        )),
     }
 }
 
+/// Validate variables of the observations against the network `bn` (see
+/// `validate_observations`), and encode the observations to a single HCTL formula.
+///
+/// Unknown dataset columns are either reported as an error, or dropped, depending on
+/// `unknown_columns`.
+pub fn encode_observation_list_for_network(
+    observation_list: ObservationList,
+    bn: &BooleanNetwork,
+    unknown_columns: UnknownColumns,
+) -> Result<String, SketchError> {
+    let observation_list = validate_observations(observation_list, bn, unknown_columns)?;
+    encode_observation_list_hctl(observation_list)
+}
+
 #[cfg(test)]
 mod tests {
     use crate::data_processing::data_encoding::{
-        encode_multiple_observations, encode_observation, encode_observation_list_hctl,
+        encode_multiple_observations, encode_observation, encode_observation_list_for_network,
+        encode_observation_list_hctl,
     };
+    use crate::data_processing::data_validation::UnknownColumns;
     use crate::data_processing::observations::{Observation, ObservationList, ObservationType};
+    use biodivine_lib_param_bn::BooleanNetwork;
 
     #[test]
     /// Test encoding of an observation.
@@ -134,4 +191,50 @@ mod tests {
         );
         assert!(encode_observation_list_hctl(unspecified_observations).is_err());
     }
+
+    #[test]
+    /// Test that invalid observation lists are reported before any formula is produced.
+    fn test_invalid_observations_encoding() {
+        let observation = Observation::try_from_str("10".to_string()).unwrap();
+        let prop_names = vec!["a".to_string(), "b".to_string()];
+
+        let empty_observations =
+            ObservationList::new(vec![], prop_names.clone(), ObservationType::TimeSeries);
+        assert!(encode_observation_list_hctl(empty_observations).is_err());
+
+        let duplicate_props = vec!["a".to_string(), "a".to_string()];
+        let observations = ObservationList::new(
+            vec![observation.clone()],
+            duplicate_props,
+            ObservationType::Attractor,
+        );
+        assert!(encode_observation_list_hctl(observations).is_err());
+
+        let invalid_props = vec!["a".to_string(), "b c".to_string()];
+        let observations = ObservationList::new(
+            vec![observation],
+            invalid_props,
+            ObservationType::FixedPoint,
+        );
+        assert!(encode_observation_list_hctl(observations).is_err());
+    }
+
+    #[test]
+    /// Test encoding observations validated against a network.
+    fn test_observations_encoding_for_network() {
+        let bn = BooleanNetwork::try_from("a -> b\nb -| a").unwrap();
+        let observation = Observation::try_from_str("101".to_string()).unwrap();
+        let prop_names = vec!["a".to_string(), "bb".to_string(), "b".to_string()];
+        let observations =
+            ObservationList::new(vec![observation], prop_names, ObservationType::FixedPoint);
+
+        let result =
+            encode_observation_list_for_network(observations.clone(), &bn, UnknownColumns::Reject);
+        assert!(result.is_err());
+        assert_eq!(
+            encode_observation_list_for_network(observations, &bn, UnknownColumns::Project)
+                .unwrap(),
+            "((3{x}: (@{x}: ((a & b) & (AX ((a & b) & {x}))))))".to_string(),
+        );
+    }
 }
@@ -1,17 +1,21 @@
 //! Contains functionality to load (or directly encode) binarized data from files.
 
 use crate::data_processing::observations::{Observation, ObservationList, ObservationType};
+use crate::errors::SketchError;
 use std::fs::read_to_string;
 
+/// Raw content of a data file - observation strings with their line numbers, string with var
+/// names, and string with data type.
+type RawObservations = (Vec<(usize, String)>, String, String);
+
 /// Read the data observations from the specified file. Ignore lines starting with `#` (comments).
 /// This function does not validate the inputs.
-/// Returns vector of trimmed observation strings, string with var names and string with data type.
-fn load_raw_observations(data_path: &str) -> Result<(Vec<String>, String, String), String> {
-    let Ok(data_file_string) = read_to_string(data_path) else {
-        return Err("Cannot read content of the specified file.".to_string());
-    };
+/// Returns vector of trimmed observation strings (with their line numbers), string with var names
+/// and string with data type.
+fn load_raw_observations(data_path: &str) -> Result<RawObservations, SketchError> {
+    let data_file_string = read_to_string(data_path).map_err(|e| SketchError::io(data_path, e))?;
 
-    let mut observation_strings: Vec<String> = Vec::new();
+    let mut observation_strings: Vec<(usize, String)> = Vec::new();
     let mut var_name_string = String::new();
     let mut data_type_string = String::new();
     for (i, line) in data_file_string.lines().enumerate() {
@@ -25,25 +29,42 @@ fn load_raw_observations(data_path: &str) -> Result<(Vec<String>, String, String
             continue;
         }
         if !trimmed_line.is_empty() && !trimmed_line.starts_with('#') {
-            observation_strings.push(trimmed_line.to_string());
+            observation_strings.push((i + 1, trimmed_line.to_string()));
         }
     }
     Ok((observation_strings, var_name_string, data_type_string))
 }
 
-/// Parse observations from their string representation.
+/// Add the location (the `line` of the `file`) to a parse error, if the data come from a file.
+fn locate(error: SketchError, file: Option<&str>, line: Option<usize>) -> SketchError {
+    match file {
+        Some(_) => error.with_location(file, line),
+        None => error,
+    }
+}
+
+/// Parse observations from their string representation (given with their line numbers).
 /// The observations must only characters '1', '0' or '-' (for missing).
-/// All observations must be strings of the same length (where length is the number of variables).
-fn parse_observations(observation_strings: Vec<String>) -> Result<Vec<Observation>, String> {
+/// All observations must be strings of length `num_vars`.
+/// If the observations come from a `file`, errors carry their location.
+fn parse_observations(
+    observation_strings: Vec<(usize, String)>,
+    num_vars: usize,
+    file: Option<&str>,
+) -> Result<Vec<Observation>, SketchError> {
     let mut observations: Vec<Observation> = Vec::new();
-    for observation_string in observation_strings {
-        match Observation::try_from_str(observation_string) {
-            Ok(observation) => observations.push(observation),
-            Err(e) => return Err(e),
+    for (line, observation_string) in observation_strings {
+        let observation = Observation::try_from_str(observation_string)
+            .map_err(|e| locate(e, file, Some(line)))?;
+        if observation.values.len() != num_vars {
+            let message = format!("Observation '{observation}' has invalid length.");
+            return Err(locate(SketchError::parse(message), file, Some(line)));
         }
+        observations.push(observation);
     }
     if observations.is_empty() {
-        return Err("No observations provided".to_string());
+        let error = SketchError::parse("No observations provided");
+        return Err(locate(error, file, None));
     }
 
     Ok(observations)
@@ -56,7 +77,7 @@ fn is_valid_in_name(c: char) -> bool {
 
 /// Tokenize and parse variable names from the single string representation.
 /// The string must contain only valid variable names delimited by '|'.
-fn parse_var_names(var_string: String) -> Result<Vec<String>, String> {
+fn parse_var_names(var_string: String) -> Result<Vec<String>, SketchError> {
     let mut var_names: Vec<String> = Vec::new();
     let mut input_chars = var_string.chars().peekable();
     let mut parsed_something = false;
@@ -65,7 +86,7 @@ fn parse_var_names(var_string: String) -> Result<Vec<String>, String> {
             c if is_valid_in_name(c) => {
                 // if we already parsed a name after previous delimiter, there must have been space
                 if parsed_something {
-                    return Err("Variable name can't contain spaces.".to_string());
+                    return Err(SketchError::parse("Variable name can't contain spaces."));
                 }
 
                 let mut name = String::new();
@@ -86,47 +107,49 @@ fn parse_var_names(var_string: String) -> Result<Vec<String>, String> {
                 // delimiters
                 // in case that 2 delimiters follow without any var in between
                 if !parsed_something {
-                    return Err("Variable name can't be empty.".to_string());
+                    return Err(SketchError::parse("Variable name can't be empty."));
                 }
                 parsed_something = false;
             }
-            _ => return Err(format!("Unexpected char '{c}' in variable name.")),
+            _ => {
+                return Err(SketchError::parse(format!(
+                    "Unexpected char '{c}' in variable name."
+                )))
+            }
         }
     }
 
     if var_names.is_empty() {
-        return Err("No variable names provided.".to_string());
+        return Err(SketchError::parse("No variable names provided."));
     }
     Ok(var_names)
 }
 
 /// Parse type of the data. Must be one of the valid options.
-fn parse_observ_type(type_string: String) -> Result<ObservationType, String> {
+fn parse_observ_type(type_string: String) -> Result<ObservationType, SketchError> {
     match type_string.as_str() {
         "Attractor" => Ok(ObservationType::Attractor),
         "FixedPoint" => Ok(ObservationType::FixedPoint),
         "TimeSeries" => Ok(ObservationType::TimeSeries),
         "Unspecified" => Ok(ObservationType::Unspecified),
-        _ => Err(format!("Invalid data type \"{type_string}\"")),
+        _ => Err(SketchError::parse(format!(
+            "Invalid data type \"{type_string}\""
+        ))),
     }
 }
 
 /// Process and combine strings for individual components of the `ObservationList` struct.
-pub fn generate_observation_list(
-    raw_observation_strs: Vec<String>,
+/// Observation strings are given with their line numbers. If the data come from a `file`, parse
+/// errors carry their location (variable names are on its first line, data type on the second).
+fn parse_observation_list(
+    numbered_observation_strs: Vec<(usize, String)>,
     raw_vars_str: String,
     raw_type_str: String,
-) -> Result<ObservationList, String> {
-    let observations = parse_observations(raw_observation_strs)?;
-    let var_names = parse_var_names(raw_vars_str)?;
-    let observation_type = parse_observ_type(raw_type_str)?;
-
-    let num_vars = var_names.len();
-    for observation in &observations {
-        if observation.values.len() != num_vars {
-            return Err(format!("Observation '{observation}' has invalid length."));
-        }
-    }
+    file: Option<&str>,
+) -> Result<ObservationList, SketchError> {
+    let var_names = parse_var_names(raw_vars_str).map_err(|e| locate(e, file, Some(1)))?;
+    let observation_type = parse_observ_type(raw_type_str).map_err(|e| locate(e, file, Some(2)))?;
+    let observations = parse_observations(numbered_observation_strs, var_names.len(), file)?;
     Ok(ObservationList::new(
         observations,
         var_names,
@@ -134,24 +157,38 @@ pub fn generate_observation_list(
     ))
 }
 
+/// Process and combine strings for individual components of the `ObservationList` struct.
+pub fn generate_observation_list(
+    raw_observation_strs: Vec<String>,
+    raw_vars_str: String,
+    raw_type_str: String,
+) -> Result<ObservationList, SketchError> {
+    let numbered_observation_strs = raw_observation_strs.into_iter().enumerate().collect();
+    parse_observation_list(numbered_observation_strs, raw_vars_str, raw_type_str, None)
+}
+
 /// Read the data observations from the specified file. Ignore lines starting with `#` (comments).
 /// The first line must contain variable names in order, delimited by '|'.
 /// The second line must contain valid string for observation type (above) or "Unspecified".
 /// The observations can contain only characters '1', '0' or '-' (for missing).
 /// All observations must be strings of the same length (where length is the number of variables).
-pub fn load_observations(data_path: &str) -> Result<ObservationList, String> {
-    let loaded_data = load_raw_observations(data_path);
-    if loaded_data.is_err() {
-        return Err("Unable to load observations from given file.".to_string());
-    }
-    let (raw_observation_strs, raw_vars_str, raw_type_str) = loaded_data.unwrap();
-    generate_observation_list(raw_observation_strs, raw_vars_str, raw_type_str)
+///
+/// Parse errors carry the location (file and line) of the problem.
+pub fn load_observations(data_path: &str) -> Result<ObservationList, SketchError> {
+    let (raw_observations, raw_vars_str, raw_type_str) = load_raw_observations(data_path)?;
+    parse_observation_list(
+        raw_observations,
+        raw_vars_str,
+        raw_type_str,
+        Some(data_path),
+    )
 }
 
 #[cfg(test)]
 mod tests {
     use crate::data_processing::data_loading::{
-        generate_observation_list, parse_observ_type, parse_observations, parse_var_names,
+        generate_observation_list, load_observations, parse_observ_type, parse_observations,
+        parse_var_names,
     };
     use crate::data_processing::observations::{
         Observation, ObservationList, ObservationType, VarValue,
@@ -173,7 +210,7 @@ mod tests {
         let res = parse_var_names(var_name_str);
         assert!(res.is_err());
         assert_eq!(
-            res.err().unwrap(),
+            res.err().unwrap().to_string(),
             "Variable name can't be empty.".to_string()
         );
 
@@ -181,7 +218,7 @@ mod tests {
         let res = parse_var_names(var_name_str);
         assert!(res.is_err());
         assert_eq!(
-            res.err().unwrap(),
+            res.err().unwrap().to_string(),
             "No variable names provided.".to_string()
         );
 
@@ -189,7 +226,7 @@ mod tests {
         let res = parse_var_names(var_name_str);
         assert!(res.is_err());
         assert_eq!(
-            res.err().unwrap(),
+            res.err().unwrap().to_string(),
             "Unexpected char '&' in variable name.".to_string()
         );
 
@@ -197,7 +234,7 @@ mod tests {
         let res = parse_var_names(var_name_str);
         assert!(res.is_err());
         assert_eq!(
-            res.err().unwrap(),
+            res.err().unwrap().to_string(),
             "Variable name can't contain spaces.".to_string()
         );
     }
@@ -233,22 +270,31 @@ mod tests {
     #[test]
     /// Test parsing of observations.
     fn test_observations_parsing() {
-        let observation_strings = vec!["000".to_string(), "0--".to_string(), "1-1".to_string()];
+        let numbered = |strings: &[&str]| -> Vec<(usize, String)> {
+            strings.iter().map(|s| s.to_string()).enumerate().collect()
+        };
+        let observation_strings = numbered(&["000", "0--", "1-1"]);
         let expected = vec![
             Observation::new(vec![VarValue::False, VarValue::False, VarValue::False]),
             Observation::new(vec![VarValue::False, VarValue::Any, VarValue::Any]),
             Observation::new(vec![VarValue::True, VarValue::Any, VarValue::True]),
         ];
-        assert_eq!(parse_observations(observation_strings).unwrap(), expected);
+        assert_eq!(
+            parse_observations(observation_strings, 3, None).unwrap(),
+            expected
+        );
 
         let observation_strings = vec![];
-        assert!(parse_observations(observation_strings).is_err());
+        assert!(parse_observations(observation_strings, 3, None).is_err());
+
+        let observation_strings = numbered(&["0i0"]);
+        assert!(parse_observations(observation_strings, 3, None).is_err());
 
-        let observation_strings = vec!["0i0".to_string()];
-        assert!(parse_observations(observation_strings).is_err());
+        let observation_strings = numbered(&["000", ""]);
+        assert!(parse_observations(observation_strings, 3, None).is_err());
 
-        let observation_strings = vec!["000".to_string(), "".to_string()];
-        assert!(parse_observations(observation_strings).is_err());
+        let observation_strings = numbered(&["000", "00"]);
+        assert!(parse_observations(observation_strings, 3, None).is_err());
     }
 
     #[test]
@@ -279,8 +325,36 @@ mod tests {
         );
         assert!(invalid.is_err());
         assert_eq!(
-            invalid.err().unwrap(),
+            invalid.err().unwrap().to_string(),
             "Observation '01' has invalid length.".to_string()
         );
     }
+
+    #[test]
+    /// Test loading observations from a file, with errors reporting their location.
+    fn test_load_observations() {
+        let data_path = std::env::temp_dir().join("sketches_test_load_observations.txt");
+        let data_path = data_path.to_str().unwrap();
+
+        std::fs::write(data_path, "a | b\nAttractor\n# comment\n01\n1-\n").unwrap();
+        let observation_list = load_observations(data_path).unwrap();
+        assert_eq!(observation_list.observations.len(), 2);
+
+        std::fs::write(data_path, "a | b\nAttractor\n01\n\n011\n").unwrap();
+        let err = load_observations(data_path).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            format!("{data_path}:5: Observation '011' has invalid length.")
+        );
+
+        std::fs::write(data_path, "a | b\nSteadyState\n01\n").unwrap();
+        let err = load_observations(data_path).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            format!("{data_path}:2: Invalid data type \"SteadyState\"")
+        );
+
+        std::fs::remove_file(data_path).unwrap();
+        assert!(load_observations(data_path).is_err());
+    }
 }
@@ -0,0 +1,263 @@
+//! Contains functionality to validate observations against the network (variables of the
+//! dataset must correspond to variables of the model).
+//!
+//! The validation is only available through the library (see `encode_observation_list_for_network`
+//! and `load_observations`) - none of the binaries loads datasets in this format, they take the
+//! observed states as HCTL state formulae instead.
+
+use crate::data_processing::observations::{Observation, ObservationList};
+use crate::errors::SketchError;
+
+use biodivine_lib_param_bn::BooleanNetwork;
+
+use std::fmt;
+
+/// Enum of possible ways to handle dataset columns (variables) that are not in the network.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum UnknownColumns {
+    /// Report unknown variables as an error.
+    Reject,
+    /// Project the observations onto variables of the network (unknown columns are dropped).
+    Project,
+}
+
+/// Structure summarizing the comparison of observation variables with network variables.
+///
+/// Unknown variables are accompanied by the closest network variable names (if there are some
+/// similar enough), to help with spotting typos.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct VariableReport {
+    pub unknown: Vec<(String, Vec<String>)>,
+    pub duplicate: Vec<String>,
+    pub missing: Vec<String>,
+}
+
+impl VariableReport {
+    /// Check whether the observation variables correspond exactly to the network variables.
+    pub fn is_exact_match(&self) -> bool {
+        self.unknown.is_empty() && self.duplicate.is_empty() && self.missing.is_empty()
+    }
+}
+
+impl fmt::Display for VariableReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut lines: Vec<String> = Vec::new();
+        for (name, suggestions) in &self.unknown {
+            if suggestions.is_empty() {
+                lines.push(format!("Unknown variable `{name}`."));
+            } else {
+                lines.push(format!(
+                    "Unknown variable `{name}` (did you mean `{}`?).",
+                    suggestions.join("`, `")
+                ));
+            }
+        }
+        for name in &self.duplicate {
+            lines.push(format!("Variable `{name}` is used more than once."));
+        }
+        if !self.missing.is_empty() {
+            lines.push(format!(
+                "Variables not covered by the data: {}.",
+                self.missing.join(", ")
+            ));
+        }
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+/// Compute the edit (Levenshtein) distance of two strings.
+fn edit_distance(first: &str, second: &str) -> usize {
+    let second: Vec<char> = second.chars().collect();
+    let mut previous_row: Vec<usize> = (0..=second.len()).collect();
+    for (i, c1) in first.chars().enumerate() {
+        let mut row = vec![i + 1];
+        for (j, c2) in second.iter().enumerate() {
+            let substitution = previous_row[j] + usize::from(c1 != *c2);
+            row.push(substitution.min(previous_row[j + 1] + 1).min(row[j] + 1));
+        }
+        previous_row = row;
+    }
+    previous_row[second.len()]
+}
+
+/// Find names closest to `name` (w.r.t. the case-insensitive edit distance) among the
+/// `candidates`. Only names that differ in at most a third of characters (and at least one) are
+/// considered similar enough.
+fn closest_names(name: &str, candidates: &[String]) -> Vec<String> {
+    let max_distance = (name.chars().count() / 3).max(1);
+    let distances: Vec<(usize, &String)> = candidates
+        .iter()
+        .map(|c| (edit_distance(&name.to_lowercase(), &c.to_lowercase()), c))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .collect();
+    let Some(min_distance) = distances.iter().map(|(distance, _)| *distance).min() else {
+        return Vec::new();
+    };
+    distances
+        .into_iter()
+        .filter(|(distance, _)| *distance == min_distance)
+        .map(|(_, c)| c.clone())
+        .collect()
+}
+
+/// Compare the variables of the `observation_list` with variables of the network `bn`, and
+/// report unknown (with suggestions), duplicate and missing variables.
+pub fn check_observation_variables(
+    observation_list: &ObservationList,
+    bn: &BooleanNetwork,
+) -> VariableReport {
+    let network_vars: Vec<String> = bn
+        .variables()
+        .map(|var| bn.get_variable_name(var).clone())
+        .collect();
+
+    let mut report = VariableReport::default();
+    for (i, name) in observation_list.var_names.iter().enumerate() {
+        if observation_list.var_names[..i].contains(name) {
+            if !report.duplicate.contains(name) {
+                report.duplicate.push(name.clone());
+            }
+        } else if bn.find_variable(name).is_none() {
+            report
+                .unknown
+                .push((name.clone(), closest_names(name, &network_vars)));
+        }
+    }
+    report.missing = network_vars
+        .into_iter()
+        .filter(|name| !observation_list.var_names.contains(name))
+        .collect();
+    report
+}
+
+/// Project the observations onto the given variables (other columns are dropped), keeping the
+/// original order of the columns.
+pub fn project_observations(
+    observation_list: &ObservationList,
+    var_names: &[String],
+) -> ObservationList {
+    let kept_columns: Vec<usize> = (0..observation_list.var_names.len())
+        .filter(|i| var_names.contains(&observation_list.var_names[*i]))
+        .collect();
+    let observations = observation_list
+        .observations
+        .iter()
+        .map(|o| Observation::new(kept_columns.iter().map(|i| o.values[*i].clone()).collect()))
+        .collect();
+    let var_names = kept_columns
+        .iter()
+        .map(|i| observation_list.var_names[*i].clone())
+        .collect();
+    ObservationList::new(observations, var_names, observation_list.data_type.clone())
+}
+
+/// Validate the variables of the `observation_list` against the network `bn`, so that it can be
+/// safely encoded into HCTL and evaluated on the network.
+///
+/// Duplicate variables are always an error. Unknown variables are either an error, or their
+/// columns are dropped, depending on `unknown_columns`. Network variables missing in the data are
+/// allowed (they are simply unconstrained by the observations).
+pub fn validate_observations(
+    observation_list: ObservationList,
+    bn: &BooleanNetwork,
+    unknown_columns: UnknownColumns,
+) -> Result<ObservationList, SketchError> {
+    let report = check_observation_variables(&observation_list, bn);
+    let has_unknown = !report.unknown.is_empty();
+    if !report.duplicate.is_empty() || (has_unknown && unknown_columns == UnknownColumns::Reject) {
+        // missing variables are not an error, leave them out of the message
+        let errors = VariableReport {
+            missing: Vec::new(),
+            ..report
+        };
+        return Err(SketchError::InvalidArgument(format!(
+            "Observations do not match the network:\n{errors}"
+        )));
+    }
+
+    if !has_unknown {
+        return Ok(observation_list);
+    }
+    let network_vars: Vec<String> = bn
+        .variables()
+        .map(|var| bn.get_variable_name(var).clone())
+        .collect();
+    let projected_list = project_observations(&observation_list, &network_vars);
+    if projected_list.var_names.is_empty() {
+        return Err(SketchError::InvalidArgument(
+            "No variable of the observations is in the network.".to_string(),
+        ));
+    }
+    Ok(projected_list)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data_processing::data_loading::generate_observation_list;
+    use crate::data_processing::data_validation::{
+        check_observation_variables, closest_names, edit_distance, validate_observations,
+        UnknownColumns,
+    };
+    use biodivine_lib_param_bn::BooleanNetwork;
+
+    #[test]
+    /// Test computing edit distances and suggesting closest names.
+    fn test_closest_names() {
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("CtrA", "CtrA"), 0);
+
+        let names = vec!["CtrAb".to_string(), "CtrA".to_string(), "DivK".to_string()];
+        assert_eq!(closest_names("ctra", &names), vec!["CtrA".to_string()]);
+        assert_eq!(closest_names("DivJ", &names), vec!["DivK".to_string()]);
+        assert!(closest_names("PleC", &names).is_empty());
+    }
+
+    #[test]
+    /// Test validating observation variables against a network.
+    fn test_validate_observations() {
+        let bn = BooleanNetwork::try_from("CtrA -> DivK\nDivK -| CtrA\nDivK -> PleC").unwrap();
+        let observations = generate_observation_list(
+            vec!["0110".to_string(), "1-01".to_string()],
+            "CtrA | DivJ | x | DivK".to_string(),
+            "Attractor".to_string(),
+        )
+        .unwrap();
+
+        let report = check_observation_variables(&observations, &bn);
+        assert_eq!(
+            report.unknown,
+            vec![
+                ("DivJ".to_string(), vec!["DivK".to_string()]),
+                ("x".to_string(), vec![]),
+            ]
+        );
+        assert!(report.duplicate.is_empty());
+        assert_eq!(report.missing, vec!["PleC".to_string()]);
+        assert!(!report.is_exact_match());
+
+        let err = validate_observations(observations.clone(), &bn, UnknownColumns::Reject)
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("did you mean `DivK`?"));
+
+        let projected = validate_observations(observations, &bn, UnknownColumns::Project).unwrap();
+        assert_eq!(
+            projected.var_names,
+            vec!["CtrA".to_string(), "DivK".to_string()]
+        );
+        assert_eq!(projected.observations[0].to_string(), "00");
+        assert_eq!(projected.observations[1].to_string(), "11");
+
+        // duplicates are reported regardless of the handling of unknown columns
+        let observations = generate_observation_list(
+            vec!["01".to_string()],
+            "CtrA | CtrA".to_string(),
+            "FixedPoint".to_string(),
+        )
+        .unwrap();
+        let report = check_observation_variables(&observations, &bn);
+        assert_eq!(report.duplicate, vec!["CtrA".to_string()]);
+        assert!(validate_observations(observations, &bn, UnknownColumns::Project).is_err());
+    }
+}
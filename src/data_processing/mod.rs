@@ -4,4 +4,5 @@
 pub mod create_inference_formulae;
 pub mod data_encoding;
 pub mod data_loading;
+pub mod data_validation;
 pub mod observations;
@@ -1,5 +1,7 @@
 //! Structs to represent observations and their data values.
 
+use crate::errors::SketchError;
+
 use std::fmt;
 
 /// Enum of possible values for variables in each observation (binary + unknown).
@@ -33,18 +35,22 @@ impl Observation {
     }
 
     /// Create observation object from string encoding its values.
-    pub fn try_from_str(observation_string: String) -> Result<Self, String> {
+    pub fn try_from_str(observation_string: String) -> Result<Self, SketchError> {
         let mut observation_vec: Vec<VarValue> = Vec::new();
         for c in observation_string.chars() {
             match c {
                 '1' => observation_vec.push(VarValue::True),
                 '0' => observation_vec.push(VarValue::False),
                 '-' => observation_vec.push(VarValue::Any),
-                _ => return Err(format!("Unexpected char '{c}' in an observation.")),
+                _ => {
+                    return Err(SketchError::parse(format!(
+                        "Unexpected char '{c}' in an observation."
+                    )))
+                }
             }
         }
         if observation_vec.is_empty() {
-            return Err("Observation can't be empty.".to_string());
+            return Err(SketchError::parse("Observation can't be empty."));
         }
 
         Ok(Self {
@@ -109,15 +115,11 @@ impl ObservationList {
 impl fmt::Display for ObservationList {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let mut format_string = format!(
-            "{} {} observations with vars [",
+            "{} {} observations with vars [{}]: \n",
             self.observations.len(),
             self.data_type,
+            self.var_names.join(", "),
         );
-        for variable in &self.var_names {
-            format_string.push_str(format!("{variable}, ").as_str());
-        }
-        format_string = format_string.strip_suffix(", ").unwrap().to_string();
-        format_string.push_str("]: \n");
         for observation in &self.observations {
             format_string.push_str(format!("> {observation}\n").as_str());
         }
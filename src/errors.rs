@@ -0,0 +1,136 @@
+//! Contains the error type shared by all functionality of the library.
+
+use std::error::Error;
+use std::fmt;
+
+/// Location of a problem in an input file (file path and line number, both optional).
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Location {
+    pub file: Option<String>,
+    pub line: Option<usize>,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (&self.file, self.line) {
+            (Some(file), Some(line)) => write!(f, "{file}:{line}"),
+            (Some(file), None) => write!(f, "{file}"),
+            (None, Some(line)) => write!(f, "line {line}"),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+/// Enum of errors that can occur during the inference and the analysis of its results.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum SketchError {
+    /// Failure to read or write a file.
+    Io { path: String, message: String },
+    /// Failure to parse an input (model, dataset, annotation, argument), possibly with the
+    /// location of the problem.
+    Parse {
+        message: String,
+        location: Option<Location>,
+    },
+    /// Variable that is not present in the network.
+    UnknownVariable(String),
+    /// Invalid (HCTL) formula, or invalid inputs for generating a formula.
+    InvalidFormula(String),
+    /// Failure to create or modify the symbolic representation of a network or its parts.
+    SymbolicEncoding(String),
+    /// Failure during the model checking.
+    ModelChecking(String),
+    /// Invalid argument of a computation (such as an empty set of candidates).
+    InvalidArgument(String),
+    /// Computation was interrupted (e.g., cancelled) before finishing.
+    Interrupted(String),
+}
+
+impl SketchError {
+    /// Create a parse error without a location.
+    pub fn parse(message: impl Into<String>) -> Self {
+        SketchError::Parse {
+            message: message.into(),
+            location: None,
+        }
+    }
+
+    /// Create a parse error located at the given line of the given file.
+    pub fn parse_at(message: impl Into<String>, file: Option<&str>, line: Option<usize>) -> Self {
+        SketchError::Parse {
+            message: message.into(),
+            location: Some(Location {
+                file: file.map(|f| f.to_string()),
+                line,
+            }),
+        }
+    }
+
+    /// Create an I/O error regarding the file at `path`.
+    pub fn io(path: &str, message: impl fmt::Display) -> Self {
+        SketchError::Io {
+            path: path.to_string(),
+            message: message.to_string(),
+        }
+    }
+
+    /// Add a location to a parse error (if it does not have one already). Other errors are
+    /// returned unchanged.
+    pub fn with_location(self, file: Option<&str>, line: Option<usize>) -> Self {
+        match self {
+            SketchError::Parse {
+                message,
+                location: None,
+            } => SketchError::parse_at(message, file, line),
+            other => other,
+        }
+    }
+}
+
+impl fmt::Display for SketchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SketchError::Io { path, message } => write!(f, "Cannot access `{path}`: {message}"),
+            SketchError::Parse {
+                message,
+                location: Some(location),
+            } => write!(f, "{location}: {message}"),
+            SketchError::Parse {
+                message,
+                location: None,
+            } => write!(f, "{message}"),
+            SketchError::UnknownVariable(name) => write!(f, "Unknown variable `{name}`."),
+            SketchError::InvalidFormula(message) => write!(f, "Invalid formula: {message}"),
+            SketchError::SymbolicEncoding(message) => write!(f, "{message}"),
+            SketchError::ModelChecking(message) => write!(f, "Model checking failed: {message}"),
+            SketchError::InvalidArgument(message) => write!(f, "{message}"),
+            SketchError::Interrupted(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl Error for SketchError {}
+
+#[cfg(test)]
+mod tests {
+    use crate::errors::SketchError;
+
+    #[test]
+    /// Test displaying errors (with and without locations).
+    fn test_error_display() {
+        let error = SketchError::parse("Unexpected char 'x' in an observation.");
+        assert_eq!(error.to_string(), "Unexpected char 'x' in an observation.");
+
+        let error = error.with_location(Some("data.csv"), Some(3));
+        assert_eq!(
+            error.to_string(),
+            "data.csv:3: Unexpected char 'x' in an observation."
+        );
+
+        let error = SketchError::UnknownVariable("v_5".to_string());
+        assert_eq!(
+            error.with_location(None, Some(1)).to_string(),
+            "Unknown variable `v_5`."
+        );
+    }
+}
@@ -0,0 +1,317 @@
+//! Contains functionality for the design of experiments that discriminate between candidates.
+//!
+//! Each experiment is a yes/no question (such as "is variable `v` active in some attractor?")
+//! that splits the candidate set into networks predicting a positive and a negative outcome.
+//! Both parts are computed symbolically for all candidates at once. The most informative
+//! experiments are those that split the candidate set most evenly.
+
+use crate::data_processing::create_inference_formulae::{
+    mk_formula_attractor, mk_formula_reachability_pair,
+};
+use crate::errors::SketchError;
+use crate::perturbations::mk_perturbed_graph;
+use crate::utils::get_network;
+
+use biodivine_hctl_model_checker::model_checking::model_check_formula_dirty;
+
+use biodivine_lib_param_bn::biodivine_std::traits::Set;
+use biodivine_lib_param_bn::symbolic_async_graph::{
+    GraphColoredVertices, GraphColors, SymbolicAsyncGraph,
+};
+use biodivine_lib_param_bn::BooleanNetwork;
+
+use std::fmt;
+
+/// Enum of possible kinds of (binary) experiments.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum Experiment {
+    /// Measure the variable in attractor states. The outcome is positive if the variable is
+    /// active in some attractor state.
+    AttractorMeasurement(String),
+    /// Knock out the variable and observe the phenotype (formula describing a partial state).
+    /// The outcome is positive if there is an attractor exhibiting the phenotype.
+    KnockoutPhenotype { variable: String, phenotype: String },
+    /// Test whether a state given by the second formula is reachable from a state given by the
+    /// first formula.
+    Reachability {
+        from_state: String,
+        to_state: String,
+    },
+}
+
+impl fmt::Display for Experiment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Experiment::AttractorMeasurement(var) => {
+                write!(f, "measure `{var}` in attractors")
+            }
+            Experiment::KnockoutPhenotype {
+                variable,
+                phenotype,
+            } => write!(f, "knock out `{variable}` and observe `{phenotype}`"),
+            Experiment::Reachability {
+                from_state,
+                to_state,
+            } => write!(f, "test reachability from `{from_state}` to `{to_state}`"),
+        }
+    }
+}
+
+/// Structure to represent the predicted outcome of an experiment for all candidates.
+/// Contains the experiment and the sets of candidates predicting the positive and the negative
+/// outcome.
+#[derive(Clone, Debug)]
+pub struct ExperimentPrediction {
+    pub experiment: Experiment,
+    pub positive: GraphColors,
+    pub negative: GraphColors,
+}
+
+impl ExperimentPrediction {
+    /// Number of candidates predicting the positive outcome.
+    pub fn positive_count(&self) -> f64 {
+        self.positive.approx_cardinality()
+    }
+
+    /// Number of candidates predicting the negative outcome.
+    pub fn negative_count(&self) -> f64 {
+        self.negative.approx_cardinality()
+    }
+
+    /// Fraction of candidates that is surely eliminated by the experiment, regardless of its
+    /// outcome. The value ranges from 0 (experiment gives no information) to 0.5 (even split).
+    pub fn split_ratio(&self) -> f64 {
+        let (positive, negative) = (self.positive_count(), self.negative_count());
+        if positive + negative == 0. {
+            return 0.;
+        }
+        positive.min(negative) / (positive + negative)
+    }
+}
+
+impl fmt::Display for ExperimentPrediction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} positive / {} negative candidates",
+            self.experiment,
+            self.positive_count(),
+            self.negative_count(),
+        )
+    }
+}
+
+/// Compute the colored set of attractor states of the `graph`.
+/// The graph must have at least one symbolic HCTL variable.
+fn compute_attractor_states(
+    graph: &SymbolicAsyncGraph,
+) -> Result<GraphColoredVertices, SketchError> {
+    model_check_formula_dirty("!{x}: AG EF {x}", graph).map_err(SketchError::ModelChecking)
+}
+
+/// Compute the set of candidates predicting the positive outcome of the `experiment`.
+/// Attractor states of the graph are only needed for attractor measurements.
+fn compute_positive_colors(
+    graph: &SymbolicAsyncGraph,
+    experiment: &Experiment,
+    attractor_states: Option<&GraphColoredVertices>,
+) -> Result<GraphColors, SketchError> {
+    let bn = get_network(graph)?;
+    let find_variable = |name: &String| {
+        bn.find_variable(name)
+            .ok_or(SketchError::UnknownVariable(name.clone()))
+    };
+
+    match experiment {
+        Experiment::AttractorMeasurement(var_name) => {
+            let var = find_variable(var_name)?;
+            let attractor_states = match attractor_states {
+                Some(states) => states.clone(),
+                None => compute_attractor_states(graph)?,
+            };
+            Ok(attractor_states
+                .intersect(&graph.fix_network_variable(var, true))
+                .colors())
+        }
+        Experiment::KnockoutPhenotype {
+            variable,
+            phenotype,
+        } => {
+            let var = find_variable(variable)?;
+            let perturbed_graph = mk_perturbed_graph(graph, &[(var, false)])?;
+            let formula = mk_formula_attractor(phenotype.clone())?;
+            let result = model_check_formula_dirty(&formula, &perturbed_graph)
+                .map_err(SketchError::ModelChecking)?;
+            Ok(result.colors())
+        }
+        Experiment::Reachability {
+            from_state,
+            to_state,
+        } => {
+            let formula =
+                mk_formula_reachability_pair(from_state.clone(), to_state.clone(), false)?;
+            let result =
+                model_check_formula_dirty(&formula, graph).map_err(SketchError::ModelChecking)?;
+            Ok(result.colors())
+        }
+    }
+}
+
+/// Predict the outcome of the `experiment` for all candidates (unit colors) of the `graph`.
+///
+/// The graph must have at least one symbolic HCTL variable (see `get_extended_symbolic_graph`).
+pub fn predict_experiment_outcome(
+    graph: &SymbolicAsyncGraph,
+    experiment: Experiment,
+) -> Result<ExperimentPrediction, SketchError> {
+    let positive =
+        compute_positive_colors(graph, &experiment, None)?.intersect(graph.unit_colors());
+    let negative = graph.mk_unit_colors().minus(&positive);
+    Ok(ExperimentPrediction {
+        experiment,
+        positive,
+        negative,
+    })
+}
+
+/// Predict outcomes of all `experiments` for the candidates (unit colors) of the `graph`, and
+/// sort them from the most discriminating ones (splitting the candidate set most evenly).
+///
+/// The graph must have at least one symbolic HCTL variable (see `get_extended_symbolic_graph`).
+pub fn rank_experiments(
+    graph: &SymbolicAsyncGraph,
+    experiments: Vec<Experiment>,
+) -> Result<Vec<ExperimentPrediction>, SketchError> {
+    // attractors are shared by all measurement experiments, so compute them only once
+    let needs_attractors = experiments
+        .iter()
+        .any(|e| matches!(e, Experiment::AttractorMeasurement(_)));
+    let attractor_states = if needs_attractors {
+        Some(compute_attractor_states(graph)?)
+    } else {
+        None
+    };
+
+    let mut predictions = Vec::with_capacity(experiments.len());
+    for experiment in experiments {
+        let positive = compute_positive_colors(graph, &experiment, attractor_states.as_ref())?
+            .intersect(graph.unit_colors());
+        let negative = graph.mk_unit_colors().minus(&positive);
+        predictions.push(ExperimentPrediction {
+            experiment,
+            positive,
+            negative,
+        });
+    }
+
+    // stable sort, so that equally good experiments keep their original order
+    predictions.sort_by(|x, y| y.split_ratio().total_cmp(&x.split_ratio()));
+    Ok(predictions)
+}
+
+/// Generate the default list of experiments for the network - measurement of each variable in
+/// attractors, and, if `knockout_phenotype` is given, a knockout of each variable observing
+/// that phenotype. Moreover, reachability is tested between each (ordered) pair of the given
+/// `reachability_states` (formulae describing partial states).
+pub fn mk_default_experiments(
+    bn: &BooleanNetwork,
+    knockout_phenotype: Option<&str>,
+    reachability_states: &[String],
+) -> Vec<Experiment> {
+    let mut experiments: Vec<Experiment> = bn
+        .variables()
+        .map(|v| Experiment::AttractorMeasurement(bn.get_variable_name(v).clone()))
+        .collect();
+    if let Some(phenotype) = knockout_phenotype {
+        experiments.extend(bn.variables().map(|v| Experiment::KnockoutPhenotype {
+            variable: bn.get_variable_name(v).clone(),
+            phenotype: phenotype.to_string(),
+        }));
+    }
+    for from_state in reachability_states {
+        for to_state in reachability_states.iter().filter(|s| *s != from_state) {
+            experiments.push(Experiment::Reachability {
+                from_state: from_state.clone(),
+                to_state: to_state.clone(),
+            });
+        }
+    }
+    experiments
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::experiment_design::{
+        mk_default_experiments, predict_experiment_outcome, rank_experiments, Experiment,
+    };
+    use biodivine_hctl_model_checker::mc_utils::get_extended_symbolic_graph;
+    use biodivine_lib_param_bn::BooleanNetwork;
+
+    const TEST_MODEL: &str = r"
+v_1 -> v_2
+v_2 -| v_1
+v_2 -> v_3
+v_3 -? v_3
+v_3 -| v_4
+v_4 -? v_4
+";
+
+    #[test]
+    /// Test predictions of individual experiments (results computed by enumeration).
+    fn test_experiment_predictions() {
+        let bn = BooleanNetwork::try_from(TEST_MODEL).unwrap();
+        let graph = get_extended_symbolic_graph(&bn, 1).unwrap();
+        assert_eq!(graph.mk_unit_colors().approx_cardinality(), 16.);
+
+        let experiment = Experiment::AttractorMeasurement("v_3".to_string());
+        let prediction = predict_experiment_outcome(&graph, experiment).unwrap();
+        assert_eq!(prediction.positive_count(), 12.);
+        assert_eq!(prediction.negative_count(), 4.);
+        assert_eq!(prediction.split_ratio(), 0.25);
+
+        let experiment = Experiment::KnockoutPhenotype {
+            variable: "v_1".to_string(),
+            phenotype: "v_4".to_string(),
+        };
+        let prediction = predict_experiment_outcome(&graph, experiment).unwrap();
+        assert_eq!(prediction.positive_count(), 15.);
+
+        let experiment = Experiment::Reachability {
+            from_state: "~v_3 & ~v_4".to_string(),
+            to_state: "v_3 & v_4".to_string(),
+        };
+        let prediction = predict_experiment_outcome(&graph, experiment).unwrap();
+        assert_eq!(prediction.positive_count(), 9.);
+        assert_eq!(prediction.negative_count(), 7.);
+
+        let experiment = Experiment::AttractorMeasurement("v_5".to_string());
+        assert!(predict_experiment_outcome(&graph, experiment).is_err());
+    }
+
+    #[test]
+    /// Test ranking of the default experiments.
+    fn test_rank_experiments() {
+        let bn = BooleanNetwork::try_from(TEST_MODEL).unwrap();
+        let graph = get_extended_symbolic_graph(&bn, 1).unwrap();
+
+        let reachability_states = vec!["~v_3 & ~v_4".to_string(), "v_3 & v_4".to_string()];
+        let experiments = mk_default_experiments(&bn, None, &reachability_states);
+        assert_eq!(experiments.len(), 6);
+        assert_eq!(
+            experiments[4],
+            Experiment::Reachability {
+                from_state: "~v_3 & ~v_4".to_string(),
+                to_state: "v_3 & v_4".to_string(),
+            }
+        );
+
+        let experiments = mk_default_experiments(&bn, Some("v_4"), &[]);
+        assert_eq!(experiments.len(), 8);
+        let ranking = rank_experiments(&graph, experiments).unwrap();
+        assert_eq!(ranking.len(), 8);
+        // measurements of `v_3` and `v_4` split the candidates 12:4, knockout of `v_4` does not
+        assert_eq!(ranking[0].split_ratio(), 0.25);
+        assert_eq!(ranking[1].split_ratio(), 0.25);
+        assert_eq!(ranking[7].split_ratio(), 0.);
+    }
+}
@@ -0,0 +1,610 @@
+//! Contains functionality to explain why a specific (fully specified) network is excluded from
+//! the set of candidates - which properties it violates, together with concrete counterexamples.
+//!
+//! Counterexamples are computed for the kinds of properties generated from data (attractors,
+//! fixed points, prohibition of additional attractors or fixed points, and time series), which
+//! are recognised in the property formulae. Other properties are only reported as violated.
+
+use crate::data_processing::create_inference_formulae::*;
+use crate::errors::SketchError;
+use crate::utils::mk_restricted_graph;
+
+use biodivine_hctl_model_checker::model_checking::model_check_formula_dirty;
+
+use biodivine_lib_param_bn::biodivine_std::traits::Set;
+use biodivine_lib_param_bn::symbolic_async_graph::{
+    GraphColoredVertices, GraphColors, SymbolicAsyncGraph,
+};
+use biodivine_lib_param_bn::{BooleanNetwork, VariableId};
+
+use std::fmt;
+
+/// Kinds of properties (generated from data) for which counterexamples can be computed.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum DynamicProperty {
+    /// Existence of an attractor containing the state.
+    Attractor(String),
+    /// Existence of the fixed point.
+    FixedPoint(String),
+    /// Absence of attractors that do not contain any of the states.
+    ForbidOtherAttractors(Vec<String>),
+    /// Absence of fixed points other than the given states.
+    ForbidOtherFixedPoints(Vec<String>),
+    /// Reachability of the sequence of states (time series).
+    TimeSeries(Vec<String>),
+}
+
+impl DynamicProperty {
+    /// Generate the HCTL formula of the property (see `create_inference_formulae`).
+    pub fn to_formula(&self) -> Result<String, SketchError> {
+        match self {
+            DynamicProperty::Attractor(state) => mk_formula_attractor(state.clone()),
+            DynamicProperty::FixedPoint(state) => mk_formula_fixed_point(state.clone()),
+            DynamicProperty::ForbidOtherAttractors(states) => {
+                mk_formula_forbid_other_attractors(states.clone())
+            }
+            DynamicProperty::ForbidOtherFixedPoints(states) => {
+                mk_formula_forbid_other_fixed_points(states.clone())
+            }
+            DynamicProperty::TimeSeries(states) => mk_formula_reachability_chain(states.clone()),
+        }
+    }
+}
+
+/// Remove all whitespace from the formula.
+fn normalize(formula: &str) -> String {
+    formula.chars().filter(|c| !c.is_whitespace()).collect()
+}
+
+/// Add spaces around conjunctions of a normalized state formula (for readable messages).
+fn denormalize(state: &str) -> String {
+    state.replace('&', " & ")
+}
+
+/// Parse the sequence of states of a reachability chain `(S1)&EF((S2)&EF(S3)))` (normalized,
+/// without the prefix with the quantifier).
+fn parse_reachability_chain(mut chain: &str) -> Option<Vec<String>> {
+    let mut states = Vec::new();
+    while let Some(rest) = chain.strip_prefix('(') {
+        let idx = rest.find(")&EF(")?;
+        states.push(denormalize(&rest[..idx]));
+        chain = &rest[idx + 5..];
+    }
+    states.push(denormalize(chain.trim_end_matches(')')));
+    Some(states).filter(|states| states.len() > 1)
+}
+
+/// Recognise which kind of property (generated from data) the `formula` expresses.
+///
+/// States are extracted from the formula by its generator's template, and the formula generated
+/// from them must match the original (up to whitespace), so that no formula is misinterpreted.
+/// Both the general and the specific variants of attractor and fixed-point formulae are
+/// recognised.
+pub fn recognise_property(formula: &str) -> Option<DynamicProperty> {
+    let formula = normalize(formula);
+    let mut candidates = Vec::new();
+    if let Some(rest) = formula.strip_prefix("(3{x}:(@{x}:(") {
+        if let Some(idx) = rest.find("&(A") {
+            let state = denormalize(&rest[..idx]);
+            candidates.push((
+                DynamicProperty::Attractor(state.clone()),
+                mk_formula_attractor_specific(state.clone()).ok(),
+            ));
+            candidates.push((
+                DynamicProperty::FixedPoint(state.clone()),
+                mk_formula_fixed_point_specific(state).ok(),
+            ));
+        }
+    }
+    if let Some(inner) = formula
+        .strip_prefix("~(3{x}:(@{x}:~(AGEF(")
+        .and_then(|rest| rest.strip_suffix(")))))"))
+    {
+        let states: Vec<String> = split_top_level(inner, '|')
+            .into_iter()
+            .map(|state| denormalize(strip_enclosing_parentheses(state)))
+            .collect();
+        candidates.push((DynamicProperty::ForbidOtherAttractors(states), None));
+    }
+    if let Some(inner) = formula
+        .strip_prefix("~(3{x}:(@{x}:")
+        .and_then(|rest| rest.strip_suffix("&(AX{x})))"))
+    {
+        let states: Option<Vec<String>> = split_top_level(inner, '&')
+            .into_iter()
+            .map(|state| {
+                Some(denormalize(strip_enclosing_parentheses(
+                    state.strip_prefix('~')?,
+                )))
+            })
+            .collect();
+        if let Some(states) = states {
+            candidates.push((DynamicProperty::ForbidOtherFixedPoints(states), None));
+        }
+    }
+    if let Some(states) = formula
+        .strip_prefix("(3{x}:(@{x}:")
+        .and_then(parse_reachability_chain)
+    {
+        candidates.push((DynamicProperty::TimeSeries(states), None));
+    }
+
+    // the general variant is generated by the property itself, the specific one is given above
+    candidates
+        .into_iter()
+        .find(|(property, specific_formula)| {
+            let matches = |generated: Option<String>| {
+                generated.is_some_and(|generated| normalize(&generated) == formula)
+            };
+            matches(property.to_formula().ok()) || matches(specific_formula.clone())
+        })
+        .map(|(property, _)| property)
+}
+
+/// Concrete counterexample demonstrating a violation of a property.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Counterexample {
+    /// No state of the network matches the state formula.
+    MissingState { state: String },
+    /// The state is not in an attractor - it can reach `escape_state`, from which it cannot
+    /// return.
+    NotInAttractor { state: String, escape_state: String },
+    /// The state is not a fixed point - it can move to `successor`.
+    NotFixedPoint { state: String, successor: String },
+    /// There is an additional attractor (containing the state, but none of the allowed ones).
+    ExtraAttractor { state: String },
+    /// The state is an additional fixed point.
+    ExtraFixedPoint { state: String },
+    /// The step of the time series (indexed from 1) is unreachable from the previous steps.
+    UnreachableStep { step: usize, state: String },
+}
+
+impl fmt::Display for Counterexample {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Counterexample::MissingState { state } => write!(f, "no state matches `{state}`"),
+            Counterexample::NotInAttractor {
+                state,
+                escape_state,
+            } => write!(
+                f,
+                "state `{state}` is not in an attractor - it can reach state `{escape_state}`, from which it cannot return"
+            ),
+            Counterexample::NotFixedPoint { state, successor } => write!(
+                f,
+                "state `{state}` is not a fixed point - it can move to state `{successor}`"
+            ),
+            Counterexample::ExtraAttractor { state } => write!(
+                f,
+                "there is an additional attractor containing state `{state}` (and none of the specified states)"
+            ),
+            Counterexample::ExtraFixedPoint { state } => {
+                write!(f, "state `{state}` is an additional fixed point")
+            }
+            Counterexample::UnreachableStep { step, state } => write!(
+                f,
+                "step {step} of the time series (`{state}`) is unreachable from the previous steps"
+            ),
+        }
+    }
+}
+
+/// Violation of a single named property, with counterexamples (if they could be computed).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PropertyViolation {
+    pub property_name: String,
+    pub counterexamples: Vec<Counterexample>,
+}
+
+impl fmt::Display for PropertyViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Property `{}` is violated", self.property_name)?;
+        if self.counterexamples.is_empty() {
+            return write!(
+                f,
+                " (no counterexample available for this kind of property)."
+            );
+        }
+        write!(f, ":")?;
+        for counterexample in &self.counterexamples {
+            write!(f, "\n  - {counterexample}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Explanation of why a network is excluded from the candidate set.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ExclusionReport {
+    /// Reason why the network is not an instance of the sketch at all (before any dynamic
+    /// properties are considered), if it is not.
+    pub instance_error: Option<String>,
+    pub violations: Vec<PropertyViolation>,
+}
+
+impl ExclusionReport {
+    /// Check whether the network is excluded (it is not an instance, or violates a property).
+    pub fn is_excluded(&self) -> bool {
+        self.instance_error.is_some() || !self.violations.is_empty()
+    }
+}
+
+impl fmt::Display for ExclusionReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(error) = &self.instance_error {
+            return write!(f, "Network is not an instance of the sketch: {error}");
+        }
+        if self.violations.is_empty() {
+            return write!(f, "Network satisfies all properties.");
+        }
+        let violations: Vec<String> = self.violations.iter().map(|v| v.to_string()).collect();
+        write!(f, "{}", violations.join("\n"))
+    }
+}
+
+/// Symbolic computations on the state-transition graph of a single network.
+pub(crate) struct NetworkDynamics<'a> {
+    pub(crate) graph: SymbolicAsyncGraph,
+    pub(crate) network: &'a BooleanNetwork,
+}
+
+impl NetworkDynamics<'_> {
+    /// Check whether the (closed) formula holds in the network.
+    fn holds(&self, formula: &str) -> Result<bool, SketchError> {
+        let result =
+            model_check_formula_dirty(formula, &self.graph).map_err(SketchError::ModelChecking)?;
+        Ok(!result.colors().is_empty())
+    }
+
+    /// Compute the set of states satisfying the state formula.
+    fn states(&self, state_formula: &str) -> Result<GraphColoredVertices, SketchError> {
+        let states = model_check_formula_dirty(state_formula, &self.graph)
+            .map_err(SketchError::ModelChecking)?;
+        Ok(states.intersect(self.graph.unit_colored_vertices()))
+    }
+
+    /// Compute the set of states reachable from `initial` (including them).
+    pub(crate) fn reach_forward(&self, initial: &GraphColoredVertices) -> GraphColoredVertices {
+        let mut reached = initial.clone();
+        loop {
+            let successors = self.graph.post(&reached);
+            if successors.is_subset(&reached) {
+                return reached;
+            }
+            reached = reached.union(&successors);
+        }
+    }
+
+    /// Compute the set of states that can reach `target` (including them).
+    pub(crate) fn reach_backward(&self, target: &GraphColoredVertices) -> GraphColoredVertices {
+        let mut reached = target.clone();
+        loop {
+            let predecessors = self.graph.pre(&reached);
+            if predecessors.is_subset(&reached) {
+                return reached;
+            }
+            reached = reached.union(&predecessors);
+        }
+    }
+
+    /// Pick a single state of the set. Returns the singleton set and the state's formula.
+    pub(crate) fn pick_state(
+        &self,
+        states: &GraphColoredVertices,
+    ) -> Option<(GraphColoredVertices, String)> {
+        let valuation = states.as_bdd().sat_witness()?;
+        let values: Vec<(VariableId, bool)> = self
+            .network
+            .variables()
+            .zip(self.graph.symbolic_context().state_variables())
+            .map(|(var, bdd_var)| (var, valuation.value(*bdd_var)))
+            .collect();
+        let literals: Vec<String> = values
+            .iter()
+            .map(|(var, value)| {
+                let name = self.network.get_variable_name(*var);
+                if *value {
+                    name.clone()
+                } else {
+                    format!("~{name}")
+                }
+            })
+            .collect();
+        let state = self
+            .graph
+            .mk_subspace(&values)
+            .intersect(self.graph.unit_colored_vertices());
+        Some((state, literals.join(" & ")))
+    }
+
+    /// Find a counterexample for a violated property.
+    fn find_counterexample(
+        &self,
+        property: &DynamicProperty,
+    ) -> Result<Option<Counterexample>, SketchError> {
+        let counterexample = match property {
+            DynamicProperty::Attractor(state_formula) => {
+                let Some((state, state_name)) = self.pick_state(&self.states(state_formula)?)
+                else {
+                    return Ok(Some(Counterexample::MissingState {
+                        state: state_formula.clone(),
+                    }));
+                };
+                let escape = self
+                    .reach_forward(&state)
+                    .minus(&self.reach_backward(&state));
+                self.pick_state(&escape)
+                    .map(|(_, escape_state)| Counterexample::NotInAttractor {
+                        state: state_name,
+                        escape_state,
+                    })
+            }
+            DynamicProperty::FixedPoint(state_formula) => {
+                let Some((state, state_name)) = self.pick_state(&self.states(state_formula)?)
+                else {
+                    return Ok(Some(Counterexample::MissingState {
+                        state: state_formula.clone(),
+                    }));
+                };
+                self.pick_state(&self.graph.post(&state))
+                    .map(|(_, successor)| Counterexample::NotFixedPoint {
+                        state: state_name,
+                        successor,
+                    })
+            }
+            DynamicProperty::ForbidOtherAttractors(state_formulae) => {
+                let mut allowed = self.graph.mk_empty_colored_vertices();
+                for state_formula in state_formulae {
+                    allowed = allowed.union(&self.states(state_formula)?);
+                }
+                // states that cannot reach any allowed state contain an additional attractor
+                let unit = self.graph.unit_colored_vertices();
+                let mut candidates = unit.minus(&self.reach_backward(&allowed));
+                // descend into the attractor - move to a state from which we cannot return
+                // until there is no such state
+                loop {
+                    let Some((state, state_name)) = self.pick_state(&candidates) else {
+                        break None;
+                    };
+                    let escape = self
+                        .reach_forward(&state)
+                        .minus(&self.reach_backward(&state));
+                    if escape.is_empty() {
+                        break Some(Counterexample::ExtraAttractor { state: state_name });
+                    }
+                    candidates = escape;
+                }
+            }
+            DynamicProperty::ForbidOtherFixedPoints(state_formulae) => {
+                let unit = self.graph.unit_colored_vertices();
+                let mut extra_fixed_points = unit.minus(&self.graph.pre(unit));
+                for state_formula in state_formulae {
+                    extra_fixed_points = extra_fixed_points.minus(&self.states(state_formula)?);
+                }
+                self.pick_state(&extra_fixed_points)
+                    .map(|(_, state)| Counterexample::ExtraFixedPoint { state })
+            }
+            DynamicProperty::TimeSeries(state_formulae) => {
+                let mut reached = self.states(&state_formulae[0])?;
+                let mut unreachable_step = None;
+                for (i, state_formula) in state_formulae.iter().enumerate().skip(1) {
+                    reached = self
+                        .reach_forward(&reached)
+                        .intersect(&self.states(state_formula)?);
+                    if reached.is_empty() {
+                        unreachable_step = Some(Counterexample::UnreachableStep {
+                            step: i + 1,
+                            state: state_formula.clone(),
+                        });
+                        break;
+                    }
+                }
+                unreachable_step
+            }
+        };
+        Ok(counterexample)
+    }
+
+    /// Find counterexamples for a violated property given by a formula. Conjunctions are split
+    /// and counterexamples are computed for each violated conjunct.
+    fn explain_formula(&self, formula: &str) -> Result<Vec<Counterexample>, SketchError> {
+        if let Some(property) = recognise_property(formula) {
+            return Ok(self.find_counterexample(&property)?.into_iter().collect());
+        }
+        // only split conjunctions without top-level hybrid operators (so all conjuncts are closed)
+        let stripped_formula = strip_enclosing_parentheses(formula);
+        let conjuncts = split_top_level(stripped_formula, '&');
+        if conjuncts.len() < 2 || split_top_level(stripped_formula, ':').len() > 1 {
+            return Ok(Vec::new());
+        }
+        let mut counterexamples = Vec::new();
+        for conjunct in conjuncts {
+            if !self.holds(conjunct)? {
+                counterexamples.extend(self.explain_formula(conjunct)?);
+            }
+        }
+        Ok(counterexamples)
+    }
+}
+
+/// Explain why the fully specified `network` is excluded from the candidates of the sketch
+/// given by `graph` (restricted by the static properties) and the named HCTL properties.
+///
+/// Each property is evaluated on the network alone, and counterexamples are computed for the
+/// violated ones (see `Counterexample`).
+pub fn explain_network_exclusion(
+    graph: &SymbolicAsyncGraph,
+    network: &BooleanNetwork,
+    named_formulae: &[(String, String)],
+) -> Result<ExclusionReport, SketchError> {
+    match graph.mk_subnetwork_colors(network) {
+        Ok(colors) => explain_network_colors_exclusion(graph, network, &colors, named_formulae),
+        Err(e) => Ok(ExclusionReport {
+            instance_error: Some(e),
+            violations: Vec::new(),
+        }),
+    }
+}
+
+/// Explain the exclusion of the `network` (see `explain_network_exclusion`), given the colors
+/// encoding it in the `graph` (see `mk_subnetwork_colors`).
+pub fn explain_network_colors_exclusion(
+    graph: &SymbolicAsyncGraph,
+    network: &BooleanNetwork,
+    network_colors: &GraphColors,
+    named_formulae: &[(String, String)],
+) -> Result<ExclusionReport, SketchError> {
+    let network_colors = network_colors.intersect(graph.unit_colors());
+    if network_colors.is_empty() {
+        return Ok(ExclusionReport {
+            instance_error: Some("it violates the update function properties.".to_string()),
+            violations: Vec::new(),
+        });
+    }
+
+    let dynamics = NetworkDynamics {
+        graph: mk_restricted_graph(graph, &network_colors)?,
+        network,
+    };
+    let mut violations = Vec::new();
+    for (name, formula) in named_formulae {
+        if !dynamics.holds(formula)? {
+            violations.push(PropertyViolation {
+                property_name: name.clone(),
+                counterexamples: dynamics.explain_formula(formula)?,
+            });
+        }
+    }
+    Ok(ExclusionReport {
+        instance_error: None,
+        violations,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data_processing::create_inference_formulae::*;
+    use crate::explanation::{
+        explain_network_exclusion, recognise_property, Counterexample, DynamicProperty,
+    };
+    use biodivine_hctl_model_checker::mc_utils::get_extended_symbolic_graph;
+    use biodivine_lib_param_bn::BooleanNetwork;
+
+    #[test]
+    /// Test recognising properties generated from data.
+    fn test_recognise_properties() {
+        let states = vec!["a & ~b".to_string(), "a & b".to_string()];
+        let cases = vec![
+            (
+                mk_formula_attractor_specific(states[0].clone()).unwrap(),
+                Some(DynamicProperty::Attractor("a & ~b".to_string())),
+            ),
+            (
+                mk_formula_fixed_point(states[0].clone()).unwrap(),
+                Some(DynamicProperty::FixedPoint("a & ~b".to_string())),
+            ),
+            (
+                mk_formula_forbid_other_attractors(states.clone()).unwrap(),
+                Some(DynamicProperty::ForbidOtherAttractors(vec![
+                    "a & ~b".to_string(),
+                    "a & b".to_string(),
+                ])),
+            ),
+            (
+                mk_formula_forbid_other_fixed_points(states.clone()).unwrap(),
+                Some(DynamicProperty::ForbidOtherFixedPoints(vec![
+                    "a & ~b".to_string(),
+                    "a & b".to_string(),
+                ])),
+            ),
+            (
+                mk_formula_reachability_chain(states.clone()).unwrap(),
+                Some(DynamicProperty::TimeSeries(vec![
+                    "a & ~b".to_string(),
+                    "a & b".to_string(),
+                ])),
+            ),
+            ("3{x}: @{x}: a & AX {x}".to_string(), None),
+            ("(3{x}: (@{x}: (a & (AG EF (b)))))".to_string(), None),
+        ];
+        for (formula, expected) in cases {
+            assert_eq!(recognise_property(&formula), expected);
+        }
+    }
+
+    #[test]
+    /// Test explaining violated properties of a simple network with two fixed points (`00`
+    /// and `11`).
+    fn test_explain_exclusion() {
+        let bn = BooleanNetwork::try_from("a -> b\nb -> a\n$a: b\n$b: a\n").unwrap();
+        let graph = get_extended_symbolic_graph(&bn, 1).unwrap();
+        let named_formulae: Vec<(String, String)> = vec![
+            ("fixed_11", mk_formula_fixed_point("a & b".to_string())),
+            ("fixed_10", mk_formula_fixed_point("a & ~b".to_string())),
+            ("attractor_10", mk_formula_attractor("a & ~b".to_string())),
+            (
+                "forbid_attractors",
+                mk_formula_forbid_other_attractors(vec!["a & b".to_string()]),
+            ),
+            (
+                "forbid_fixed_points",
+                mk_formula_forbid_other_fixed_points(vec!["a & b".to_string()]),
+            ),
+            (
+                "time_series",
+                mk_formula_reachability_chain(vec!["a & b".to_string(), "~a & ~b".to_string()]),
+            ),
+            ("general", Ok("~(3{x}: @{x}: ~a & AX {x})".to_string())),
+        ]
+        .into_iter()
+        .map(|(name, formula)| (name.to_string(), formula.unwrap()))
+        .collect();
+
+        let report = explain_network_exclusion(&graph, &bn, &named_formulae).unwrap();
+        assert!(report.is_excluded());
+        assert!(report.instance_error.is_none());
+        let names: Vec<&str> = report
+            .violations
+            .iter()
+            .map(|v| v.property_name.as_str())
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                "fixed_10",
+                "attractor_10",
+                "forbid_attractors",
+                "forbid_fixed_points",
+                "time_series",
+                "general"
+            ]
+        );
+        assert!(matches!(
+            report.violations[0].counterexamples[..],
+            [Counterexample::NotFixedPoint { .. }]
+        ));
+        assert!(matches!(
+            report.violations[1].counterexamples[..],
+            [Counterexample::NotInAttractor { .. }]
+        ));
+        let extra_state = "~a & ~b".to_string();
+        assert_eq!(
+            report.violations[2].counterexamples,
+            vec![Counterexample::ExtraAttractor {
+                state: extra_state.clone()
+            }]
+        );
+        assert_eq!(
+            report.violations[3].counterexamples,
+            vec![Counterexample::ExtraFixedPoint { state: extra_state }]
+        );
+        assert_eq!(
+            report.violations[4].counterexamples,
+            vec![Counterexample::UnreachableStep {
+                step: 2,
+                state: "~a & ~b".to_string()
+            }]
+        );
+        assert!(report.violations[5].counterexamples.is_empty());
+    }
+}
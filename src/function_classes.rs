@@ -0,0 +1,548 @@
+//! Contains functionality to restrict update functions to biologically motivated classes.
+//!
+//! The classes are given as sketch annotations, either globally (applied to all variables whose
+//! update function is not fully specified) or for individual variables:
+//!
+//! ```text
+//! #! function_class: nested_canalizing
+//! #! function_class: CtrA: and_of_ors(2)
+//! #! function_class: DivK: threshold(2)
+//! ```
+//!
+//! Weights of threshold functions are integers bounded by the given maximal absolute value
+//! (`threshold` alone stands for `threshold(1)`). For example, `a & (b | c)` is the threshold
+//! function `[2a + b + c >= 3]`, which needs `threshold(2)`.
+//!
+//! The restriction is computed symbolically on the row-wise encoding of update functions (for
+//! each combination of regulator values, a BDD over parameters describing when the function is
+//! true), and applied to the color set before model checking.
+
+use crate::errors::SketchError;
+use crate::utils::{get_network, mk_restricted_graph, mk_update_fn_rows};
+
+use biodivine_lib_bdd::Bdd;
+use biodivine_lib_param_bn::biodivine_std::traits::Set;
+use biodivine_lib_param_bn::symbolic_async_graph::{GraphColors, SymbolicAsyncGraph};
+use biodivine_lib_param_bn::{BooleanNetwork, ModelAnnotation, Monotonicity, VariableId};
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+
+/// Maximal number of clause combinations enumerated for the `AndOfOrs` class.
+const MAX_CLAUSE_COMBINATIONS: usize = 1_000_000;
+
+/// Maximal number of weight combinations enumerated for the `Threshold` class.
+const MAX_WEIGHT_COMBINATIONS: usize = 1_000_000;
+
+/// Enum of supported classes of update functions.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum FunctionClass {
+    /// Threshold functions, i.e., `f(x) = [sum_i w_i * x_i >= theta]` with an integer threshold
+    /// `theta` and integer weights `w_i` of absolute value at most the given bound. Weights of
+    /// activators are non-negative, weights of inhibitors non-positive (zero weights are allowed
+    /// for regulators that are not observable).
+    Threshold(usize),
+    /// Nested canalizing functions (all regulators are canalizing in some order).
+    NestedCanalizing,
+    /// Sign-consistent (unate) functions, i.e., monotone in each regulator.
+    SignConsistent,
+    /// Conjunctions of at most the given number of disjunctive clauses.
+    AndOfOrs(usize),
+}
+
+impl fmt::Display for FunctionClass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FunctionClass::Threshold(max_weight) => write!(f, "threshold({max_weight})"),
+            FunctionClass::NestedCanalizing => write!(f, "nested_canalizing"),
+            FunctionClass::SignConsistent => write!(f, "sign_consistent"),
+            FunctionClass::AndOfOrs(num_clauses) => write!(f, "and_of_ors({num_clauses})"),
+        }
+    }
+}
+
+impl TryFrom<&str> for FunctionClass {
+    type Error = SketchError;
+
+    /// Parse the function class from its string representation (as used in annotations).
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let argument = |class: &str, name: &str| {
+            class
+                .strip_prefix(name)
+                .and_then(|rest| rest.strip_prefix('('))
+                .and_then(|rest| rest.strip_suffix(')'))
+                .and_then(|num| num.trim().parse::<usize>().ok())
+        };
+        match value.trim() {
+            "threshold" => Ok(FunctionClass::Threshold(1)),
+            "nested_canalizing" => Ok(FunctionClass::NestedCanalizing),
+            "sign_consistent" => Ok(FunctionClass::SignConsistent),
+            other => {
+                if let Some(max_weight) = argument(other, "threshold").filter(|w| *w > 0) {
+                    Ok(FunctionClass::Threshold(max_weight))
+                } else if let Some(num_clauses) = argument(other, "and_of_ors") {
+                    Ok(FunctionClass::AndOfOrs(num_clauses))
+                } else {
+                    Err(SketchError::parse(format!(
+                        "Invalid function class `{other}`."
+                    )))
+                }
+            }
+        }
+    }
+}
+
+/// Read function classes from an `.aeon` model annotation object.
+///
+/// A global class (`#! function_class: CLASS`) applies to all variables whose update function is
+/// not fully specified (it is implicit, or contains parameters). A class given for a particular
+/// variable (`#! function_class: VAR: CLASS`) overrides the global one.
+pub fn read_function_classes(
+    bn: &BooleanNetwork,
+    annotations: &ModelAnnotation,
+) -> Result<Vec<(VariableId, FunctionClass)>, SketchError> {
+    let Some(class_node) = annotations.get_child(&["function_class"]) else {
+        return Ok(Vec::new());
+    };
+    let parse_value = |value: &String| {
+        if value.lines().count() > 1 {
+            return Err(SketchError::parse(format!(
+                "Found multiple function classes `{value}`."
+            )));
+        }
+        FunctionClass::try_from(value.as_str())
+    };
+
+    let mut classes = HashMap::new();
+    if let Some(value) = class_node.value() {
+        let global_class = parse_value(value)?;
+        for var in bn.variables() {
+            let is_unspecified = match bn.get_update_function(var) {
+                Some(update_fn) => !update_fn.collect_parameters().is_empty(),
+                None => true,
+            };
+            if is_unspecified {
+                classes.insert(var, global_class);
+            }
+        }
+    }
+    for (var_name, child) in class_node.children() {
+        let Some(var) = bn.find_variable(var_name) else {
+            return Err(SketchError::UnknownVariable(var_name.clone()));
+        };
+        let Some(value) = child.value() else {
+            return Err(SketchError::parse(format!(
+                "Missing function class for variable `{var_name}`."
+            )));
+        };
+        classes.insert(var, parse_value(value)?);
+    }
+
+    let mut classes: Vec<(VariableId, FunctionClass)> = classes.into_iter().collect();
+    classes.sort_by_key(|(var, _)| *var);
+    Ok(classes)
+}
+
+/// Allowed signs of each regulator of `var` (true for positive), based on its monotonicity.
+fn allowed_signs(bn: &BooleanNetwork, var: VariableId) -> Vec<Vec<bool>> {
+    bn.regulators(var)
+        .into_iter()
+        .map(|regulator| {
+            let monotonicity = bn
+                .as_graph()
+                .find_regulation(regulator, var)
+                .and_then(|regulation| regulation.get_monotonicity());
+            match monotonicity {
+                Some(Monotonicity::Activation) => vec![true],
+                Some(Monotonicity::Inhibition) => vec![false],
+                None => vec![true, false],
+            }
+        })
+        .collect()
+}
+
+/// Compute all combinations of elements, one from each of the `options`.
+fn cartesian_product<T: Copy>(options: &[Vec<T>]) -> Vec<Vec<T>> {
+    let mut combinations = vec![Vec::new()];
+    for values in options {
+        combinations = combinations
+            .into_iter()
+            .flat_map(|prefix| {
+                values.iter().map(move |value| {
+                    let mut combination = prefix.clone();
+                    combination.push(*value);
+                    combination
+                })
+            })
+            .collect();
+    }
+    combinations
+}
+
+/// Encode that the function given by `rows` equals one of the given truth `tables`.
+fn mk_truth_tables_bdd(rows: &[Bdd], tables: &HashSet<Vec<bool>>, unit: &Bdd) -> Bdd {
+    let mut result = unit.and_not(unit);
+    for table in tables {
+        let mut function_bdd = unit.clone();
+        for (row_bdd, value) in rows.iter().zip(table) {
+            function_bdd = if *value {
+                function_bdd.and(row_bdd)
+            } else {
+                function_bdd.and_not(row_bdd)
+            };
+        }
+        result = result.or(&function_bdd);
+    }
+    result
+}
+
+/// Encode that the function given by `rows` is a threshold function with weights of absolute
+/// value at most `max_weight` (and signs given by the allowed `signs` of regulators).
+///
+/// For fixed weights, such function only depends on the weighted sum of inputs, and is monotone
+/// in it. Hence, rows with the same sum must have the same value, and values grow with the sum.
+/// Many weights order the rows by their sums in the same way, so each ordering is encoded once.
+fn mk_threshold_bdd(
+    rows: &[Bdd],
+    signs: &[Vec<bool>],
+    max_weight: usize,
+    unit: &Bdd,
+) -> Result<Bdd, SketchError> {
+    let max_weight = max_weight as i32;
+    let weight_options: Vec<Vec<i32>> = signs
+        .iter()
+        .map(|allowed| {
+            let mut weights = Vec::new();
+            if allowed.contains(&false) {
+                weights.extend(-max_weight..0);
+            }
+            weights.push(0);
+            if allowed.contains(&true) {
+                weights.extend(1..=max_weight);
+            }
+            weights
+        })
+        .collect();
+    let num_combinations = weight_options
+        .iter()
+        .try_fold(1usize, |acc, weights| acc.checked_mul(weights.len()));
+    if num_combinations.is_none_or(|num| num > MAX_WEIGHT_COMBINATIONS) {
+        return Err(SketchError::InvalidArgument(format!(
+            "Too many weight combinations to enumerate for `threshold({max_weight})`."
+        )));
+    }
+
+    // rows grouped by the weighted sum, in the increasing order of sums
+    let mut orderings: HashSet<Vec<Vec<usize>>> = HashSet::new();
+    for weights in cartesian_product(&weight_options) {
+        let mut levels: BTreeMap<i32, Vec<usize>> = BTreeMap::new();
+        for row in 0..rows.len() {
+            let weighted_sum: i32 = weights
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| row & (1 << i) != 0)
+                .map(|(_, weight)| *weight)
+                .sum();
+            levels.entry(weighted_sum).or_default().push(row);
+        }
+        orderings.insert(levels.into_values().collect());
+    }
+
+    let mut result = unit.and_not(unit);
+    for levels in orderings {
+        let mut ordering_bdd = unit.clone();
+        let mut previous_level: Option<&Bdd> = None;
+        for level in &levels {
+            let level_bdd = &rows[level[0]];
+            for row in &level[1..] {
+                ordering_bdd = ordering_bdd.and(&level_bdd.iff(&rows[*row]));
+            }
+            if let Some(previous_bdd) = previous_level {
+                ordering_bdd = ordering_bdd.and(&previous_bdd.imp(level_bdd));
+            }
+            previous_level = Some(level_bdd);
+        }
+        result = result.or(&ordering_bdd);
+    }
+    Ok(result)
+}
+
+/// Encode that the function given by `rows` is constant `value` on the sub-cube given by
+/// partially fixed regulator values `fixed` (`None` meaning the regulator is free).
+fn mk_constant_on_subcube(rows: &[Bdd], fixed: &[Option<bool>], value: bool, unit: &Bdd) -> Bdd {
+    let mut result = unit.clone();
+    for (row, row_bdd) in rows.iter().enumerate() {
+        let in_subcube = fixed
+            .iter()
+            .enumerate()
+            .all(|(i, val)| val.is_none_or(|val| (row & (1 << i) != 0) == val));
+        if in_subcube {
+            result = if value {
+                result.and(row_bdd)
+            } else {
+                result.and_not(row_bdd)
+            };
+        }
+    }
+    result
+}
+
+/// Encode that the function given by `rows`, restricted to the sub-cube given by `fixed`, is
+/// nested canalizing in all free regulators. Results for sub-cubes are cached in `cache`.
+fn mk_nested_canalizing_bdd(
+    rows: &[Bdd],
+    fixed: Vec<Option<bool>>,
+    unit: &Bdd,
+    cache: &mut HashMap<Vec<Option<bool>>, Bdd>,
+) -> Bdd {
+    if let Some(result) = cache.get(&fixed) {
+        return result.clone();
+    }
+    let free: Vec<usize> = (0..fixed.len()).filter(|i| fixed[*i].is_none()).collect();
+
+    let mut result = unit.and_not(unit);
+    if free.is_empty() {
+        result = unit.clone();
+    } else if free.len() == 1 {
+        // the last regulator must determine the output completely
+        for input in [false, true] {
+            for output in [false, true] {
+                let mut canalized = fixed.clone();
+                canalized[free[0]] = Some(input);
+                let mut other = fixed.clone();
+                other[free[0]] = Some(!input);
+                let bdd = mk_constant_on_subcube(rows, &canalized, output, unit)
+                    .and(&mk_constant_on_subcube(rows, &other, !output, unit));
+                result = result.or(&bdd);
+            }
+        }
+    } else {
+        // some regulator canalizes the output, and the rest is nested canalizing
+        for regulator in free {
+            for input in [false, true] {
+                let mut canalized = fixed.clone();
+                canalized[regulator] = Some(input);
+                let mut other = fixed.clone();
+                other[regulator] = Some(!input);
+                let rest = mk_nested_canalizing_bdd(rows, other, unit, cache);
+                if rest.is_false() {
+                    continue;
+                }
+                for output in [false, true] {
+                    let bdd = mk_constant_on_subcube(rows, &canalized, output, unit).and(&rest);
+                    result = result.or(&bdd);
+                }
+            }
+        }
+    }
+    cache.insert(fixed, result.clone());
+    result
+}
+
+/// Encode that the function given by `rows` is monotone (in either direction) in each regulator.
+fn mk_sign_consistent_bdd(rows: &[Bdd], num_regulators: usize, unit: &Bdd) -> Bdd {
+    let mut result = unit.clone();
+    for i in 0..num_regulators {
+        let mut can_increase = unit.and_not(unit);
+        let mut can_decrease = unit.and_not(unit);
+        for row in (0..rows.len()).filter(|row| row & (1 << i) == 0) {
+            let (inactive, active) = (&rows[row], &rows[row | (1 << i)]);
+            can_increase = can_increase.or(&active.and_not(inactive));
+            can_decrease = can_decrease.or(&inactive.and_not(active));
+        }
+        result = result.and_not(&can_increase.and(&can_decrease));
+    }
+    result
+}
+
+/// Compute truth tables of all conjunctions of at most `num_clauses` disjunctive clauses over
+/// `num_regulators` inputs, with literal polarities given by allowed `signs` of regulators.
+fn mk_and_of_ors_tables(
+    num_regulators: usize,
+    signs: &[Vec<bool>],
+    num_clauses: usize,
+) -> Result<HashSet<Vec<bool>>, SketchError> {
+    let num_rows = 1usize << num_regulators;
+    // each regulator is either missing in a clause, or appears as a literal of allowed polarity
+    let literal_options: Vec<Vec<Option<bool>>> = signs
+        .iter()
+        .map(|allowed| {
+            let mut options = vec![None];
+            options.extend(allowed.iter().map(|sign| Some(*sign)));
+            options
+        })
+        .collect();
+    let mut clauses: Vec<Vec<Option<bool>>> = vec![Vec::new()];
+    for options in &literal_options {
+        clauses = clauses
+            .into_iter()
+            .flat_map(|prefix| {
+                options.iter().map(move |option| {
+                    let mut clause = prefix.clone();
+                    clause.push(*option);
+                    clause
+                })
+            })
+            .collect();
+    }
+
+    // truth tables of clauses, including a trivially true "clause" for shorter conjunctions
+    let mut clause_tables = vec![vec![true; num_rows]];
+    for clause in clauses {
+        let table = (0..num_rows)
+            .map(|row| {
+                clause.iter().enumerate().any(|(i, literal)| {
+                    literal.is_some_and(|positive| (row & (1 << i) != 0) == positive)
+                })
+            })
+            .collect();
+        clause_tables.push(table);
+    }
+
+    // conjunctions of `num_clauses` clause tables (duplicate tables are merged on the way)
+    let mut tables: HashSet<Vec<bool>> = HashSet::from([vec![true; num_rows]]);
+    let mut num_combinations = 1;
+    for _ in 0..num_clauses {
+        num_combinations *= clause_tables.len();
+        if num_combinations > MAX_CLAUSE_COMBINATIONS {
+            return Err(SketchError::InvalidArgument(format!(
+                "Too many clause combinations to enumerate for `and_of_ors({num_clauses})`."
+            )));
+        }
+        tables = tables
+            .iter()
+            .flat_map(|table| {
+                clause_tables.iter().map(move |clause_table| {
+                    table
+                        .iter()
+                        .zip(clause_table)
+                        .map(|(x, y)| *x && *y)
+                        .collect::<Vec<bool>>()
+                })
+            })
+            .collect();
+    }
+    Ok(tables)
+}
+
+/// Compute the set of colors (within the graph's unit colors), for which the update function of
+/// variable `var` belongs to the function `class`.
+pub fn mk_function_class_colors(
+    graph: &SymbolicAsyncGraph,
+    var: VariableId,
+    class: FunctionClass,
+) -> Result<GraphColors, SketchError> {
+    let bn = get_network(graph)?;
+    let rows = mk_update_fn_rows(graph, var)?;
+    let signs = allowed_signs(bn, var);
+    let unit = graph.unit_colors().as_bdd();
+
+    let class_bdd = match class {
+        FunctionClass::Threshold(max_weight) => mk_threshold_bdd(&rows, &signs, max_weight, unit)?,
+        FunctionClass::NestedCanalizing => {
+            let fixed = vec![None; signs.len()];
+            mk_nested_canalizing_bdd(&rows, fixed, unit, &mut HashMap::new())
+        }
+        FunctionClass::SignConsistent => mk_sign_consistent_bdd(&rows, signs.len(), unit),
+        FunctionClass::AndOfOrs(num_clauses) => {
+            let tables = mk_and_of_ors_tables(signs.len(), &signs, num_clauses)?;
+            mk_truth_tables_bdd(&rows, &tables, unit)
+        }
+    };
+    Ok(graph.mk_unit_colors().copy(class_bdd))
+}
+
+/// Restrict the colors of the graph to those, where each update function belongs to the class
+/// given for its variable in `classes`.
+/// Returns a graph with colour space restricted only to the suitable colors.
+pub fn apply_function_classes(
+    graph: SymbolicAsyncGraph,
+    classes: &[(VariableId, FunctionClass)],
+) -> Result<SymbolicAsyncGraph, SketchError> {
+    let mut colors = graph.mk_unit_colors();
+    for (var, class) in classes {
+        colors = colors.intersect(&mk_function_class_colors(&graph, *var, *class)?);
+    }
+    mk_restricted_graph(&graph, &colors)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::function_classes::{
+        apply_function_classes, mk_function_class_colors, read_function_classes, FunctionClass,
+    };
+    use biodivine_hctl_model_checker::mc_utils::get_extended_symbolic_graph;
+    use biodivine_lib_param_bn::{BooleanNetwork, ModelAnnotation};
+
+    const TEST_MODEL: &str = r"
+a -> t
+b -> t
+c -? t
+";
+
+    #[test]
+    /// Test parsing of function classes.
+    fn test_function_class_parsing() {
+        for class in [
+            FunctionClass::Threshold(2),
+            FunctionClass::NestedCanalizing,
+            FunctionClass::SignConsistent,
+            FunctionClass::AndOfOrs(3),
+        ] {
+            let class_str = class.to_string();
+            assert_eq!(FunctionClass::try_from(class_str.as_str()).unwrap(), class);
+        }
+        assert_eq!(
+            FunctionClass::try_from("threshold").unwrap(),
+            FunctionClass::Threshold(1)
+        );
+        assert!(FunctionClass::try_from("threshold(0)").is_err());
+        assert!(FunctionClass::try_from("and_of_ors(x)").is_err());
+        assert!(FunctionClass::try_from("canalizing").is_err());
+    }
+
+    #[test]
+    /// Test computing colors of function classes (results computed by enumeration).
+    fn test_function_class_colors() {
+        let bn = BooleanNetwork::try_from(TEST_MODEL).unwrap();
+        let graph = get_extended_symbolic_graph(&bn, 1).unwrap();
+        // 20 admissible functions of `t`, and 2 constant functions for each of `a`, `b`, `c`
+        assert_eq!(graph.mk_unit_colors().approx_cardinality(), 160.);
+
+        let t = bn.find_variable("t").unwrap();
+        let count = |class| {
+            mk_function_class_colors(&graph, t, class)
+                .unwrap()
+                .approx_cardinality()
+        };
+        // weights of `a & (b | c)` (as `[2a + b + c >= 3]`) or `a | (b & ~c)` are not unit
+        assert_eq!(count(FunctionClass::Threshold(1)), 48.);
+        assert_eq!(count(FunctionClass::Threshold(2)), 144.);
+        assert_eq!(count(FunctionClass::NestedCanalizing), 128.);
+        assert_eq!(count(FunctionClass::SignConsistent), 144.);
+        assert_eq!(count(FunctionClass::AndOfOrs(1)), 16.);
+        assert_eq!(count(FunctionClass::AndOfOrs(2)), 128.);
+    }
+
+    #[test]
+    /// Test reading function classes from annotations and applying them.
+    fn test_apply_function_classes() {
+        let model = format!(
+            "#! function_class: sign_consistent\n#! function_class: t: threshold\n{TEST_MODEL}"
+        );
+        let bn = BooleanNetwork::try_from(model.as_str()).unwrap();
+        let annotations = ModelAnnotation::from_model_string(model.as_str());
+        let classes = read_function_classes(&bn, &annotations).unwrap();
+        assert_eq!(classes.len(), 4);
+        let t = bn.find_variable("t").unwrap();
+        assert!(classes.contains(&(t, FunctionClass::Threshold(1))));
+
+        let graph = get_extended_symbolic_graph(&bn, 1).unwrap();
+        let graph = apply_function_classes(graph, &classes).unwrap();
+        assert_eq!(graph.mk_unit_colors().approx_cardinality(), 48.);
+
+        let invalid_model = format!("#! function_class: x: threshold\n{TEST_MODEL}");
+        let annotations = ModelAnnotation::from_model_string(invalid_model.as_str());
+        assert!(read_function_classes(&bn, &annotations).is_err());
+    }
+}
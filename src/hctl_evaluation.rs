@@ -0,0 +1,544 @@
+//! Contains a symbolic HCTL model checker whose computation can be interrupted.
+//!
+//! The model checking library can't be stopped once it starts evaluating a formula. Therefore,
+//! the monitored inference (see `InferenceMonitor`) evaluates formulae by this model checker,
+//! which checks an `InterruptGuard` in every iteration of each fixed-point computation (including
+//! the size of the intermediate results). An interrupted evaluation thus stops promptly (on the
+//! thread that started it) and releases all of its memory.
+//!
+//! Formulae are evaluated either under the asynchronous semantics (using the transitions of the
+//! symbolic graph), or under a general update schedule given by a `TransitionRelation`. As in the
+//! model checking library, states without any successor have a self-loop. Universal operators
+//! are evaluated as complements of existential ones (such as `AG phi = ~EF ~phi`), so that most
+//! of them are computed by the (saturated) backward reachability. HCTL variables are
+//! assigned to the extra state variables of the symbolic context by the number of quantifiers
+//! above them, so a formula can nest as many quantifiers as there are HCTL variables (which is
+//! the number of variables of a minimized formula). Wild-card propositions and restricted
+//! quantifier domains are not supported.
+
+use crate::monitoring::{InterruptGuard, InterruptReason};
+use crate::update_semantics::TransitionRelation;
+use crate::utils::get_network;
+
+use biodivine_hctl_model_checker::preprocessing::hctl_tree::{HctlTreeNode, NodeType};
+use biodivine_hctl_model_checker::preprocessing::operator_enums::{
+    Atomic, BinaryOp, HybridOp, UnaryOp,
+};
+
+use biodivine_lib_bdd::{Bdd, BddVariable};
+use biodivine_lib_param_bn::biodivine_std::traits::Set;
+use biodivine_lib_param_bn::symbolic_async_graph::{GraphColoredVertices, SymbolicAsyncGraph};
+use biodivine_lib_param_bn::VariableId;
+
+use std::cell::{OnceCell, RefCell};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Enum of reasons why an evaluation of a formula did not finish.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum EvaluationError {
+    /// The formula can't be evaluated (such as when it contains an unknown proposition).
+    Failed(String),
+    /// The evaluation was interrupted by its `InterruptGuard`.
+    Interrupted(InterruptReason),
+}
+
+impl fmt::Display for EvaluationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EvaluationError::Failed(message) => write!(f, "{message}"),
+            EvaluationError::Interrupted(reason) => write!(f, "Evaluation {reason}."),
+        }
+    }
+}
+
+/// Model checker evaluating HCTL formulae on all candidates of a graph at once (see the module
+/// documentation).
+pub struct HctlEvaluator<'a> {
+    graph: &'a SymbolicAsyncGraph,
+    transitions: Option<&'a TransitionRelation>,
+    guard: &'a InterruptGuard,
+    /// Extra state variables used for HCTL variables (for each network variable).
+    hctl_vars: Vec<Vec<BddVariable>>,
+    /// States without any asynchronous successor (computed when first needed).
+    sinks: OnceCell<Bdd>,
+    /// Results of already evaluated sub-formulae (if they are shared).
+    cache: Option<RefCell<HashMap<String, Bdd>>>,
+}
+
+impl<'a> HctlEvaluator<'a> {
+    /// Create a model checker for the `graph`, evaluating formulae under the given `transitions`
+    /// (or the asynchronous semantics if there are none). The `guard` is checked during the
+    /// evaluation.
+    pub fn new(
+        graph: &'a SymbolicAsyncGraph,
+        transitions: Option<&'a TransitionRelation>,
+        guard: &'a InterruptGuard,
+    ) -> HctlEvaluator<'a> {
+        let hctl_vars = match transitions {
+            Some(transitions) => transitions.hctl_vars().to_vec(),
+            None => {
+                let context = graph.symbolic_context();
+                graph
+                    .variables()
+                    .map(|var| context.get_extra_state_variables(var).clone())
+                    .collect()
+            }
+        };
+        HctlEvaluator {
+            graph,
+            transitions,
+            guard,
+            hctl_vars,
+            sinks: OnceCell::new(),
+            cache: None,
+        }
+    }
+
+    /// Keep the results of all evaluated sub-formulae, so that sub-formulae shared by several
+    /// evaluated formulae are only computed once.
+    pub fn share_subformulae(mut self) -> HctlEvaluator<'a> {
+        self.cache = Some(RefCell::new(HashMap::new()));
+        self
+    }
+
+    /// Evaluate the HCTL formula `tree` on all candidates of the graph.
+    pub fn evaluate(&self, tree: &HctlTreeNode) -> Result<GraphColoredVertices, EvaluationError> {
+        let result = self.eval(tree, &HashMap::new())?;
+        let result = result.and(self.graph.unit_colored_vertices().as_bdd());
+        Ok(GraphColoredVertices::new(
+            result,
+            self.graph.symbolic_context(),
+        ))
+    }
+
+    fn num_hctl_vars(&self) -> usize {
+        self.hctl_vars.first().map_or(0, |vars| vars.len())
+    }
+
+    /// Stop the evaluation if the guard signals an interrupt.
+    fn check(&self) -> Result<(), EvaluationError> {
+        match self.guard.check() {
+            Some(reason) => Err(EvaluationError::Interrupted(reason)),
+            None => Ok(()),
+        }
+    }
+
+    /// Stop the evaluation if the (intermediate) result `bdd` exceeds the BDD size limit.
+    fn check_size(&self, bdd: &Bdd) -> Result<(), EvaluationError> {
+        match self.guard.check_bdd(bdd) {
+            Some(reason) => Err(EvaluationError::Interrupted(reason)),
+            None => Ok(()),
+        }
+    }
+
+    fn wrap(&self, bdd: Bdd) -> GraphColoredVertices {
+        GraphColoredVertices::new(bdd, self.graph.symbolic_context())
+    }
+
+    /// Compute the predecessors of the `set`, including the states of the `set` that have
+    /// a self-loop.
+    fn ex(&self, set: &Bdd) -> Bdd {
+        if let Some(transitions) = self.transitions {
+            return transitions.pre_bdd(self.graph, set);
+        }
+        let sinks = self.sinks.get_or_init(|| {
+            let unit = self.graph.mk_unit_colored_vertices();
+            unit.minus(&self.graph.can_post(&unit)).as_bdd().clone()
+        });
+        let predecessors = self.graph.pre(&self.wrap(set.clone()));
+        predecessors.as_bdd().or(&set.and(sinks))
+    }
+
+    fn ax(&self, set: &Bdd) -> Bdd {
+        let unit = self.graph.unit_colored_vertices().as_bdd();
+        unit.and_not(&self.ex(&unit.and_not(set)))
+    }
+
+    /// Compute the least (or greatest) fixed point of `z -> step(z)` starting from `initial`,
+    /// checking the guard (and the size of `z`) in each iteration.
+    fn fixed_point(
+        &self,
+        initial: Bdd,
+        step: impl Fn(&Bdd) -> Bdd,
+    ) -> Result<Bdd, EvaluationError> {
+        let mut z = initial;
+        loop {
+            self.check()?;
+            self.check_size(&z)?;
+            let next = step(&z);
+            if next == z {
+                return Ok(z);
+            }
+            z = next;
+        }
+    }
+
+    /// Compute the states from which some state of `target` is reachable by a path through the
+    /// `through` states (the least fixed point of `z -> target | (through & EX z)`).
+    ///
+    /// Under the asynchronous semantics, the predecessors are added for one variable at a time
+    /// (saturation), which is usually much faster than adding all predecessors at once.
+    fn reach_backward(&self, target: Bdd, through: Option<&Bdd>) -> Result<Bdd, EvaluationError> {
+        if self.transitions.is_some() {
+            return match through {
+                Some(through) => {
+                    self.fixed_point(target.clone(), |z| target.or(&through.and(&self.ex(z))))
+                }
+                None => self.fixed_point(target.clone(), |z| target.or(&self.ex(z))),
+            };
+        }
+        let variables: Vec<VariableId> = self.graph.variables().collect();
+        let mut reached = self.wrap(target);
+        'saturation: loop {
+            self.check()?;
+            self.check_size(reached.as_bdd())?;
+            for var in variables.iter().rev() {
+                let mut step = self.graph.var_pre(*var, &reached).minus(&reached);
+                if let Some(through) = through {
+                    step = step.intersect(&self.wrap(through.clone()));
+                }
+                if !step.is_empty() {
+                    reached = reached.union(&step);
+                    continue 'saturation;
+                }
+            }
+            return Ok(reached.as_bdd().clone());
+        }
+    }
+
+    /// Compute the states with a path staying in the `set` forever (the greatest fixed point of
+    /// `z -> set & EX z`).
+    fn eg(&self, set: Bdd) -> Result<Bdd, EvaluationError> {
+        self.fixed_point(set.clone(), |z| set.and(&self.ex(z)))
+    }
+
+    /// Compute the states from which every path satisfies `left` until `right` holds (or
+    /// forever), as the complement of `E(~right U (~left & ~right))`, so that it is computed by
+    /// the backward reachability.
+    fn aw(&self, left: &Bdd, right: &Bdd) -> Result<Bdd, EvaluationError> {
+        let unit = self.graph.unit_colored_vertices().as_bdd();
+        let not_right = unit.and_not(right);
+        let violation = self.reach_backward(not_right.and_not(left), Some(&not_right))?;
+        Ok(unit.and_not(&violation))
+    }
+
+    /// BDD expressing that the state is equal to the value of the `i`-th HCTL variable.
+    fn mk_hctl_var_equality(&self, i: usize) -> Bdd {
+        let bdd_vars = self.graph.symbolic_context().bdd_variable_set();
+        let state_vars = self.graph.symbolic_context().state_variables();
+        state_vars.iter().zip(&self.hctl_vars).fold(
+            bdd_vars.mk_true(),
+            |acc, (state_var, hctl_vars)| {
+                acc.and(
+                    &bdd_vars
+                        .mk_var(*state_var)
+                        .iff(&bdd_vars.mk_var(hctl_vars[i])),
+                )
+            },
+        )
+    }
+
+    fn hctl_var_bdd_vars(&self, i: usize) -> Vec<BddVariable> {
+        self.hctl_vars.iter().map(|vars| vars[i]).collect()
+    }
+
+    /// Evaluate the formula `tree` (using the results of shared sub-formulae, if enabled), with
+    /// `var_indices` assigning HCTL variables in scope to indices of extra state variables.
+    fn eval(
+        &self,
+        tree: &HctlTreeNode,
+        var_indices: &HashMap<String, usize>,
+    ) -> Result<Bdd, EvaluationError> {
+        let Some(cache) = &self.cache else {
+            return self.eval_node(tree, var_indices);
+        };
+        // the result of a sub-formula depends on the assignment of its free variables
+        let mut assignment: Vec<(&String, &usize)> = var_indices.iter().collect();
+        assignment.sort();
+        let key = format!("{assignment:?} {}", tree.formula_str);
+        if let Some(result) = cache.borrow().get(&key) {
+            return Ok(result.clone());
+        }
+        let result = self.eval_node(tree, var_indices)?;
+        cache.borrow_mut().insert(key, result.clone());
+        Ok(result)
+    }
+
+    /// Evaluate the top-most operator of the formula `tree` (see `eval`).
+    fn eval_node(
+        &self,
+        tree: &HctlTreeNode,
+        var_indices: &HashMap<String, usize>,
+    ) -> Result<Bdd, EvaluationError> {
+        self.check()?;
+        let context = self.graph.symbolic_context();
+        let unit = self.graph.unit_colored_vertices().as_bdd();
+        let var_index = |var: &String| {
+            var_indices
+                .get(var)
+                .copied()
+                .ok_or(EvaluationError::Failed(format!(
+                    "HCTL variable `{var}` is not bound."
+                )))
+        };
+
+        let result = match &tree.node_type {
+            NodeType::TerminalNode(Atomic::True) => unit.clone(),
+            NodeType::TerminalNode(Atomic::False) => context.bdd_variable_set().mk_false(),
+            NodeType::TerminalNode(Atomic::Prop(name)) => {
+                let var = get_network(self.graph)
+                    .map_err(|e| EvaluationError::Failed(e.to_string()))?
+                    .find_variable(name)
+                    .ok_or(EvaluationError::Failed(format!(
+                        "Unknown proposition `{name}`."
+                    )))?;
+                context.mk_state_variable_is_true(var).and(unit)
+            }
+            NodeType::TerminalNode(Atomic::Var(var)) => {
+                self.mk_hctl_var_equality(var_index(var)?).and(unit)
+            }
+            NodeType::TerminalNode(Atomic::WildCardProp(name)) => {
+                return Err(EvaluationError::Failed(format!(
+                    "Wild-card proposition `{name}` is not supported."
+                )))
+            }
+            NodeType::UnaryNode(op, child) => {
+                let child = self.eval(child, var_indices)?;
+                match op {
+                    UnaryOp::Not => unit.and_not(&child),
+                    UnaryOp::Ex => self.ex(&child),
+                    UnaryOp::Ax => self.ax(&child),
+                    UnaryOp::Ef => self.reach_backward(child, None)?,
+                    // AF phi = ~EG ~phi
+                    UnaryOp::Af => unit.and_not(&self.eg(unit.and_not(&child))?),
+                    UnaryOp::Eg => self.eg(child)?,
+                    // AG phi = ~EF ~phi
+                    UnaryOp::Ag => unit.and_not(&self.reach_backward(unit.and_not(&child), None)?),
+                }
+            }
+            NodeType::BinaryNode(op, left, right) => {
+                let left = self.eval(left, var_indices)?;
+                let right = self.eval(right, var_indices)?;
+                match op {
+                    BinaryOp::And => left.and(&right),
+                    BinaryOp::Or => left.or(&right),
+                    BinaryOp::Xor => left.xor(&right).and(unit),
+                    BinaryOp::Imp => left.imp(&right).and(unit),
+                    BinaryOp::Iff => left.iff(&right).and(unit),
+                    BinaryOp::Eu => self.reach_backward(right, Some(&left))?,
+                    // A(phi U psi) = A(phi W psi) & AF psi
+                    BinaryOp::Au => self
+                        .aw(&left, &right)?
+                        .and_not(&self.eg(unit.and_not(&right))?),
+                    // E(phi W psi) = E(phi U psi) | EG phi
+                    BinaryOp::Ew => self.reach_backward(right, Some(&left))?.or(&self.eg(left)?),
+                    BinaryOp::Aw => self.aw(&left, &right)?,
+                }
+            }
+            NodeType::HybridNode(_, _, Some(domain), _) => {
+                return Err(EvaluationError::Failed(format!(
+                    "Quantifier domain `{domain}` is not supported."
+                )))
+            }
+            NodeType::HybridNode(HybridOp::Jump, var, None, child) => {
+                let i = var_index(var)?;
+                let child = self.eval(child, var_indices)?;
+                child
+                    .and(&self.mk_hctl_var_equality(i))
+                    .exists(context.state_variables())
+                    .and(unit)
+            }
+            NodeType::HybridNode(op, var, None, child) => {
+                // quantifier introduces a new variable, assigned by the number of outer quantifiers
+                let i = var_indices.len();
+                if i >= self.num_hctl_vars() {
+                    return Err(EvaluationError::Failed(format!(
+                        "Formula needs more than {} HCTL variables.",
+                        self.num_hctl_vars()
+                    )));
+                }
+                let mut inner_var_indices = var_indices.clone();
+                inner_var_indices.insert(var.clone(), i);
+                let child = self.eval(child, &inner_var_indices)?;
+                let projected = self.hctl_var_bdd_vars(i);
+                match op {
+                    HybridOp::Bind => child.and(&self.mk_hctl_var_equality(i)).exists(&projected),
+                    HybridOp::Exists => child.exists(&projected),
+                    _ => unit.and_not(&unit.and_not(&child).exists(&projected)),
+                }
+            }
+        };
+        self.check_size(&result)?;
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data_processing::create_inference_formulae::*;
+    use crate::hctl_evaluation::{EvaluationError, HctlEvaluator};
+    use crate::monitoring::{
+        CancellationToken, InferenceMonitor, InterruptGuard, InterruptReason, ResourceLimits,
+    };
+    use biodivine_hctl_model_checker::mc_utils::get_extended_symbolic_graph;
+    use biodivine_hctl_model_checker::model_checking::model_check_formula_dirty;
+    use biodivine_hctl_model_checker::preprocessing::parser::parse_and_minimize_hctl_formula;
+    use biodivine_lib_param_bn::BooleanNetwork;
+    use std::fs::read_to_string;
+
+    const TEST_MODEL: &str = r"
+v_3 -| v_1
+v_1 -?? v_2
+v_2 -> v_3
+v_3 -> v_3
+";
+
+    #[test]
+    /// Test that the results of the evaluator match those of the model checking library, and
+    /// that shared sub-formulae give the same results.
+    fn test_evaluator_matches_library() {
+        let bn = BooleanNetwork::try_from(TEST_MODEL).unwrap();
+        let graph = get_extended_symbolic_graph(&bn, 2).unwrap();
+        let guard = InterruptGuard::none();
+        let evaluator = HctlEvaluator::new(&graph, None, &guard);
+        let shared_evaluator = HctlEvaluator::new(&graph, None, &guard).share_subformulae();
+        let formulae = [
+            "!{x}: AG EF {x}",
+            "3{x}: @{x}: (v_1 & AX {x})",
+            "3{x}: @{x}: (~v_1 & EF (v_1 & v_3) & AG EF {x})",
+            "E (v_1 U v_2) & ~A (v_3 W v_2) | EG v_1",
+            "3{x}: 3{y}: (@{x}: ~v_2 & ~(EF {y})) & (@{y}: v_2 & AG EF {y})",
+            "AF v_1 | AG (v_2 | EG ~v_3)",
+            "A (v_1 U v_3) & E (v_2 W ~v_1) | A (~v_3 W v_2)",
+        ];
+        for formula in formulae {
+            let tree = parse_and_minimize_hctl_formula(graph.symbolic_context(), formula).unwrap();
+            let expected = model_check_formula_dirty(formula, &graph).unwrap();
+            assert_eq!(evaluator.evaluate(&tree).unwrap(), expected);
+            assert_eq!(shared_evaluator.evaluate(&tree).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    /// Test that a cancelled evaluation (or one with too large intermediate results) stops with
+    /// the reason of the interrupt.
+    fn test_evaluator_interrupted() {
+        let bn = BooleanNetwork::try_from(TEST_MODEL).unwrap();
+        let graph = get_extended_symbolic_graph(&bn, 1).unwrap();
+        let tree =
+            parse_and_minimize_hctl_formula(graph.symbolic_context(), "!{x}: AG EF {x}").unwrap();
+
+        let token = CancellationToken::new();
+        let guard = InferenceMonitor::new(token.clone()).mk_interrupt_guard();
+        token.cancel();
+        let result = HctlEvaluator::new(&graph, None, &guard).evaluate(&tree);
+        assert_eq!(
+            result,
+            Err(EvaluationError::Interrupted(InterruptReason::Cancelled))
+        );
+
+        let mut monitor = InferenceMonitor::new(CancellationToken::new());
+        monitor.set_resource_limits(ResourceLimits {
+            global_timeout: None,
+            property_timeout: None,
+            max_bdd_size: Some(1),
+        });
+        let guard = monitor.mk_interrupt_guard();
+        let result = HctlEvaluator::new(&graph, None, &guard).evaluate(&tree);
+        assert!(matches!(
+            result,
+            Err(EvaluationError::Interrupted(InterruptReason::BddSizeLimit(
+                _
+            )))
+        ));
+    }
+
+    /// Check that the evaluator (and the monitored evaluation, which shares sub-formulae) gives
+    /// the same results as the model checking library on the model, for formulae produced by
+    /// all generators of inference formulae (using the first two attractor states of the model).
+    fn check_evaluator_matches_library_on_model(model_path: &str, states_path: &str) {
+        let aeon_string = read_to_string(model_path).unwrap();
+        let bn = BooleanNetwork::try_from(aeon_string.as_str()).unwrap();
+        let graph = get_extended_symbolic_graph(&bn, 2).unwrap();
+        let states: Vec<String> = read_to_string(states_path)
+            .unwrap()
+            .lines()
+            .take(2)
+            .map(|line| line.to_string())
+            .collect();
+        let first = states[0].clone();
+        let last = states[states.len() - 1].clone();
+        // partially specified state (its first literal)
+        let partial = first.split('&').next().unwrap().trim().to_string();
+
+        let formulae = vec![
+            mk_formula_attractor_specific(first.clone()).unwrap(),
+            mk_formula_attractor_aeon(partial.clone()).unwrap(),
+            mk_formula_attractor(partial.clone()).unwrap(),
+            mk_formula_attractor_set(states.clone()).unwrap(),
+            mk_formula_forbid_other_attractors(states.clone()).unwrap(),
+            mk_formula_exclusive_attractors(states.clone()).unwrap(),
+            mk_formula_fixed_point_specific(first.clone()).unwrap(),
+            mk_formula_fixed_point(partial.clone()).unwrap(),
+            mk_formula_fixed_point_set(states.clone()).unwrap(),
+            mk_formula_forbid_other_fixed_points(states.clone()).unwrap(),
+            mk_formula_exclusive_fixed_points(states.clone()).unwrap(),
+            mk_formula_reachability_pair(partial.clone(), last.clone(), false).unwrap(),
+            mk_formula_reachability_pair(first.clone(), last.clone(), true).unwrap(),
+            mk_formula_reachability_chain(vec![partial, first, last]).unwrap(),
+        ];
+        let trees: Vec<_> = formulae
+            .iter()
+            .map(|formula| {
+                parse_and_minimize_hctl_formula(graph.symbolic_context(), formula).unwrap()
+            })
+            .collect();
+        let expected: Vec<_> = formulae
+            .iter()
+            .map(|formula| model_check_formula_dirty(formula, &graph).unwrap())
+            .collect();
+
+        let guard = InterruptGuard::none();
+        let evaluator = HctlEvaluator::new(&graph, None, &guard);
+        for (tree, expected) in trees.iter().zip(&expected) {
+            assert_eq!(&evaluator.evaluate(tree).unwrap(), expected);
+        }
+        // a shared token can be cancelled, so the monitor uses the evaluator
+        let token = CancellationToken::new();
+        let monitor = InferenceMonitor::new(token.clone());
+        let results = monitor.model_check_trees(trees.clone(), &graph).unwrap();
+        assert_eq!(results.unwrap(), expected);
+        // otherwise, it uses the model checking library
+        let monitor = InferenceMonitor::new(CancellationToken::new());
+        let results = monitor.model_check_trees(trees, &graph).unwrap();
+        assert_eq!(results.unwrap(), expected);
+    }
+
+    #[test]
+    /// Test that the evaluator matches the library on inference formulae [model celldivb_9v].
+    fn test_evaluator_matches_library_celldivb() {
+        check_evaluator_matches_library_on_model(
+            "benchmark_models/celldivb_9v/model_parametrized.aeon",
+            "benchmark_models/celldivb_9v/attractor_states.txt",
+        );
+    }
+
+    #[test]
+    /// Test that the evaluator matches the library on inference formulae [model eprotein_35v].
+    fn test_evaluator_matches_library_eprotein() {
+        check_evaluator_matches_library_on_model(
+            "benchmark_models/eprotein_35v/model_parametrized.aeon",
+            "benchmark_models/eprotein_35v/attractor_states.txt",
+        );
+    }
+
+    #[test]
+    /// Test that the evaluator matches the library on inference formulae [model nsp4_60v].
+    fn test_evaluator_matches_library_nsp4() {
+        check_evaluator_matches_library_on_model(
+            "benchmark_models/nsp4_60v/model_parametrized.aeon",
+            "benchmark_models/nsp4_60v/attractor_states.txt",
+        );
+    }
+}
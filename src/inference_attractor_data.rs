@@ -4,11 +4,55 @@
 use crate::data_processing::create_inference_formulae::{
     mk_formula_attractor_specific, mk_formula_fixed_point_specific,
     mk_formula_forbid_other_attractors, mk_formula_forbid_other_fixed_points,
+    validate_state_propositions,
 };
 
+use crate::errors::SketchError;
+use crate::most_permissive::MostPermissive;
+use crate::update_semantics::{TransitionRelation, UpdateSemantics};
+use crate::utils::{get_network, mk_restricted_graph};
+
 use biodivine_hctl_model_checker::model_checking::model_check_formula_unsafe_ex;
 
 use biodivine_lib_param_bn::symbolic_async_graph::{GraphColors, SymbolicAsyncGraph};
+use biodivine_lib_param_bn::BooleanNetwork;
+
+/// Generate formulae for the attractor data - a formula for each attractor state (expressing
+/// the presence of the attractor or fixed point), and if `forbid_extra_attr` is true, a formula
+/// forbidding all additional attractors (or fixed points).
+///
+/// Empty states are ignored, and propositions of all states are validated against the `bn`.
+pub fn mk_attractor_data_formulae(
+    attr_set: Vec<String>,
+    bn: &BooleanNetwork,
+    use_fixed_points: bool,
+    forbid_extra_attr: bool,
+) -> Result<(Vec<String>, Option<String>), SketchError> {
+    let attr_set: Vec<String> = attr_set.into_iter().filter(|s| !s.is_empty()).collect();
+    let prop_names: Vec<String> = bn
+        .variables()
+        .map(|var| bn.get_variable_name(var).clone())
+        .collect();
+    for attractor_state in &attr_set {
+        validate_state_propositions(attractor_state, &prop_names)?;
+    }
+    let attractor_formulae = attr_set
+        .iter()
+        .map(|attractor_state| {
+            if use_fixed_points {
+                mk_formula_fixed_point_specific(attractor_state.clone())
+            } else {
+                mk_formula_attractor_specific(attractor_state.clone())
+            }
+        })
+        .collect::<Result<Vec<String>, SketchError>>()?;
+    let forbid_formula = match (forbid_extra_attr, use_fixed_points) {
+        (false, _) => None,
+        (true, true) => Some(mk_formula_forbid_other_fixed_points(attr_set)?),
+        (true, false) => Some(mk_formula_forbid_other_attractors(attr_set)?),
+    };
+    Ok((attractor_formulae, forbid_formula))
+}
 
 /// Optimised variant for inference through the attractor data - after evaluating each dynamical
 /// property (expressing attractor presence), the set of valid colours is restricted only to
@@ -19,12 +63,62 @@ use biodivine_lib_param_bn::symbolic_async_graph::{GraphColors, SymbolicAsyncGra
 ///
 /// If `forbid_extra_attr` is true, absence of all additional attractors (not containing any
 /// specified state) is required.
+///
+/// All formulae are generated and validated (including propositions of the attractor states)
+/// before the model checking starts.
 pub fn perform_inference_with_attractors_specific(
+    attr_set: Vec<String>,
+    graph: SymbolicAsyncGraph,
+    use_fixed_points: bool,
+    forbid_extra_attr: bool,
+) -> Result<GraphColors, SketchError> {
+    perform_inference_with_attractors_under_semantics(
+        attr_set,
+        graph,
+        use_fixed_points,
+        forbid_extra_attr,
+        UpdateSemantics::Asynchronous,
+    )
+}
+
+/// Variant of `perform_inference_with_attractors_specific` with the given update `semantics`.
+///
+/// For the synchronous and the most permissive semantics, the symbolic context of the `graph`
+/// must contain an additional extra state variable for each network variable (see
+/// `TransitionRelation` and `MostPermissive`).
+pub fn perform_inference_with_attractors_under_semantics(
     attr_set: Vec<String>,
     mut graph: SymbolicAsyncGraph,
     use_fixed_points: bool,
     forbid_extra_attr: bool,
-) -> GraphColors {
+    semantics: UpdateSemantics,
+) -> Result<GraphColors, SketchError> {
+    // generate and validate all formulae before any model checking starts
+    let (attractor_formulae, forbid_formula) = mk_attractor_data_formulae(
+        attr_set,
+        get_network(&graph)?,
+        use_fixed_points,
+        forbid_extra_attr,
+    )?;
+    let transition_relation = match semantics {
+        UpdateSemantics::Synchronous => Some(TransitionRelation::synchronous(&graph)?),
+        _ => None,
+    };
+    let most_permissive = match semantics {
+        UpdateSemantics::MostPermissive => Some(MostPermissive::new(&graph)?),
+        _ => None,
+    };
+    let model_check = |formula: &str, graph: &SymbolicAsyncGraph| {
+        if let Some(most_permissive) = &most_permissive {
+            return most_permissive.model_check_property(formula, graph);
+        }
+        match &transition_relation {
+            Some(transitions) => transitions.model_check_formula(formula, graph),
+            None => model_check_formula_unsafe_ex(formula, graph),
+        }
+        .map_err(SketchError::ModelChecking)
+    };
+
     let mut inferred_colors = graph.mk_unit_colors();
     println!(
         "After applying update function properties, {} candidates remain.",
@@ -37,31 +131,13 @@ pub fn perform_inference_with_attractors_specific(
 
     // first, evaluate the properties that ensure attractor(s) existence
     println!("Computing candidates with desired attractors...");
-    for attractor_state in attr_set.clone() {
-        if attractor_state.is_empty() {
-            continue;
-        }
-
-        // automatically generate the formula
-        let formula = if use_fixed_points {
-            mk_formula_fixed_point_specific(attractor_state)
-        } else {
-            mk_formula_attractor_specific(attractor_state)
-        };
-
+    for formula in attractor_formulae {
         // compute satisfying colours
-        inferred_colors = model_check_formula_unsafe_ex(&formula, &graph)
-            .unwrap()
-            .colors();
+        inferred_colors = model_check(&formula, &graph)?.colors();
 
         // restrict the valid colors (unit_colored_set in the graph object)
         // TODO: check
-        graph = SymbolicAsyncGraph::with_custom_context(
-            graph.as_network().unwrap(),
-            graph.symbolic_context().clone(),
-            inferred_colors.as_bdd().clone(),
-        )
-        .unwrap();
+        graph = mk_restricted_graph(&graph, &inferred_colors)?;
     }
     println!(
         "After ensuring all properties regarding attractor presence, {} candidates remain.",
@@ -70,24 +146,22 @@ pub fn perform_inference_with_attractors_specific(
 
     // if desired, add the property which forbids any additional attractors that do not correspond
     // to any of the observations
-    if forbid_extra_attr {
+    if let Some(formula) = forbid_formula {
         println!("Computing candidates with no additional unwanted attractors...");
-        let formula = if use_fixed_points {
-            mk_formula_forbid_other_fixed_points(attr_set)
-        } else {
-            mk_formula_forbid_other_attractors(attr_set)
-        };
-        inferred_colors = model_check_formula_unsafe_ex(&formula, &graph)
-            .unwrap()
-            .colors();
+        inferred_colors = model_check(&formula, &graph)?.colors();
     }
 
-    inferred_colors
+    Ok(inferred_colors)
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::inference_attractor_data::perform_inference_with_attractors_specific;
+    use crate::errors::SketchError;
+    use crate::inference_attractor_data::{
+        perform_inference_with_attractors_specific,
+        perform_inference_with_attractors_under_semantics,
+    };
+    use crate::update_semantics::UpdateSemantics;
     use crate::utils::check_if_result_contains_goal_unsafe;
     use biodivine_hctl_model_checker::mc_utils::get_extended_symbolic_graph;
     use biodivine_lib_param_bn::BooleanNetwork;
@@ -112,14 +186,15 @@ mod tests {
             graph.clone(),
             true,
             false,
-        );
+        )
+        .unwrap();
         assert_eq!(
             inferred_colors.approx_cardinality(),
             two_expected_result_numbers[0]
         );
 
         let inferred_colors =
-            perform_inference_with_attractors_specific(observations, graph, true, true);
+            perform_inference_with_attractors_specific(observations, graph, true, true).unwrap();
         assert_eq!(
             inferred_colors.approx_cardinality(),
             two_expected_result_numbers[1]
@@ -141,6 +216,84 @@ mod tests {
         );
     }
 
+    #[test]
+    /// Test that invalid attractor states are reported before the model checking.
+    fn test_inference_invalid_states() {
+        let bn = BooleanNetwork::try_from("a -> b\nb -| a").unwrap();
+        let graph = get_extended_symbolic_graph(&bn, 1).unwrap();
+
+        let observations = vec!["a & ~c".to_string()];
+        let result =
+            perform_inference_with_attractors_specific(observations, graph.clone(), true, true);
+        assert_eq!(
+            result.err(),
+            Some(SketchError::UnknownVariable("c".to_string()))
+        );
+
+        let observations = vec!["a & b".to_string(), "a & ~a".to_string()];
+        let result = perform_inference_with_attractors_specific(observations, graph, false, false);
+        assert!(matches!(result.err(), Some(SketchError::InvalidFormula(_))));
+    }
+
+    #[test]
+    /// Test that the inference from fixed points gives the same candidates under all semantics
+    /// (fixed points do not depend on the semantics), and that the goal network is found under
+    /// the synchronous semantics as well.
+    fn test_inference_with_fixed_points_under_semantics() {
+        let aeon_string =
+            read_to_string("benchmark_models/celldivb_9v/model_parametrized.aeon").unwrap();
+        let bn = BooleanNetwork::try_from(aeon_string.as_str()).unwrap();
+        let goal_aeon_string =
+            read_to_string("benchmark_models/celldivb_9v/model_concrete.aeon").unwrap();
+        let observations: Vec<String> =
+            read_to_string("benchmark_models/celldivb_9v/attractor_states.txt")
+                .unwrap()
+                .lines()
+                .map(|line| line.to_string())
+                .collect();
+
+        let async_graph = get_extended_symbolic_graph(&bn, 1).unwrap();
+        let async_colors = perform_inference_with_attractors_specific(
+            observations.clone(),
+            async_graph,
+            true,
+            true,
+        )
+        .unwrap();
+        // one more extra variable for the synchronous transition relation
+        let sync_graph = get_extended_symbolic_graph(&bn, 2).unwrap();
+        let sync_colors = perform_inference_with_attractors_under_semantics(
+            observations.clone(),
+            sync_graph.clone(),
+            true,
+            true,
+            UpdateSemantics::Synchronous,
+        )
+        .unwrap();
+        assert_eq!(
+            async_colors.approx_cardinality(),
+            sync_colors.approx_cardinality()
+        );
+        assert!(check_if_result_contains_goal_unsafe(
+            sync_graph.clone(),
+            goal_aeon_string,
+            sync_colors
+        )
+        .unwrap());
+        let mp_colors = perform_inference_with_attractors_under_semantics(
+            observations,
+            sync_graph,
+            true,
+            true,
+            UpdateSemantics::MostPermissive,
+        )
+        .unwrap();
+        assert_eq!(
+            async_colors.approx_cardinality(),
+            mp_colors.approx_cardinality()
+        );
+    }
+
     /// Test if inferred colors include the color of goal network.
     /// As a test data use concrete model and try to infer it back from its steady-state data and
     /// partially defined model that was created by erasing some concrete model's update functions.
@@ -165,24 +318,25 @@ mod tests {
             graph.clone(),
             true, // only fixed-points
             true,
-        );
+        )
+        .unwrap();
         assert!(check_if_result_contains_goal_unsafe(
             graph.clone(),
             goal_aeon_string.clone(),
             inferred_colors
-        ));
+        )
+        .unwrap());
 
         let inferred_colors = perform_inference_with_attractors_specific(
             observations,
             graph.clone(),
             true, // only fixed-points
             false,
+        )
+        .unwrap();
+        assert!(
+            check_if_result_contains_goal_unsafe(graph, goal_aeon_string, inferred_colors).unwrap()
         );
-        assert!(check_if_result_contains_goal_unsafe(
-            graph,
-            goal_aeon_string,
-            inferred_colors
-        ));
     }
 
     #[test]
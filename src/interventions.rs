@@ -0,0 +1,277 @@
+//! Contains the search for interventions that control the candidate networks.
+//!
+//! An intervention is a set of perturbations (knockouts or over-expressions, see the
+//! `perturbations` module). It controls a candidate network if all attractor states of the
+//! perturbed network exhibit the target phenotype, regardless of the initial state. For each
+//! intervention, the set of controlled candidates is computed symbolically for all candidates
+//! at once.
+//!
+//! Interventions are searched by increasing size. Supersets of interventions controlling all
+//! candidates are skipped, and a superset of other interventions is only reported if it controls
+//! some additional candidates, so that only minimal interventions are reported (for each
+//! controlled candidate).
+
+use crate::data_processing::create_inference_formulae::{
+    validate_state_formula, validate_state_propositions,
+};
+use crate::errors::SketchError;
+use crate::monitoring::InferenceMonitor;
+use crate::perturbations::mk_perturbed_graph;
+use crate::update_semantics::{UpdateSchedule, UpdateSemantics};
+use crate::utils::{get_network, search_minimal_subsets, SubsetOutcome};
+
+use biodivine_hctl_model_checker::preprocessing::parser::parse_and_minimize_hctl_formula;
+
+use biodivine_lib_param_bn::biodivine_std::traits::Set;
+use biodivine_lib_param_bn::symbolic_async_graph::{GraphColors, SymbolicAsyncGraph};
+use biodivine_lib_param_bn::VariableId;
+
+use std::fmt;
+
+/// Structure to represent an intervention and the candidates it controls.
+#[derive(Clone, Debug)]
+pub struct Intervention {
+    /// Names of the perturbed variables and their fixed values.
+    pub perturbations: Vec<(String, bool)>,
+    pub controlled: GraphColors,
+    /// Number of all candidates for which the intervention was evaluated.
+    pub num_candidates: f64,
+}
+
+impl Intervention {
+    /// Number of candidates controlled by the intervention.
+    pub fn controlled_count(&self) -> f64 {
+        self.controlled.approx_cardinality()
+    }
+
+    /// Fraction of all candidates controlled by the intervention.
+    pub fn controlled_fraction(&self) -> f64 {
+        if self.num_candidates == 0. {
+            return 0.;
+        }
+        self.controlled_count() / self.num_candidates
+    }
+}
+
+impl fmt::Display for Intervention {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let perturbations: Vec<String> = self
+            .perturbations
+            .iter()
+            .map(|(var, value)| format!("{var}={}", u8::from(*value)))
+            .collect();
+        write!(
+            f,
+            "{}: controls {} of {} candidates ({:.1}%)",
+            perturbations.join(", "),
+            self.controlled_count(),
+            self.num_candidates,
+            100. * self.controlled_fraction(),
+        )
+    }
+}
+
+/// Create a formula expressing that all attractor states exhibit the `phenotype`.
+fn mk_formula_control(phenotype: &str) -> String {
+    format!("~(3{{x}}: (@{{x}}: ~({phenotype}) & (AG EF {{x}})))")
+}
+
+/// Search for minimal interventions perturbing at most `max_size` of the given `variables`, such
+/// that all attractors of the perturbed candidates (unit colors of the `graph`) exhibit the
+/// `target_phenotype` (formula describing a partial state).
+///
+/// Only interventions controlling at least the `min_fraction` of candidates (and at least one)
+/// are reported. Supersets of interventions controlling all candidates are not considered, and
+/// other supersets are only reported if they control some candidate that none of their reported
+/// subsets controls. The result is sorted by the number of controlled candidates (interventions
+/// of the same quality are ordered by size).
+///
+/// Attractors are evaluated under the update `schedule`, whose transitions are encoded for each
+/// perturbed network (the most permissive semantics is not supported). The `monitor` can
+/// interrupt the search (an interrupted search returns an error), its own transitions are not
+/// used. The graph must have at least one symbolic HCTL variable (see
+/// `get_extended_symbolic_graph`), and the extra variables needed by the schedule.
+pub fn search_interventions(
+    graph: &SymbolicAsyncGraph,
+    target_phenotype: &str,
+    variables: &[VariableId],
+    max_size: usize,
+    min_fraction: f64,
+    schedule: &UpdateSchedule,
+    monitor: &InferenceMonitor,
+) -> Result<Vec<Intervention>, SketchError> {
+    if schedule.semantics == UpdateSemantics::MostPermissive {
+        return Err(SketchError::InvalidArgument(
+            "Intervention search is not supported under the most permissive semantics.".to_string(),
+        ));
+    }
+    if !(min_fraction > 0. && min_fraction <= 1.) {
+        return Err(SketchError::InvalidArgument(format!(
+            "The fraction of controlled candidates must be in (0, 1], not `{min_fraction}`."
+        )));
+    }
+    let bn = get_network(graph)?;
+    let var_names: Vec<String> = bn
+        .variables()
+        .map(|v| bn.get_variable_name(v).clone())
+        .collect();
+    validate_state_formula(target_phenotype)?;
+    validate_state_propositions(target_phenotype, &var_names)?;
+    let formula = mk_formula_control(target_phenotype);
+    let tree = parse_and_minimize_hctl_formula(graph.symbolic_context(), &formula)
+        .map_err(SketchError::InvalidFormula)?;
+    let num_candidates = graph.mk_unit_colors().approx_cardinality();
+
+    // each variable can be perturbed to either value
+    let perturbations: Vec<(VariableId, bool)> = variables
+        .iter()
+        .flat_map(|var| [(*var, false), (*var, true)])
+        .collect();
+    let mut interventions = search_minimal_subsets(
+        &perturbations,
+        max_size,
+        |perturbations: &[(VariableId, bool)], reported_subsets: &[&Intervention]| {
+            let perturbs_twice = perturbations
+                .iter()
+                .enumerate()
+                .any(|(i, (var, _))| perturbations[..i].iter().any(|(v, _)| v == var));
+            if perturbs_twice {
+                return Ok(SubsetOutcome::Rejected);
+            }
+
+            let perturbed_graph = mk_perturbed_graph(graph, perturbations)?;
+            // the transitions of a custom schedule differ for the perturbed network
+            let mut perturbed_monitor = monitor.mk_nested_monitor();
+            if let Some(transitions) = schedule.mk_transition_relation(&perturbed_graph)? {
+                perturbed_monitor.set_transition_relation(transitions);
+            }
+            let result = perturbed_monitor.model_check_tree(tree.clone(), &perturbed_graph)?;
+            let controlled = match result {
+                Ok(result) => result.colors().intersect(graph.unit_colors()),
+                Err(reason) => {
+                    return Err(SketchError::Interrupted(format!(
+                        "Intervention search {reason}."
+                    )))
+                }
+            };
+            // candidates controlled by a smaller intervention don't count
+            let newly_controlled = reported_subsets
+                .iter()
+                .fold(controlled.clone(), |acc, smaller| {
+                    acc.minus(&smaller.controlled)
+                });
+            let count = controlled.approx_cardinality();
+            if newly_controlled.is_empty() || count < min_fraction * num_candidates {
+                return Ok(SubsetOutcome::Rejected);
+            }
+            let intervention = Intervention {
+                perturbations: perturbations
+                    .iter()
+                    .map(|(var, value)| (var_names[var.to_index()].clone(), *value))
+                    .collect(),
+                controlled,
+                num_candidates,
+            };
+            if count == num_candidates {
+                Ok(SubsetOutcome::Minimal(intervention))
+            } else {
+                Ok(SubsetOutcome::Reported(intervention))
+            }
+        },
+    )?;
+
+    // stable sort, so that equally good interventions stay ordered by size
+    interventions.sort_by(|x, y| y.controlled_count().total_cmp(&x.controlled_count()));
+    Ok(interventions)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::errors::SketchError;
+    use crate::interventions::search_interventions;
+    use crate::monitoring::{CancellationToken, InferenceMonitor};
+    use crate::update_semantics::{TransitionRelation, UpdateSchedule, UpdateSemantics};
+    use biodivine_hctl_model_checker::mc_utils::get_extended_symbolic_graph;
+    use biodivine_lib_param_bn::{BooleanNetwork, VariableId};
+
+    const TEST_MODEL: &str = r"
+v_1 -> v_2
+v_2 -| v_1
+v_2 -> v_3
+v_3 -? v_3
+";
+
+    #[test]
+    /// Test searching interventions that drive the candidates into attractors with `~v_3`
+    /// (results computed by enumeration of the 4 candidates).
+    fn test_search_interventions() {
+        let bn = BooleanNetwork::try_from(TEST_MODEL).unwrap();
+        let graph = get_extended_symbolic_graph(&bn, 1).unwrap();
+        assert_eq!(graph.mk_unit_colors().approx_cardinality(), 4.);
+        let variables: Vec<VariableId> = bn.variables().collect();
+        let schedule = UpdateSchedule::default();
+        let monitor = InferenceMonitor::new(CancellationToken::new());
+
+        // only the knockout of `v_3` works for all candidates, its supersets are skipped
+        let interventions =
+            search_interventions(&graph, "~v_3", &variables, 2, 1., &schedule, &monitor).unwrap();
+        assert_eq!(interventions.len(), 1);
+        assert_eq!(
+            interventions[0].perturbations,
+            vec![("v_3".to_string(), false)]
+        );
+        assert_eq!(interventions[0].controlled_fraction(), 1.);
+
+        // knockouts of `v_1` or `v_2` work for candidates where `v_3` does not sustain itself
+        // (their combination controls no other candidates, so it is not reported)
+        let interventions =
+            search_interventions(&graph, "~v_3", &variables, 2, 0.5, &schedule, &monitor).unwrap();
+        let counts: Vec<(String, f64)> = interventions
+            .iter()
+            .map(|i| (i.perturbations[0].0.clone(), i.controlled_count()))
+            .collect();
+        let expected = vec![
+            ("v_3".to_string(), 4.),
+            ("v_1".to_string(), 2.),
+            ("v_2".to_string(), 2.),
+        ];
+        assert_eq!(counts, expected);
+
+        let result = search_interventions(&graph, "~v_4", &variables, 1, 1., &schedule, &monitor);
+        assert!(matches!(result, Err(SketchError::UnknownVariable(_))));
+        let result = search_interventions(&graph, "~v_3", &variables, 1, 0., &schedule, &monitor);
+        assert!(matches!(result, Err(SketchError::InvalidArgument(_))));
+    }
+
+    #[test]
+    /// Test that interventions are evaluated under the transitions of the perturbed network, even
+    /// if the monitor carries the transitions of the original one.
+    fn test_search_interventions_under_schedule() {
+        let bn = BooleanNetwork::try_from(TEST_MODEL).unwrap();
+        // one more extra variable for the synchronous transition relation
+        let graph = get_extended_symbolic_graph(&bn, 2).unwrap();
+        let variables: Vec<VariableId> = bn.variables().collect();
+        let schedule = UpdateSchedule {
+            semantics: UpdateSemantics::Synchronous,
+            priority_classes: Vec::new(),
+        };
+        let mut monitor = InferenceMonitor::new(CancellationToken::new());
+        monitor.set_transition_relation(TransitionRelation::synchronous(&graph).unwrap());
+
+        // the knockout of `v_3` works for all candidates under any schedule
+        let interventions =
+            search_interventions(&graph, "~v_3", &variables, 1, 1., &schedule, &monitor).unwrap();
+        assert_eq!(interventions.len(), 1);
+        assert_eq!(
+            interventions[0].perturbations,
+            vec![("v_3".to_string(), false)]
+        );
+
+        let schedule = UpdateSchedule {
+            semantics: UpdateSemantics::MostPermissive,
+            priority_classes: Vec::new(),
+        };
+        let result = search_interventions(&graph, "~v_3", &variables, 1, 1., &schedule, &monitor);
+        assert!(matches!(result, Err(SketchError::InvalidArgument(_))));
+    }
+}
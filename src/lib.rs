@@ -1,6 +1,25 @@
 //! Library for BN inference using Boolean network sketches. It contains the high-level framework,
 //! with case studies present as binaries.
 
+pub mod benchmark_generation;
+pub mod benchmarks;
+pub mod candidate_analysis;
 pub mod data_processing;
+pub mod errors;
+pub mod experiment_design;
+pub mod explanation;
+pub mod function_classes;
+pub mod hctl_evaluation;
 pub mod inference_attractor_data;
+pub mod interventions;
+pub mod model_repair;
+pub mod monitoring;
+pub mod most_permissive;
+pub mod partitioning;
+pub mod perturbations;
+pub mod property_scheduling;
+pub mod sketch_inference;
+pub mod sketch_relaxation;
+pub mod subformula_sharing;
+pub mod update_semantics;
 pub mod utils;
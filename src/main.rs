@@ -4,27 +4,42 @@
 //! Computes the set of all consistent networks.
 //! Outputs summary of candidate set or set of witness networks, if desired.
 
-use biodivine_hctl_model_checker::mc_utils::{
-    collect_unique_hctl_vars, get_extended_symbolic_graph,
-};
 use biodivine_hctl_model_checker::preprocessing::hctl_tree::HctlTreeNode;
-use biodivine_hctl_model_checker::preprocessing::parser::parse_and_minimize_hctl_formula;
 
 use biodivine_lib_param_bn::biodivine_std::traits::Set;
-use biodivine_lib_param_bn::symbolic_async_graph::SymbolicContext;
-use biodivine_lib_param_bn::{BooleanNetwork, ModelAnnotation};
+use biodivine_lib_param_bn::{BooleanNetwork, ModelAnnotation, VariableId};
 
-use boolean_network_sketches::utils::{
-    apply_constraint_trees_and_restrict, pick_random_color, summarize_candidates_naively,
+use boolean_network_sketches::candidate_analysis::consensus::{
+    compute_consensus, print_consensus_summary,
+};
+use boolean_network_sketches::candidate_analysis::parsimony::{
+    select_most_parsimonious, ComplexityMetric,
+};
+use boolean_network_sketches::candidate_analysis::regulations::print_regulation_summary;
+use boolean_network_sketches::errors::SketchError;
+use boolean_network_sketches::experiment_design::{mk_default_experiments, rank_experiments};
+use boolean_network_sketches::explanation::explain_network_exclusion;
+use boolean_network_sketches::interventions::search_interventions;
+use boolean_network_sketches::model_repair::search_model_repairs;
+use boolean_network_sketches::monitoring::{CancellationToken, InferenceMonitor, ResourceLimits};
+use boolean_network_sketches::partitioning::{
+    infer_by_partitions, mk_color_partitions, PartitionStore,
 };
+use boolean_network_sketches::property_scheduling::{PropertyOrder, PropertyScheduler};
+use boolean_network_sketches::sketch_inference::{
+    evaluate_sketch_properties, EvaluationSettings, SketchComponents,
+};
+use boolean_network_sketches::sketch_relaxation::search_relaxations;
+use boolean_network_sketches::subformula_sharing::count_shared_subformulae;
+use boolean_network_sketches::update_semantics::UpdateSemantics;
+use boolean_network_sketches::utils::{pick_random_color, summarize_candidates_naively};
 
 use clap::Parser;
 
-use std::cmp::max;
 use std::fs::{read_to_string, File};
 use std::io::Write;
 use std::path::{Path, PathBuf};
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
 
 /// Structure to collect CLI arguments.
 #[derive(Parser)]
@@ -40,6 +55,25 @@ struct Arguments {
     #[clap(short, long, num_args = 0)]
     summarize_candidates: bool,
 
+    /// Summarize how often each regulation is functional, activating, inhibiting, or dual across
+    /// all consistent candidates (regulations unspecified in the sketch are listed first).
+    #[clap(short = 'r', long, num_args = 0)]
+    summarize_regulations: bool,
+
+    /// Write the consensus of all consistent candidates to the given file, as a partially specified
+    /// model in aeon format (update functions shared by all candidates are written out).
+    #[clap(short, long)]
+    consensus_output: Option<String>,
+
+    /// Select the most parsimonious candidates w.r.t. the complexity of update functions. The metric
+    /// is one of `essential-regulators`, `dnf-clauses`, `dnf-literals`.
+    #[clap(short = 'm', long)]
+    parsimony_metric: Option<String>,
+
+    /// Number of most parsimonious candidates to select (if 0, select all optimal ones).
+    #[clap(short = 'k', long, default_value = "0")]
+    parsimony_top_k: usize,
+
     /// Output N consistent networks at the end (if there are).
     #[clap(short, long, default_value = "0")]
     n_witnesses: i32,
@@ -48,91 +82,302 @@ struct Arguments {
     /// one network to one file (if no argument given, just print witnesses).
     #[clap(short, long, default_value = "")]
     witness_dir: String,
+
+    /// Suggest N most discriminating experiments (measurements of variables in attractors,
+    /// knockouts if a phenotype is given, and reachability between the given states) to perform
+    /// next.
+    #[clap(short = 'e', long, default_value = "0")]
+    suggest_experiments: usize,
+
+    /// Phenotype (formula describing a partial state) to observe in suggested knockout experiments.
+    #[clap(short = 'p', long)]
+    knockout_phenotype: Option<String>,
+
+    /// State (formula describing a partial state) to use in suggested reachability experiments -
+    /// reachability is tested between each pair of the given states (can be repeated).
+    #[clap(long)]
+    reachability_state: Vec<String>,
+
+    /// Search for minimal interventions (sets of perturbed variables) after which all attractors
+    /// of the candidates exhibit the given phenotype (formula describing a partial state).
+    #[clap(long)]
+    control_target: Option<String>,
+
+    /// Maximal number of variables perturbed by a searched intervention.
+    #[clap(long, default_value = "2")]
+    max_intervention_size: usize,
+
+    /// Only report interventions controlling at least the given fraction of candidates.
+    #[clap(long, default_value = "1.0")]
+    min_control_fraction: f64,
+
+    /// Order in which properties are evaluated - `fixed` (alphabetical), `heuristic` (by estimated
+    /// cost and selectivity), or `adaptive` (heuristic, updated by observed evaluation times).
+    #[clap(short = 'o', long, default_value = "fixed")]
+    property_order: String,
+
+    /// Number of threads used to evaluate properties. With more than one thread, the properties
+    /// are evaluated in parallel (and their order is not used). If 0, all available cores are used.
+    #[clap(short = 't', long, default_value = "1")]
+    threads: usize,
+
+    /// Stop the inference if it runs longer than the given number of seconds.
+    #[clap(long)]
+    timeout: Option<f64>,
+
+    /// Stop the inference if evaluating a single property takes longer than the given number of
+    /// seconds.
+    #[clap(long)]
+    property_timeout: Option<f64>,
+
+    /// Stop the inference if a BDD computed while evaluating a property (including intermediate
+    /// results) has more than the given number of nodes.
+    #[clap(long)]
+    max_bdd_size: Option<usize>,
+
+    /// Evaluate properties sharing some temporal sub-formulae together, so that the shared
+    /// sub-formulae are computed only once (requires a single thread).
+    #[clap(long)]
+    share_subformulae: bool,
+
+    /// Split the colour space into the given number of disjoint partitions that are processed
+    /// independently (one after another), and merge their results.
+    #[clap(long, default_value = "1")]
+    partitions: usize,
+
+    /// Directory where results of individual partitions are stored. When a run is repeated, the
+    /// stored results are reused (so that an interrupted run can be resumed).
+    #[clap(long)]
+    partition_dir: Option<String>,
+
+    /// Only process the partition with the given index (from 0), store its result to the
+    /// partition directory, and exit. Can be used to process partitions in separate processes,
+    /// whose results are then merged by a run without this argument.
+    #[clap(long)]
+    partition_index: Option<usize>,
+
+    /// Path to a fully specified BN model to look for in the resulting set of candidates. If it
+    /// is excluded, the violated properties are reported (with counterexamples).
+    #[clap(short, long)]
+    goal_model: Option<String>,
+
+    /// If the goal model contradicts the properties, search for minimal sets of (at most the
+    /// given number of) variables whose update functions must change to repair it.
+    #[clap(long, default_value = "0")]
+    max_repair_size: usize,
+
+    /// Maximal number of consistent update functions listed for each variable of a repair.
+    #[clap(long, default_value = "5")]
+    max_repair_functions: usize,
+
+    /// If there are no consistent candidates, search for minimal sets of (at most the given
+    /// number of) relaxations of regulations that restore the consistency.
+    #[clap(long, default_value = "0")]
+    suggest_relaxations: usize,
 }
 
-/// Read the list of named properties from an `.aeon` model annotation object.
-///
-/// The properties are expected to appear as `#!dynamic_property: NAME: FORMULA` model annotations.
-/// They are returned in alphabetic order w.r.t. the property name.
-fn read_model_properties(annotations: &ModelAnnotation) -> Result<Vec<(String, String)>, String> {
-    let Some(property_node) = annotations.get_child(&["dynamic_property"]) else {
-        return Ok(Vec::new());
-    };
-    let mut properties = Vec::with_capacity(property_node.children().len());
-    for (name, child) in property_node.children() {
-        if !child.children().is_empty() {
-            return Err(format!("Property `{name}` contains nested values."));
-        }
-        let Some(value) = child.value() else {
-            return Err(format!("Found empty dynamic property `{name}`."));
-        };
-        if value.lines().count() > 1 {
-            return Err(format!("Found multiple properties named `{name}`."));
-        }
-        properties.push((name.clone(), value.clone()));
-    }
-    // Sort alphabetically to avoid possible non-determinism down the line.
-    properties.sort_by(|(x, _), (y, _)| x.cmp(y));
-    Ok(properties)
+/// Convert a number of seconds given as an argument to a duration.
+fn parse_seconds(seconds: Option<f64>, arg_name: &str) -> Result<Option<Duration>, SketchError> {
+    seconds
+        .map(|s| {
+            Duration::try_from_secs_f64(s).map_err(|_| {
+                SketchError::InvalidArgument(format!("Invalid value `{s}` of `{arg_name}`."))
+            })
+        })
+        .transpose()
 }
 
 /// Perform the inference of Boolean networks from the input sketch.
-pub fn run_inference(
-    model_path: String,
-    summarize_candidates: bool,
-    n_witnesses: i32,
-    mut witness_dir: String,
-) -> Result<(), String> {
+fn run_inference(args: Arguments) -> Result<(), SketchError> {
+    let model_path = args.model_path.clone();
+    let n_witnesses = args.n_witnesses;
+    let mut witness_dir = args.witness_dir.clone();
     let mut rng = rand::thread_rng();
     let start = SystemTime::now();
 
+    // the time limit is measured from the very start of the computation
+    let limits = ResourceLimits {
+        global_timeout: parse_seconds(args.timeout, "timeout")?,
+        property_timeout: parse_seconds(args.property_timeout, "property-timeout")?,
+        max_bdd_size: args.max_bdd_size,
+    };
+    let mut monitor = InferenceMonitor::new(CancellationToken::new());
+    monitor.set_resource_limits(limits);
+
     // load the BN and properties from the model file
     println!("INPUT PRE-PROCESSING\n");
     if !Path::new(model_path.as_str()).is_file() {
-        return Err(format!("{model_path} is not valid file"));
+        return Err(SketchError::io(&model_path, "not a valid file"));
     }
 
     // load the model and two sets of formulae (from model annotations)
-    let Ok(aeon_string) = read_to_string(model_path.clone()) else {
-        return Err(format!("Input file `{model_path}` is not accessible."));
-    };
-    let bn = BooleanNetwork::try_from(aeon_string.as_str())?;
+    let aeon_string =
+        read_to_string(model_path.clone()).map_err(|e| SketchError::io(&model_path, e))?;
+    let bn = BooleanNetwork::try_from(aeon_string.as_str())
+        .map_err(|e| SketchError::parse_at(e, Some(&model_path), None))?;
     let annotations = ModelAnnotation::from_model_string(aeon_string.as_str());
-    let named_properties = read_model_properties(&annotations)?;
-    println!("Loaded model and properties out of `{model_path}`.");
-
     // parse formulae and compute number of symbolic vars needed to represent the HCTL properties
-    println!("Parsing formulae and generating symbolic representation...");
-    let mut num_hctl_vars = 0;
-    let mut property_trees: Vec<HctlTreeNode> = Vec::new();
-    let plain_context = SymbolicContext::new(&bn).unwrap();
-    for (_name, formula) in &named_properties {
-        let tree = parse_and_minimize_hctl_formula(&plain_context, formula.as_str())?;
-        let num_tree_vars = collect_unique_hctl_vars(tree.clone()).len();
-        num_hctl_vars = max(num_hctl_vars, num_tree_vars);
-        property_trees.push(tree);
+    let sketch = SketchComponents::read(&bn, &annotations)?;
+    let schedule = &sketch.schedule;
+    if !schedule.is_asynchronous() && args.suggest_experiments > 0 {
+        return Err(SketchError::InvalidArgument(
+            "Experiment design is only supported for the asynchronous semantics.".to_string(),
+        ));
+    }
+    if schedule.semantics == UpdateSemantics::MostPermissive && args.control_target.is_some() {
+        return Err(SketchError::InvalidArgument(
+            "Intervention search is not supported under the most permissive semantics.".to_string(),
+        ));
+    }
+    if !(args.min_control_fraction > 0. && args.min_control_fraction <= 1.) {
+        return Err(SketchError::InvalidArgument(
+            "The value of `min-control-fraction` must be in (0, 1].".to_string(),
+        ));
+    }
+    if args.share_subformulae && args.threads != 1 {
+        return Err(SketchError::InvalidArgument(
+            "Sharing of sub-formulae is only supported with a single thread.".to_string(),
+        ));
     }
+    // properties under the most permissive semantics are evaluated one by one, in the given order
+    let settings = EvaluationSettings {
+        property_order: PropertyOrder::try_from(args.property_order.as_str())?,
+        threads: args.threads,
+        share_subformulae: args.share_subformulae,
+    };
+    let property_order = settings.property_order;
+    if schedule.semantics == UpdateSemantics::MostPermissive
+        && (args.threads != 1 || property_order != PropertyOrder::Fixed || args.share_subformulae)
+    {
+        return Err(SketchError::InvalidArgument(
+            "The most permissive semantics only supports a single thread, the `fixed` property order, and no sharing of sub-formulae.".to_string(),
+        ));
+    }
+    println!("Loaded model and properties out of `{model_path}`.");
+    println!("Update semantics: {schedule}.");
+
     println!(
-        "Successfully parsed all {} properties.",
-        property_trees.len(),
+        "Successfully parsed all {} properties ({} of perturbed networks).",
+        sketch.property_trees.len() + sketch.perturbed_properties.len(),
+        sketch.perturbed_properties.len(),
     );
 
-    // Instantiate extended STG with enough variables to evaluate all formulae.
-    let Ok(graph) = get_extended_symbolic_graph(&bn, num_hctl_vars as u16) else {
-        return Err("Unable to generate STG for provided PSBN model.".to_string());
-    };
+    // Instantiate extended STG with enough variables to evaluate all formulae (and to encode
+    // the update semantics), and restrict update functions to the classes given in the sketch.
+    // Experiment design and intervention search need a symbolic variable to compute attractors.
+    let min_extra_vars = u16::from(args.suggest_experiments > 0 || args.control_target.is_some());
+    let graph = sketch.mk_graph(&bn, min_extra_vars)?;
     println!(
         "Successfully encoded model with {} variables and {} parameters.",
         graph.symbolic_context().num_state_variables(),
         graph.symbolic_context().num_parameter_variables(),
     );
+    println!(
+        "Successfully processed update function properties ({} function class restrictions).",
+        sketch.function_classes.len()
+    );
     println!("\n---------------------------------\nRUNNING THE INFERENCE\n");
 
-    println!("Successfully processed update function properties.");
+    // keep the graph without dynamic properties to explain the exclusion of the goal network
+    let sketch_graph = args.goal_model.as_ref().map(|_| graph.clone());
     println!("Processing dynamic properties...");
 
-    // perform the colored model checking
-    let graph = apply_constraint_trees_and_restrict(property_trees, graph, "- property processed");
+    // perform the colored model checking, reporting progress after each property
+    if property_order != PropertyOrder::Fixed && args.threads == 1 {
+        let scheduler = PropertyScheduler::new(sketch.property_trees.clone(), property_order);
+        println!(
+            "Initial ({property_order}) order of properties: {}",
+            scheduler.planned_order().join(", ")
+        );
+    }
+    if args.threads == 1 && args.share_subformulae {
+        let trees: Vec<&HctlTreeNode> =
+            sketch.property_trees.iter().map(|(_, tree)| tree).collect();
+        println!(
+            "Properties share {} temporal sub-formulae.",
+            count_shared_subformulae(&trees).len()
+        );
+    }
+    if let Some(transition_relation) = schedule.mk_transition_relation(&graph)? {
+        monitor.set_transition_relation(transition_relation);
+    }
+    monitor.set_progress_callback(|progress| println!("{progress}"));
+    let graph =
+        if args.partitions == 1 && args.partition_dir.is_none() && args.partition_index.is_none() {
+            let constrained = evaluate_sketch_properties(&sketch, graph, &settings, &mut monitor)?;
+            if let Some(interruption) = constrained.interruption {
+                // report the partial result - candidates satisfying properties processed so far
+                println!("{interruption}");
+                println!(
+                    "Partial result: {} candidates satisfy all properties processed before `{}`.",
+                    constrained.graph.mk_unit_colors().approx_cardinality(),
+                    interruption.property_name,
+                );
+                return Err(SketchError::Interrupted(interruption.to_string()));
+            }
+            constrained.graph
+        } else {
+            // split the colour space, and process the partitions independently
+            let partitions = mk_color_partitions(&graph, args.partitions)?;
+            let store = match &args.partition_dir {
+                Some(dir) => Some(PartitionStore::open(
+                    Path::new(dir),
+                    args.partitions,
+                    &aeon_string,
+                )?),
+                None => None,
+            };
+            let indices: Vec<usize> = match args.partition_index {
+                Some(index) if store.is_some() => vec![index],
+                Some(_) => {
+                    return Err(SketchError::InvalidArgument(
+                        "Processing a single partition requires a partition directory.".to_string(),
+                    ))
+                }
+                None => (0..args.partitions).collect(),
+            };
+            let result = infer_by_partitions(
+                &graph,
+                &partitions,
+                &indices,
+                store.as_ref(),
+                |index, partition_graph| {
+                    println!(
+                        "Processing partition {}/{} ({} candidates).",
+                        index + 1,
+                        args.partitions,
+                        partition_graph.mk_unit_colors().approx_cardinality()
+                    );
+                    evaluate_sketch_properties(&sketch, partition_graph, &settings, &mut monitor)
+                },
+            )?;
+            if result.num_loaded > 0 {
+                println!(
+                    "Results of {} partitions were loaded from previous runs.",
+                    result.num_loaded
+                );
+            }
+            if let Some((index, interruption)) = result.interruption {
+                // report the partial result - candidates of partitions processed so far
+                println!("{interruption}");
+                println!(
+                "Partial result: {} candidates found in partitions processed before partition {}.",
+                result.graph.mk_unit_colors().approx_cardinality(),
+                index + 1,
+            );
+                return Err(SketchError::Interrupted(interruption.to_string()));
+            }
+            if let Some(index) = args.partition_index {
+                println!(
+                    "Partition {}/{} processed, {} consistent candidates stored.",
+                    index + 1,
+                    args.partitions,
+                    result.graph.mk_unit_colors().approx_cardinality()
+                );
+                return Ok(());
+            }
+            result.graph
+        };
     let valid_colors = graph.mk_unit_colors(); // graph's unit colors have been restricted to consistent ones
     println!("Successfully processed all dynamic properties.");
     println!(
@@ -144,13 +389,82 @@ pub fn run_inference(
         "{} consistent candidate networks found in total.",
         valid_colors.approx_cardinality()
     );
+
+    // suggest how to relax the regulations of an inconsistent sketch
+    if valid_colors.is_empty() && args.suggest_relaxations > 0 {
+        println!("\nSearching for relaxations of the sketch...");
+        let relaxations =
+            search_relaxations(&bn, &annotations, args.suggest_relaxations, &monitor)?;
+        if relaxations.is_empty() {
+            println!(
+                "No set of at most {} relaxations restores the consistency.",
+                args.suggest_relaxations
+            );
+        }
+        for relaxation in relaxations {
+            println!("{relaxation}");
+        }
+    }
+
+    // check whether the goal network (if supplied) is among candidates, and if not, explain why
+    if let (Some(goal_path), Some(sketch_graph)) = (&args.goal_model, sketch_graph) {
+        let goal_aeon_string =
+            read_to_string(goal_path).map_err(|e| SketchError::io(goal_path, e))?;
+        let goal_bn = BooleanNetwork::try_from(goal_aeon_string.as_str())
+            .map_err(|e| SketchError::parse_at(e, Some(goal_path), None))?;
+        let goal_excluded =
+            if !schedule.is_asynchronous() || !sketch.perturbed_properties.is_empty() {
+                // counterexamples are only computed for (unperturbed) asynchronous dynamics
+                let goal_colors = graph
+                    .mk_subnetwork_colors(&goal_bn)
+                    .map_err(SketchError::SymbolicEncoding)?;
+                let goal_included = goal_colors.minus(&valid_colors).is_empty();
+                if goal_included {
+                    println!("OK - goal network is included in the candidate set.");
+                } else {
+                    println!("NOK - goal network is NOT included in the candidate set.");
+                }
+                !goal_included
+            } else {
+                let report =
+                    explain_network_exclusion(&sketch_graph, &goal_bn, &sketch.named_properties)?;
+                if report.is_excluded() {
+                    println!("NOK - goal network is NOT included in the candidate set.");
+                    println!("{report}");
+                } else {
+                    println!("OK - goal network is included in the candidate set.");
+                }
+                report.is_excluded()
+            };
+
+        // find the minimal sets of update functions that must change to fix the goal network
+        if goal_excluded && args.max_repair_size > 0 {
+            println!("\nSearching for repairs of the goal network...");
+            let repairs = search_model_repairs(
+                &goal_bn,
+                &annotations,
+                args.max_repair_size,
+                args.max_repair_functions,
+                &monitor,
+            )?;
+            if repairs.is_empty() {
+                println!(
+                    "No repair changing at most {} update functions found.",
+                    args.max_repair_size
+                );
+            }
+            for repair in repairs {
+                print!("{repair}");
+            }
+        }
+    }
     println!("\n---------------------------------");
 
     // summarize the complete results if required
-    if summarize_candidates {
+    if args.summarize_candidates {
         println!("SUMMARIZING ALL CONSISTENT CANDIDATES\n");
         println!("There are following variants of update functions for each variable:");
-        summarize_candidates_naively(&graph, valid_colors.clone(), true);
+        summarize_candidates_naively(&graph, valid_colors.clone(), true)?;
 
         if n_witnesses > 0 {
             println!("\n---------------------------------\nGENERATING WITNESSES\n");
@@ -159,6 +473,55 @@ pub fn run_inference(
         }
     }
 
+    // summarize the influence of regulations across candidates if required
+    if args.summarize_regulations {
+        println!("SUMMARIZING REGULATIONS ACROSS CONSISTENT CANDIDATES\n");
+        print_regulation_summary(&graph, &valid_colors)?;
+        println!("\n---------------------------------\n");
+    }
+
+    // rank candidates by their complexity and select the simplest ones
+    if let Some(metric) = &args.parsimony_metric {
+        let metric = ComplexityMetric::try_from(metric.as_str())?;
+        println!("SELECTING MOST PARSIMONIOUS CANDIDATES ({metric})\n");
+        let selected =
+            select_most_parsimonious(&graph, &valid_colors, metric, args.parsimony_top_k)?;
+        let mut selected_colors = graph.mk_empty_colors();
+        for (score, score_colors) in selected {
+            println!(
+                "{} selected candidates with score {score}",
+                score_colors.approx_cardinality()
+            );
+            selected_colors = selected_colors.union(&score_colors);
+        }
+        println!("\nThere are following variants of update functions for each variable:");
+        summarize_candidates_naively(&graph, selected_colors, true)?;
+        println!("\n---------------------------------\n");
+    }
+
+    // derive the update logic shared by all candidates and write the consensus model
+    if let Some(consensus_path) = &args.consensus_output {
+        println!("COMPUTING CONSENSUS OF CONSISTENT CANDIDATES\n");
+        let (consensus_bn, consensus_fns) = compute_consensus(&graph, &valid_colors)?;
+        print_consensus_summary(&graph, &consensus_fns)?;
+
+        // keep the original annotations (properties), so that the result is a valid sketch
+        let mut consensus_string: String = aeon_string
+            .lines()
+            .filter(|line| line.trim_start().starts_with("#!"))
+            .map(|line| format!("{line}\n"))
+            .collect();
+        consensus_string.push_str(consensus_bn.to_string().as_str());
+        let Ok(mut file) = File::create(consensus_path) else {
+            return Err(SketchError::io(consensus_path, "cannot create file"));
+        };
+        if file.write_all(consensus_string.as_bytes()).is_err() {
+            return Err(SketchError::io(consensus_path, "cannot write to file"));
+        }
+        println!("\nConsensus model written to `{consensus_path}`.");
+        println!("\n---------------------------------\n");
+    }
+
     // generate random witnesses if required
     let mut i = 0;
     let mut valid_colors = valid_colors;
@@ -179,20 +542,25 @@ pub fn run_inference(
             break;
         }
 
-        let c = pick_random_color(&mut rng, &graph, &valid_colors);
+        let c = pick_random_color(&mut rng, &graph, &valid_colors)?;
         let witness_bn = graph.pick_witness(&c);
 
         if witness_dir.is_empty() {
             // just print them
             println!("witness network number {}:\n", i + 1);
-            print!("{}", witness_bn.to_bnet(false).unwrap());
+            let bnet_string = witness_bn
+                .to_bnet(false)
+                .map_err(SketchError::SymbolicEncoding)?;
+            print!("{bnet_string}");
             println!("-------");
         } else {
             // write witness to its own file
             let file_name = format!("witness{}.aeon", i + 1);
             let file_path = Path::new(witness_dir.as_str()).join(file_name);
-            let mut file = File::create(file_path).unwrap();
-            file.write_all(witness_bn.to_string().as_bytes()).unwrap();
+            let path_str = file_path.to_string_lossy().to_string();
+            let mut file = File::create(file_path).map_err(|e| SketchError::io(&path_str, e))?;
+            file.write_all(witness_bn.to_string().as_bytes())
+                .map_err(|e| SketchError::io(&path_str, e))?;
             println!("witness number {} generated", i + 1);
         }
 
@@ -204,10 +572,52 @@ pub fn run_inference(
     // if some witnesses were generated, always summarize them
     if !witness_colors.is_empty() {
         println!("\nSummarization of update fns of ALL WITNESSES:");
-        summarize_candidates_naively(&graph, witness_colors, true);
+        summarize_candidates_naively(&graph, witness_colors, true)?;
+        println!("\n---------------------------------\n");
+    }
+
+    // rank possible experiments by how evenly they split the candidates
+    if args.suggest_experiments > 0 && !graph.mk_unit_colors().is_empty() {
+        println!("SUGGESTING NEXT EXPERIMENTS\n");
+        let experiments = mk_default_experiments(
+            &bn,
+            args.knockout_phenotype.as_deref(),
+            &args.reachability_state,
+        );
+        let ranking = rank_experiments(&graph, experiments)?;
+        for (i, prediction) in ranking.iter().take(args.suggest_experiments).enumerate() {
+            println!("{}. {prediction}", i + 1);
+        }
         println!("\n---------------------------------\n");
     }
 
+    // find interventions driving (enough of) the candidates into the target phenotype
+    if let Some(target) = &args.control_target {
+        if !graph.mk_unit_colors().is_empty() {
+            println!("SEARCHING INTERVENTIONS\n");
+            let variables: Vec<VariableId> = bn.variables().collect();
+            let interventions = search_interventions(
+                &graph,
+                target,
+                &variables,
+                args.max_intervention_size,
+                args.min_control_fraction,
+                schedule,
+                &monitor,
+            )?;
+            if interventions.is_empty() {
+                println!(
+                    "No intervention of size at most {} controls enough candidates.",
+                    args.max_intervention_size
+                );
+            }
+            for (i, intervention) in interventions.iter().enumerate() {
+                println!("{}. {intervention}", i + 1);
+            }
+            println!("\n---------------------------------\n");
+        }
+    }
+
     println!(
         "Total elapsed time from the start of the computation: {}ms",
         start.elapsed().unwrap().as_millis()
@@ -220,12 +630,7 @@ pub fn run_inference(
 fn main() {
     let args = Arguments::parse();
 
-    let inference_res = run_inference(
-        args.model_path,
-        args.summarize_candidates,
-        args.n_witnesses,
-        args.witness_dir,
-    );
+    let inference_res = run_inference(args);
 
     if inference_res.is_err() {
         println!("Error during computation: {}", inference_res.err().unwrap())
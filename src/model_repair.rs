@@ -0,0 +1,193 @@
+//! Contains the repair of a fully specified model that contradicts the properties of a sketch.
+//!
+//! A repair is a set of variables whose update functions must change so that the model becomes
+//! consistent with the properties. For each examined set of variables, their update functions
+//! are turned into unknown functions (of the same regulators), and the candidates of the
+//! resulting PSBN are computed. Sets are examined by increasing size, and supersets of successful
+//! repairs are skipped, so that only minimal repairs are reported.
+
+use crate::errors::SketchError;
+use crate::monitoring::InferenceMonitor;
+use crate::sketch_inference::infer_sketch_candidates;
+use crate::utils::{enumerate_update_functions, search_minimal_subsets, SubsetOutcome};
+
+use biodivine_lib_param_bn::biodivine_std::traits::Set;
+use biodivine_lib_param_bn::{BooleanNetwork, ModelAnnotation, RegulatoryGraph, VariableId};
+
+use std::fmt;
+
+/// Structure to represent a (minimal) repair of a model, and the consistent replacements of
+/// the update functions of the repaired variables.
+#[derive(Clone, Debug)]
+pub struct ModelRepair {
+    /// Names of the variables whose update functions are replaced.
+    pub variables: Vec<String>,
+    /// Number of consistent networks obtained by the repair.
+    pub num_candidates: f64,
+    /// For each repaired variable, (a limited number of) its consistent update functions.
+    pub replacements: Vec<(String, Vec<String>)>,
+}
+
+impl fmt::Display for ModelRepair {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{}: {} consistent networks",
+            self.variables.join(", "),
+            self.num_candidates,
+        )?;
+        for (var, functions) in &self.replacements {
+            write!(f, "  {var}: {}", functions.join(" | "))?;
+            if functions.len() > 1 {
+                write!(f, " ({} variants listed)", functions.len())?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Create a PSBN from the fully specified network `bn`, in which the update functions of the
+/// given `variables` are unknown. Regulations of these variables lose their monotonicity and
+/// observability, so that any function of the same regulators can replace the original one.
+pub fn mk_repair_network(
+    bn: &BooleanNetwork,
+    variables: &[VariableId],
+) -> Result<BooleanNetwork, SketchError> {
+    let var_names = bn
+        .variables()
+        .map(|v| bn.get_variable_name(v).clone())
+        .collect();
+    let mut regulatory_graph = RegulatoryGraph::new(var_names);
+    for regulation in bn.as_graph().regulations() {
+        let regulator = bn.get_variable_name(regulation.get_regulator());
+        let target = bn.get_variable_name(regulation.get_target());
+        let result = if variables.contains(&regulation.get_target()) {
+            regulatory_graph.add_regulation(regulator, target, false, None)
+        } else {
+            regulatory_graph.add_regulation(
+                regulator,
+                target,
+                regulation.is_observable(),
+                regulation.get_monotonicity(),
+            )
+        };
+        result.map_err(SketchError::SymbolicEncoding)?;
+    }
+
+    let mut repair_bn = BooleanNetwork::new(regulatory_graph);
+    for var in bn.variables() {
+        if !variables.contains(&var) {
+            repair_bn
+                .set_update_function(var, bn.get_update_function(var).clone())
+                .map_err(SketchError::SymbolicEncoding)?;
+        }
+    }
+    Ok(repair_bn)
+}
+
+/// Search for minimal repairs of the fully specified network `bn` (with at most `max_size`
+/// variables), so that it satisfies the properties given by the sketch `annotations`.
+///
+/// For each repair, at most `max_functions` consistent update functions are listed for each
+/// repaired variable. An empty result is returned if the network already satisfies the
+/// properties. Returns an error if `bn` is not fully specified. The inference of each examined
+/// repair is a nested run of the `monitor` (an interrupted search returns an error).
+pub fn search_model_repairs(
+    bn: &BooleanNetwork,
+    annotations: &ModelAnnotation,
+    max_size: usize,
+    max_functions: usize,
+    monitor: &InferenceMonitor,
+) -> Result<Vec<ModelRepair>, SketchError> {
+    let variables: Vec<VariableId> = bn.variables().collect();
+    if bn.num_parameters() > 0
+        || variables
+            .iter()
+            .any(|v| bn.get_update_function(*v).is_none())
+    {
+        return Err(SketchError::InvalidArgument(
+            "Only a fully specified model can be repaired.".to_string(),
+        ));
+    }
+    let original_graph = infer_sketch_candidates(bn, annotations, 0, monitor)?;
+    if !original_graph.mk_unit_colors().is_empty() {
+        return Ok(Vec::new());
+    }
+
+    search_minimal_subsets(&variables, max_size, |subset: &[VariableId], _| {
+        let repair_bn = mk_repair_network(bn, subset)?;
+        let graph = infer_sketch_candidates(&repair_bn, annotations, 0, monitor)?;
+        let candidates = graph.mk_unit_colors();
+        if candidates.is_empty() {
+            return Ok(SubsetOutcome::Rejected);
+        }
+        let mut replacements = Vec::with_capacity(subset.len());
+        for var in subset {
+            let functions = enumerate_update_functions(&graph, &candidates, *var, max_functions)?;
+            replacements.push((bn.get_variable_name(*var).clone(), functions));
+        }
+        Ok(SubsetOutcome::Minimal(ModelRepair {
+            variables: subset
+                .iter()
+                .map(|v| bn.get_variable_name(*v).clone())
+                .collect(),
+            num_candidates: candidates.approx_cardinality(),
+            replacements,
+        }))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model_repair::{mk_repair_network, search_model_repairs};
+    use crate::monitoring::{CancellationToken, InferenceMonitor};
+    use biodivine_lib_param_bn::{BooleanNetwork, ModelAnnotation};
+
+    const TEST_SKETCH: &str = r"
+#! dynamic_property: p1: #`3{x}: @{x}: (v_1 & v_2 & (AX {x}))`#
+#! dynamic_property: p2: #`3{x}: @{x}: (~v_1 & ~v_2 & (AX {x}))`#
+";
+
+    #[test]
+    /// Test that the repair network has unknown functions of the selected variables.
+    fn test_mk_repair_network() {
+        let bn = BooleanNetwork::try_from("a -> b\nb -| a\n$a: !b\n$b: a\n").unwrap();
+        let a = bn.find_variable("a").unwrap();
+        let repair_bn = mk_repair_network(&bn, &[a]).unwrap();
+        let b = bn.find_variable("b").unwrap();
+        assert!(repair_bn.get_update_function(a).is_none());
+        assert_eq!(repair_bn.get_update_function(b), bn.get_update_function(b));
+        let regulation = repair_bn
+            .as_graph()
+            .regulations()
+            .find(|r| r.get_target() == a);
+        assert!(!regulation.unwrap().is_observable());
+    }
+
+    #[test]
+    /// Test repair of a model (an oscillator) that should have two fixed points.
+    fn test_search_model_repairs() {
+        let annotations = ModelAnnotation::from_model_string(TEST_SKETCH);
+        let monitor = InferenceMonitor::new(CancellationToken::new());
+        let bn =
+            BooleanNetwork::try_from("v_1 -> v_2\nv_2 -| v_1\n$v_1: !v_2\n$v_2: v_1\n").unwrap();
+        let repairs = search_model_repairs(&bn, &annotations, 2, 5, &monitor).unwrap();
+        // only the function of `v_1` must change (to `v_2`), `v_2` can't be repaired alone
+        assert_eq!(repairs.len(), 1);
+        assert_eq!(repairs[0].variables, vec!["v_1".to_string()]);
+        assert_eq!(repairs[0].num_candidates, 1.);
+        assert_eq!(repairs[0].replacements[0].1.len(), 1);
+
+        // a consistent model needs no repairs
+        let bn =
+            BooleanNetwork::try_from("v_1 -> v_2\nv_2 -> v_1\n$v_1: v_2\n$v_2: v_1\n").unwrap();
+        assert!(search_model_repairs(&bn, &annotations, 2, 5, &monitor)
+            .unwrap()
+            .is_empty());
+
+        // partially specified models can't be repaired
+        let bn = BooleanNetwork::try_from("v_1 -> v_2\nv_2 -> v_1\n$v_1: v_2\n").unwrap();
+        assert!(search_model_repairs(&bn, &annotations, 2, 5, &monitor).is_err());
+    }
+}
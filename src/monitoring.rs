@@ -0,0 +1,484 @@
+//! Contains functionality to monitor the progress of long model-checking runs, to cancel them,
+//! and to limit the resources they use.
+//!
+//! The model checking library cannot be interrupted, so the monitored runs evaluate properties
+//! by the model checker of `hctl_evaluation`. It checks an `InterruptGuard` (the cancellation
+//! token and time limits) in every iteration of its fixed-point computations, so an interrupted
+//! evaluation stops promptly and the caller gets the result computed so far.
+//!
+//! The BDD size limit is enforced in the same way, on all intermediate results of the model
+//! checker, so that a single property can't exhaust the memory before its result is finished.
+//! A run that can't be interrupted (no limits are set and no one else holds its cancellation
+//! token) under the asynchronous semantics evaluates properties by the model checking library.
+
+use crate::errors::SketchError;
+use crate::hctl_evaluation::{EvaluationError, HctlEvaluator};
+use crate::update_semantics::TransitionRelation;
+
+use biodivine_hctl_model_checker::model_checking::{
+    model_check_multiple_trees_dirty, model_check_tree_dirty,
+};
+use biodivine_hctl_model_checker::preprocessing::hctl_tree::HctlTreeNode;
+
+use biodivine_lib_bdd::Bdd;
+use biodivine_lib_param_bn::symbolic_async_graph::{GraphColoredVertices, SymbolicAsyncGraph};
+
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Shared flag that can be used (from any thread) to request cancellation of a run.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Request the cancellation of all runs using this token.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Check whether the token can still be cancelled by someone else (some other copy of the
+    /// token exists).
+    pub fn is_shared(&self) -> bool {
+        Arc::strong_count(&self.cancelled) > 1
+    }
+}
+
+/// Structure describing the state of a run after a property was processed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PropertyProgress {
+    pub property_name: String,
+    /// Index of the property (starting from 1).
+    pub property_index: usize,
+    pub num_properties: usize,
+    /// Time spent evaluating this property.
+    pub property_time: Duration,
+    /// Time since the start of the run.
+    pub elapsed: Duration,
+    /// Number of candidates satisfying all properties processed so far.
+    pub num_candidates: f64,
+    /// Size (number of nodes) of the BDD representing the remaining candidates.
+    pub bdd_size: usize,
+}
+
+impl fmt::Display for PropertyProgress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "- property `{}` processed ({}/{}) in {}ms, {} candidates remain (BDD with {} nodes)",
+            self.property_name,
+            self.property_index,
+            self.num_properties,
+            self.property_time.as_millis(),
+            self.num_candidates,
+            self.bdd_size,
+        )
+    }
+}
+
+/// Limits on the resources used by a run. Limits that are `None` are not enforced.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct ResourceLimits {
+    /// Limit on the wall-clock time of the whole run.
+    pub global_timeout: Option<Duration>,
+    /// Limit on the wall-clock time of evaluating a single property.
+    pub property_timeout: Option<Duration>,
+    /// Limit on the number of nodes of any BDD computed while evaluating a property (including
+    /// the intermediate results of the model checker).
+    pub max_bdd_size: Option<usize>,
+}
+
+/// Enum of reasons why a run can be interrupted.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum InterruptReason {
+    Cancelled,
+    GlobalTimeout(Duration),
+    PropertyTimeout(Duration),
+    /// BDD of given size exceeded the limit.
+    BddSizeLimit(usize),
+}
+
+impl fmt::Display for InterruptReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InterruptReason::Cancelled => write!(f, "cancelled"),
+            InterruptReason::GlobalTimeout(limit) => {
+                write!(f, "exceeded the global timeout of {}s", limit.as_secs_f64())
+            }
+            InterruptReason::PropertyTimeout(limit) => {
+                write!(
+                    f,
+                    "exceeded the property timeout of {}s",
+                    limit.as_secs_f64()
+                )
+            }
+            InterruptReason::BddSizeLimit(size) => {
+                write!(f, "exceeded the BDD size limit (BDD with {size} nodes)")
+            }
+        }
+    }
+}
+
+/// Structure describing an interrupted run - which property was being evaluated and why the
+/// run was stopped.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Interruption {
+    pub property_name: String,
+    pub reason: InterruptReason,
+}
+
+impl fmt::Display for Interruption {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Run {} while processing property `{}`.",
+            self.reason, self.property_name
+        )
+    }
+}
+
+/// Object checked by a running computation to find out whether it should stop (or whether its
+/// intermediate results are too large). It is a snapshot of the monitor (its cancellation token
+/// and limits), so it can be also checked from other
+/// threads. Optionally, it also checks a stop flag shared by several workers.
+#[derive(Clone, Debug)]
+pub struct InterruptGuard {
+    cancellation_token: CancellationToken,
+    limits: ResourceLimits,
+    run_start: Instant,
+    property_start: Instant,
+    stop_flag: Option<Arc<AtomicBool>>,
+}
+
+impl InterruptGuard {
+    /// Create a guard that never interrupts the computation.
+    pub fn none() -> Self {
+        Self {
+            cancellation_token: CancellationToken::new(),
+            limits: ResourceLimits::default(),
+            run_start: Instant::now(),
+            property_start: Instant::now(),
+            stop_flag: None,
+        }
+    }
+
+    /// Also stop the computation when the `stop_flag` is set (reported as a cancellation).
+    pub fn with_stop_flag(mut self, stop_flag: Arc<AtomicBool>) -> Self {
+        self.stop_flag = Some(stop_flag);
+        self
+    }
+
+    /// Create a copy of the guard for the evaluation of a property that starts now.
+    pub fn for_next_property(&self) -> Self {
+        Self {
+            property_start: Instant::now(),
+            ..self.clone()
+        }
+    }
+
+    /// Check whether the computation should be interrupted (and why). The property timeout is
+    /// measured from the creation of the guard.
+    pub fn check(&self) -> Option<InterruptReason> {
+        if let Some(stop_flag) = &self.stop_flag {
+            if stop_flag.load(Ordering::SeqCst) {
+                return Some(InterruptReason::Cancelled);
+            }
+        }
+        if self.cancellation_token.is_cancelled() {
+            return Some(InterruptReason::Cancelled);
+        }
+        if let Some(limit) = self.limits.global_timeout {
+            if self.run_start.elapsed() > limit {
+                return Some(InterruptReason::GlobalTimeout(limit));
+            }
+        }
+        match self.limits.property_timeout {
+            Some(limit) if self.property_start.elapsed() > limit => {
+                Some(InterruptReason::PropertyTimeout(limit))
+            }
+            _ => None,
+        }
+    }
+
+    /// Check whether the (intermediate) result `bdd` exceeds the BDD size limit.
+    pub fn check_bdd(&self, bdd: &Bdd) -> Option<InterruptReason> {
+        match self.limits.max_bdd_size {
+            Some(limit) if bdd.size() > limit => Some(InterruptReason::BddSizeLimit(bdd.size())),
+            _ => None,
+        }
+    }
+}
+
+/// Callback reporting the progress of a run.
+pub type ProgressCallback<'a> = Box<dyn FnMut(&PropertyProgress) + 'a>;
+
+/// Object used to monitor a run - it reports progress via an (optional) callback, checks
+/// the cancellation token, and enforces resource limits.
+///
+/// Properties are evaluated under the asynchronous semantics, unless the transitions of another
+/// update schedule (synchronous semantics, priority classes, or their combination) are set (see
+/// `set_transition_relation`).
+pub struct InferenceMonitor<'a> {
+    progress_callback: Option<ProgressCallback<'a>>,
+    cancellation_token: CancellationToken,
+    limits: ResourceLimits,
+    start: Instant,
+    transition_relation: Option<TransitionRelation>,
+}
+
+impl<'a> InferenceMonitor<'a> {
+    /// Create a monitor checking the given cancellation token, without any progress callback
+    /// or resource limits. The time of the run is measured from the creation of the monitor.
+    pub fn new(cancellation_token: CancellationToken) -> Self {
+        Self {
+            progress_callback: None,
+            cancellation_token,
+            limits: ResourceLimits::default(),
+            start: Instant::now(),
+            transition_relation: None,
+        }
+    }
+
+    /// Create a monitor for a nested run (such as the inference of a variant of the sketch). It
+    /// shares the cancellation token, the resource limits, and the start time of this monitor,
+    /// but it has no progress callback, and it evaluates properties under the asynchronous
+    /// semantics (until other transitions are set).
+    pub fn mk_nested_monitor(&self) -> InferenceMonitor<'static> {
+        InferenceMonitor {
+            progress_callback: None,
+            cancellation_token: self.cancellation_token.clone(),
+            limits: self.limits.clone(),
+            start: self.start,
+            transition_relation: None,
+        }
+    }
+
+    /// Evaluate properties under the update schedule given by the `transitions` (such as the
+    /// synchronous semantics or priority classes) instead of the asynchronous semantics.
+    pub fn set_transition_relation(&mut self, transitions: TransitionRelation) {
+        self.transition_relation = Some(transitions);
+    }
+
+    /// Transitions used to evaluate properties (if the update schedule is not the plain
+    /// asynchronous one).
+    pub fn transition_relation(&self) -> Option<&TransitionRelation> {
+        self.transition_relation.as_ref()
+    }
+
+    /// Replace the transitions used to evaluate properties (such as when evaluating properties
+    /// of a perturbed network), returning the previous ones.
+    pub fn replace_transition_relation(
+        &mut self,
+        transitions: Option<TransitionRelation>,
+    ) -> Option<TransitionRelation> {
+        std::mem::replace(&mut self.transition_relation, transitions)
+    }
+
+    /// Set the resource limits enforced during the run.
+    pub fn set_resource_limits(&mut self, limits: ResourceLimits) {
+        self.limits = limits;
+    }
+
+    /// Set a callback that is called after each processed property.
+    pub fn set_progress_callback(&mut self, callback: impl FnMut(&PropertyProgress) + 'a) {
+        self.progress_callback = Some(Box::new(callback));
+    }
+
+    /// Time since the start of the run.
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    /// Report the progress to the callback (if there is one).
+    pub fn report_progress(&mut self, progress: &PropertyProgress) {
+        if let Some(callback) = self.progress_callback.as_mut() {
+            callback(progress);
+        }
+    }
+
+    /// Check whether the run should be interrupted (and why).
+    pub fn check_interrupt(&self) -> Option<InterruptReason> {
+        if self.cancellation_token.is_cancelled() {
+            return Some(InterruptReason::Cancelled);
+        }
+        match self.limits.global_timeout {
+            Some(limit) if self.elapsed() > limit => Some(InterruptReason::GlobalTimeout(limit)),
+            _ => None,
+        }
+    }
+
+    /// Check whether a BDD of the given size exceeds the limit.
+    pub fn check_bdd_size(&self, bdd_size: usize) -> Option<InterruptReason> {
+        match self.limits.max_bdd_size {
+            Some(limit) if bdd_size > limit => Some(InterruptReason::BddSizeLimit(bdd_size)),
+            _ => None,
+        }
+    }
+
+    /// Create a guard for the evaluation of a property that starts now (see `InterruptGuard`).
+    pub fn mk_interrupt_guard(&self) -> InterruptGuard {
+        InterruptGuard {
+            cancellation_token: self.cancellation_token.clone(),
+            limits: self.limits.clone(),
+            run_start: self.start,
+            property_start: Instant::now(),
+            stop_flag: None,
+        }
+    }
+
+    /// Check whether an evaluation could be interrupted (or has to be evaluated under other than
+    /// the asynchronous semantics). If not, formulae are evaluated by the model checking library.
+    fn needs_evaluator(&self) -> bool {
+        self.transition_relation.is_some()
+            || self.limits != ResourceLimits::default()
+            || self.cancellation_token.is_shared()
+            || self.cancellation_token.is_cancelled()
+    }
+
+    /// Convert the result of an interruptible evaluation (see `hctl_evaluation`).
+    fn finish_evaluation<T>(
+        result: Result<T, EvaluationError>,
+    ) -> Result<Result<T, InterruptReason>, SketchError> {
+        match result {
+            Ok(result) => Ok(Ok(result)),
+            Err(EvaluationError::Interrupted(reason)) => Ok(Err(reason)),
+            Err(EvaluationError::Failed(message)) => Err(SketchError::ModelChecking(message)),
+        }
+    }
+
+    /// Evaluate the HCTL formula `tree` on the `graph` under the semantics of the monitor,
+    /// checking for interrupts during the evaluation. The size of the result is checked against
+    /// the BDD size limit.
+    ///
+    /// Returns `Ok(Err(reason))` if the run was interrupted before the evaluation finished.
+    pub fn model_check_tree(
+        &self,
+        tree: HctlTreeNode,
+        graph: &SymbolicAsyncGraph,
+    ) -> Result<Result<GraphColoredVertices, InterruptReason>, SketchError> {
+        if !self.needs_evaluator() {
+            return model_check_tree_dirty(tree, graph)
+                .map(Ok)
+                .map_err(SketchError::ModelChecking);
+        }
+        let guard = self.mk_interrupt_guard();
+        let evaluator = HctlEvaluator::new(graph, self.transition_relation.as_ref(), &guard);
+        let result = Self::finish_evaluation(evaluator.evaluate(&tree))?;
+        Ok(
+            result.and_then(|result| match self.check_bdd_size(result.as_bdd().size()) {
+                Some(reason) => Err(reason),
+                None => Ok(result),
+            }),
+        )
+    }
+
+    /// Evaluate several HCTL formulae `trees` on the `graph` at once, sharing the results of
+    /// common sub-formulae. Otherwise, it works as `model_check_tree` (the property timeout
+    /// applies to the whole evaluation).
+    pub fn model_check_trees(
+        &self,
+        trees: Vec<HctlTreeNode>,
+        graph: &SymbolicAsyncGraph,
+    ) -> Result<Result<Vec<GraphColoredVertices>, InterruptReason>, SketchError> {
+        if !self.needs_evaluator() {
+            return model_check_multiple_trees_dirty(trees, graph)
+                .map(Ok)
+                .map_err(SketchError::ModelChecking);
+        }
+        let guard = self.mk_interrupt_guard();
+        let evaluator = HctlEvaluator::new(graph, self.transition_relation.as_ref(), &guard)
+            .share_subformulae();
+        let results: Result<Vec<_>, _> =
+            trees.iter().map(|tree| evaluator.evaluate(tree)).collect();
+        let results = Self::finish_evaluation(results)?;
+        Ok(results.and_then(|results| {
+            match results
+                .iter()
+                .find_map(|result| self.check_bdd_size(result.as_bdd().size()))
+            {
+                Some(reason) => Err(reason),
+                None => Ok(results),
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::monitoring::{
+        CancellationToken, InferenceMonitor, InterruptReason, PropertyProgress, ResourceLimits,
+    };
+    use std::time::Duration;
+
+    #[test]
+    /// Test cancelling a run via a (shared) token, and reporting the progress.
+    fn test_monitor() {
+        let token = CancellationToken::new();
+        let mut reported = Vec::new();
+        let mut monitor = InferenceMonitor::new(token.clone());
+        monitor.set_progress_callback(|progress| reported.push(progress.property_name.clone()));
+        assert!(token.is_shared());
+        assert!(!CancellationToken::new().is_shared());
+
+        assert_eq!(monitor.check_interrupt(), None);
+        let progress = PropertyProgress {
+            property_name: "p".to_string(),
+            property_index: 1,
+            num_properties: 2,
+            property_time: Duration::from_millis(5),
+            elapsed: monitor.elapsed(),
+            num_candidates: 8.,
+            bdd_size: 4,
+        };
+        monitor.report_progress(&progress);
+
+        // the copy of the token shares the flag
+        token.clone().cancel();
+        assert_eq!(monitor.check_interrupt(), Some(InterruptReason::Cancelled));
+        drop(monitor);
+        assert_eq!(reported, vec!["p".to_string()]);
+        assert_eq!(
+            progress.to_string(),
+            "- property `p` processed (1/2) in 5ms, 8 candidates remain (BDD with 4 nodes)"
+        );
+    }
+
+    #[test]
+    /// Test enforcing resource limits.
+    fn test_resource_limits() {
+        let mut monitor = InferenceMonitor::new(CancellationToken::new());
+        assert_eq!(monitor.check_interrupt(), None);
+        assert_eq!(monitor.check_bdd_size(1_000_000), None);
+
+        monitor.set_resource_limits(ResourceLimits {
+            global_timeout: Some(Duration::ZERO),
+            property_timeout: None,
+            max_bdd_size: Some(100),
+        });
+        std::thread::sleep(Duration::from_millis(1));
+        assert_eq!(
+            monitor.check_interrupt(),
+            Some(InterruptReason::GlobalTimeout(Duration::ZERO))
+        );
+        assert_eq!(monitor.check_bdd_size(100), None);
+        assert_eq!(
+            monitor.check_bdd_size(101),
+            Some(InterruptReason::BddSizeLimit(101))
+        );
+        assert_eq!(
+            InterruptReason::GlobalTimeout(Duration::from_millis(1500)).to_string(),
+            "exceeded the global timeout of 1.5s"
+        );
+    }
+}
@@ -0,0 +1,525 @@
+//! Contains the evaluation of properties generated from data under the most permissive semantics.
+//!
+//! The most permissive semantics abstracts away the timing of updates - a variable that is
+//! changing its value can be read as both 0 and 1 by its targets, until the change finishes.
+//! It thus covers the behaviour of all quantitative refinements of a network, its reachability
+//! is more permissive than under the asynchronous semantics, and its attractors are exactly the
+//! minimal trap spaces of the network. Conclusions that hold both under the asynchronous and
+//! the most permissive semantics are therefore robust w.r.t. the choice of the semantics. The
+//! semantics is selected by the sketch annotation `#! semantics: most_permissive`.
+//!
+//! The semantics is not a transition system over Boolean states, so general HCTL formulae can't
+//! be evaluated. Only properties generated from data are supported (see `recognise_property`) -
+//! reachability of time series, presence of attractors and fixed points, and absence of other
+//! attractors or fixed points. States of time series must be fully specified.
+//!
+//! All properties are evaluated symbolically for all candidate networks (colors) at once. The
+//! computation uses a copy of the state variables - the last extra state variable of each
+//! network variable in the symbolic context.
+
+use crate::data_processing::create_inference_formulae::{
+    parse_literal, split_top_level, strip_enclosing_parentheses,
+};
+use crate::errors::SketchError;
+use crate::explanation::{recognise_property, DynamicProperty};
+use crate::monitoring::{InterruptGuard, InterruptReason};
+use crate::utils::{get_network, mk_update_fn_true};
+
+use biodivine_lib_bdd::{Bdd, BddVariable};
+use biodivine_lib_param_bn::symbolic_async_graph::{GraphColoredVertices, SymbolicAsyncGraph};
+
+use std::cell::OnceCell;
+
+/// Symbolic encoding of the most permissive dynamics of all candidate networks of a graph.
+///
+/// The encoding only depends on the symbolic context of the graph, so it can be used with any
+/// graph that shares the context (such as graphs with restricted colors).
+#[derive(Clone)]
+pub struct MostPermissive {
+    /// State variables (in the order of network variables).
+    state_vars: Vec<BddVariable>,
+    /// Copy of each state variable (in the order of network variables).
+    copy_vars: Vec<BddVariable>,
+    /// BDD expressing that state variables and their copies are equal.
+    copy_equality: Bdd,
+    /// BDDs (over state variables and parameters) of the update functions.
+    update_fns: Vec<Bdd>,
+    /// For each variable, a BDD (over state variables and parameters) of the states in which
+    /// the variable is free in the smallest trap space containing the state.
+    trap_space_free: Vec<Bdd>,
+    /// States (and parameters) that are contained in a minimal trap space.
+    minimal_trap_spaces: Bdd,
+    /// Fixed points (over state variables and parameters), computed when first needed.
+    fixed_points: OnceCell<Bdd>,
+}
+
+impl MostPermissive {
+    /// Encode the most permissive dynamics of the network underlying the `graph`. The symbolic
+    /// context of the graph must contain at least one extra state variable for each network
+    /// variable (used for the copy of the state).
+    pub fn new(graph: &SymbolicAsyncGraph) -> Result<MostPermissive, SketchError> {
+        match Self::new_interruptibly(graph, &InterruptGuard::none())? {
+            Ok(most_permissive) => Ok(most_permissive),
+            Err(reason) => Err(SketchError::Interrupted(reason.to_string())),
+        }
+    }
+
+    /// Encode the most permissive dynamics (see `new`), checking the `guard` in each iteration of
+    /// the (potentially expensive) computation of trap spaces.
+    ///
+    /// Returns `Ok(Err(reason))` if the computation was interrupted.
+    pub fn new_interruptibly(
+        graph: &SymbolicAsyncGraph,
+        guard: &InterruptGuard,
+    ) -> Result<Result<MostPermissive, InterruptReason>, SketchError> {
+        let context = graph.symbolic_context();
+        let bdd_vars = context.bdd_variable_set();
+        let bn = get_network(graph)?;
+        let mut copy_vars = Vec::new();
+        let mut copy_equality = bdd_vars.mk_true();
+        let mut update_fns = Vec::new();
+        for (var, state_var) in bn.variables().zip(context.state_variables()) {
+            let Some(copy_var) = context.get_extra_state_variables(var).last() else {
+                return Err(SketchError::SymbolicEncoding(
+                    "The most permissive semantics requires an extra state variable for each variable."
+                        .to_string(),
+                ));
+            };
+            copy_equality =
+                copy_equality.and(&bdd_vars.mk_var(*copy_var).iff(&bdd_vars.mk_var(*state_var)));
+            copy_vars.push(*copy_var);
+            update_fns.push(mk_update_fn_true(graph, var)?);
+        }
+
+        let mut most_permissive = MostPermissive {
+            state_vars: context.state_variables().clone(),
+            copy_vars,
+            copy_equality,
+            update_fns,
+            trap_space_free: Vec::new(),
+            minimal_trap_spaces: bdd_vars.mk_false(),
+            fixed_points: OnceCell::new(),
+        };
+        most_permissive.trap_space_free = match most_permissive.mk_trap_space_free(graph, guard) {
+            Ok(trap_space_free) => trap_space_free,
+            Err(reason) => return Ok(Err(reason)),
+        };
+        most_permissive.minimal_trap_spaces = most_permissive.mk_minimal_trap_spaces(graph);
+        if let Some(reason) = guard
+            .check()
+            .or_else(|| guard.check_bdd(&most_permissive.minimal_trap_spaces))
+        {
+            return Ok(Err(reason));
+        }
+        Ok(Ok(most_permissive))
+    }
+
+    /// Evaluate the property given by the `formula` (see `recognise_property`) on the `graph`.
+    /// The result contains all states of the colors satisfying the property (as a result of
+    /// a closed HCTL formula would).
+    pub fn model_check_property(
+        &self,
+        formula: &str,
+        graph: &SymbolicAsyncGraph,
+    ) -> Result<GraphColoredVertices, SketchError> {
+        let Some(property) = recognise_property(formula) else {
+            return Err(SketchError::InvalidFormula(format!(
+                "Formula `{formula}` is not supported under the most permissive semantics (only properties generated from data are)."
+            )));
+        };
+        let colors = self.eval_property(&property, graph)?;
+        let bdd = colors.and(graph.unit_colored_vertices().as_bdd());
+        Ok(GraphColoredVertices::new(bdd, graph.symbolic_context()))
+    }
+
+    /// Compute the colors (as a BDD over parameters) satisfying the `property`.
+    fn eval_property(
+        &self,
+        property: &DynamicProperty,
+        graph: &SymbolicAsyncGraph,
+    ) -> Result<Bdd, SketchError> {
+        let bdd_vars = graph.symbolic_context().bdd_variable_set();
+        let fixed_points = || {
+            self.fixed_points
+                .get_or_init(|| self.mk_fixed_points(graph))
+        };
+        Ok(match property {
+            DynamicProperty::Attractor(state) => {
+                let state = self.mk_state(graph, state)?;
+                self.minimal_trap_spaces
+                    .and(&state)
+                    .exists(&self.state_vars)
+            }
+            DynamicProperty::FixedPoint(state) => {
+                let state = self.mk_state(graph, state)?;
+                fixed_points().and(&state).exists(&self.state_vars)
+            }
+            DynamicProperty::ForbidOtherAttractors(states) => {
+                // minimal trap spaces not intersecting any of the states
+                let mut intersecting = bdd_vars.mk_false();
+                for state in states {
+                    let literals = self.parse_state(graph, state)?;
+                    intersecting =
+                        intersecting.or(&self.mk_trap_space_intersects(graph, &literals));
+                }
+                self.minimal_trap_spaces
+                    .and_not(&intersecting)
+                    .exists(&self.state_vars)
+                    .not()
+            }
+            DynamicProperty::ForbidOtherFixedPoints(states) => {
+                let mut allowed = bdd_vars.mk_false();
+                for state in states {
+                    allowed = allowed.or(&self.mk_state(graph, state)?);
+                }
+                fixed_points()
+                    .and_not(&allowed)
+                    .exists(&self.state_vars)
+                    .not()
+            }
+            DynamicProperty::TimeSeries(states) => {
+                let states = states
+                    .iter()
+                    .map(|state| self.parse_full_state(graph, state))
+                    .collect::<Result<Vec<_>, _>>()?;
+                states.windows(2).fold(bdd_vars.mk_true(), |acc, pair| {
+                    acc.and(&self.mk_reachable(graph, &pair[0], &pair[1]))
+                })
+            }
+        })
+    }
+
+    /// Parse the literals of a (partial) `state` given by a conjunction of literals, as pairs of
+    /// variable indices and values.
+    fn parse_state(
+        &self,
+        graph: &SymbolicAsyncGraph,
+        state: &str,
+    ) -> Result<Vec<(usize, bool)>, SketchError> {
+        let bn = get_network(graph)?;
+        split_top_level(strip_enclosing_parentheses(state), '&')
+            .into_iter()
+            .map(|conjunct| {
+                let Some((name, value)) = parse_literal(conjunct) else {
+                    return Err(SketchError::InvalidFormula(format!(
+                        "State `{state}` is not a conjunction of literals."
+                    )));
+                };
+                let var = bn
+                    .find_variable(name)
+                    .ok_or(SketchError::UnknownVariable(name.to_string()))?;
+                Ok((var.to_index(), value))
+            })
+            .collect()
+    }
+
+    /// Parse a fully specified `state` (see `parse_state`) into values of all variables.
+    fn parse_full_state(
+        &self,
+        graph: &SymbolicAsyncGraph,
+        state: &str,
+    ) -> Result<Vec<bool>, SketchError> {
+        let mut values = vec![None; self.state_vars.len()];
+        for (i, value) in self.parse_state(graph, state)? {
+            values[i] = Some(value);
+        }
+        values.into_iter().collect::<Option<Vec<bool>>>().ok_or_else(|| {
+            SketchError::InvalidFormula(format!(
+                "Reachability under the most permissive semantics requires fully specified states, `{state}` is not."
+            ))
+        })
+    }
+
+    /// Create a BDD (over state variables) of the (partial) `state`.
+    fn mk_state(&self, graph: &SymbolicAsyncGraph, state: &str) -> Result<Bdd, SketchError> {
+        let bdd_vars = graph.symbolic_context().bdd_variable_set();
+        let literals = self.parse_state(graph, state)?;
+        Ok(literals.iter().fold(bdd_vars.mk_true(), |acc, (i, value)| {
+            acc.and(&bdd_vars.mk_literal(self.state_vars[*i], *value))
+        }))
+    }
+
+    /// Create a BDD (over state variables and parameters) of the states where the update
+    /// function of the `i`-th variable evaluates to `value`.
+    fn mk_update_to(&self, i: usize, value: bool) -> Bdd {
+        if value {
+            self.update_fns[i].clone()
+        } else {
+            self.update_fns[i].not()
+        }
+    }
+
+    /// Rename the state variables of the `bdd` to their copies (the `bdd` must not depend on
+    /// the copies).
+    fn to_copy(&self, bdd: &Bdd) -> Bdd {
+        bdd.and(&self.copy_equality).exists(&self.state_vars)
+    }
+
+    /// Compute the variables that are free in the smallest trap space containing each state.
+    ///
+    /// Starting from the state itself, a variable is freed if its update function can take
+    /// the opposite value somewhere in the current subspace, until no variable can be freed.
+    /// The `guard` is checked in each iteration.
+    fn mk_trap_space_free(
+        &self,
+        graph: &SymbolicAsyncGraph,
+        guard: &InterruptGuard,
+    ) -> Result<Vec<Bdd>, InterruptReason> {
+        let bdd_vars = graph.symbolic_context().bdd_variable_set();
+        // update functions evaluated in the copy of the state
+        let copy_update_fns: Vec<Bdd> = self.update_fns.iter().map(|f| self.to_copy(f)).collect();
+        let mut free = vec![bdd_vars.mk_false(); self.state_vars.len()];
+        loop {
+            if let Some(reason) = guard.check() {
+                return Err(reason);
+            }
+            let in_subspace = self.mk_copy_in_trap_space(graph, &free);
+            if let Some(reason) = guard.check_bdd(&in_subspace) {
+                return Err(reason);
+            }
+            let mut changed = false;
+            for (i, copy_update_fn) in copy_update_fns.iter().enumerate() {
+                let state = bdd_vars.mk_var(self.state_vars[i]);
+                let can_change = in_subspace
+                    .and(&copy_update_fn.xor(&state))
+                    .exists(&self.copy_vars);
+                let new_free = free[i].or(&can_change);
+                if new_free != free[i] {
+                    free[i] = new_free;
+                    changed = true;
+                }
+            }
+            if !changed {
+                return Ok(free);
+            }
+        }
+    }
+
+    /// Create a BDD (over state variables, their copies, and parameters) expressing that the
+    /// copy is in the subspace around the state with the `free` variables.
+    fn mk_copy_in_trap_space(&self, graph: &SymbolicAsyncGraph, free: &[Bdd]) -> Bdd {
+        let bdd_vars = graph.symbolic_context().bdd_variable_set();
+        free.iter()
+            .enumerate()
+            .fold(bdd_vars.mk_true(), |acc, (i, free_var)| {
+                let equal = bdd_vars
+                    .mk_var(self.state_vars[i])
+                    .iff(&bdd_vars.mk_var(self.copy_vars[i]));
+                acc.and(&free_var.or(&equal))
+            })
+    }
+
+    /// Compute the states contained in minimal trap spaces (the attractors of the most
+    /// permissive semantics). The smallest trap space of such a state is contained in the
+    /// smallest trap space of each of its states.
+    fn mk_minimal_trap_spaces(&self, graph: &SymbolicAsyncGraph) -> Bdd {
+        let bdd_vars = graph.symbolic_context().bdd_variable_set();
+        let copy_in_trap_space = self.mk_copy_in_trap_space(graph, &self.trap_space_free);
+        // the state is in the smallest trap space of the copy
+        let state_in_copy_trap_space = self.trap_space_free.iter().enumerate().fold(
+            bdd_vars.mk_true(),
+            |acc, (i, free_var)| {
+                let equal = bdd_vars
+                    .mk_var(self.state_vars[i])
+                    .iff(&bdd_vars.mk_var(self.copy_vars[i]));
+                acc.and(&self.to_copy(free_var).or(&equal))
+            },
+        );
+        let escaping = copy_in_trap_space
+            .and_not(&state_in_copy_trap_space)
+            .exists(&self.copy_vars);
+        graph.unit_colored_vertices().as_bdd().and_not(&escaping)
+    }
+
+    /// Create a BDD (over state variables and parameters) of the states whose smallest trap
+    /// space intersects the (partial) state given by `literals`.
+    fn mk_trap_space_intersects(
+        &self,
+        graph: &SymbolicAsyncGraph,
+        literals: &[(usize, bool)],
+    ) -> Bdd {
+        let bdd_vars = graph.symbolic_context().bdd_variable_set();
+        literals.iter().fold(bdd_vars.mk_true(), |acc, (i, value)| {
+            let literal = bdd_vars.mk_literal(self.state_vars[*i], *value);
+            acc.and(&self.trap_space_free[*i].or(&literal))
+        })
+    }
+
+    /// Compute the fixed points (over state variables and parameters).
+    fn mk_fixed_points(&self, graph: &SymbolicAsyncGraph) -> Bdd {
+        let bdd_vars = graph.symbolic_context().bdd_variable_set();
+        self.update_fns
+            .iter()
+            .zip(&self.state_vars)
+            .fold(bdd_vars.mk_true(), |acc, (update_fn, state_var)| {
+                acc.and(&update_fn.iff(&bdd_vars.mk_var(*state_var)))
+            })
+    }
+
+    /// Compute the colors (as a BDD over parameters) in which the state `to` is reachable from
+    /// the state `from` under the most permissive semantics.
+    ///
+    /// The reachability is witnessed by a set of variables that change (at least temporarily).
+    /// We compute the greatest set of candidate variables such that (a) each candidate can be
+    /// freed - its update function takes the opposite of its initial value in the subspace
+    /// around `from` given by the candidates freed before, and (b) each freed variable with
+    /// the same value in both states can return to its initial value in the final subspace.
+    /// The state `to` is reachable iff all differing variables are among the candidates.
+    fn mk_reachable(&self, graph: &SymbolicAsyncGraph, from: &[bool], to: &[bool]) -> Bdd {
+        let bdd_vars = graph.symbolic_context().bdd_variable_set();
+        let num_vars = self.state_vars.len();
+        let mk_subspace = |free: &[Bdd]| {
+            free.iter()
+                .enumerate()
+                .fold(bdd_vars.mk_true(), |acc, (i, free_var)| {
+                    acc.and(&free_var.or(&bdd_vars.mk_literal(self.state_vars[i], from[i])))
+                })
+        };
+
+        let mut candidates = vec![bdd_vars.mk_true(); num_vars];
+        loop {
+            // free the candidates that can change, until no other candidate can be freed
+            let mut free = vec![bdd_vars.mk_false(); num_vars];
+            loop {
+                let subspace = mk_subspace(&free);
+                let mut changed = false;
+                for i in 0..num_vars {
+                    let can_change = subspace
+                        .and(&self.mk_update_to(i, !from[i]))
+                        .exists(&self.state_vars)
+                        .and(&candidates[i]);
+                    let new_free = free[i].or(&can_change);
+                    if new_free != free[i] {
+                        free[i] = new_free;
+                        changed = true;
+                    }
+                }
+                if !changed {
+                    break;
+                }
+            }
+
+            // freed variables that must end with their initial value must be able to return
+            let subspace = mk_subspace(&free);
+            let next_candidates: Vec<Bdd> = (0..num_vars)
+                .map(|i| {
+                    if from[i] != to[i] {
+                        return free[i].clone();
+                    }
+                    let can_return = subspace
+                        .and(&bdd_vars.mk_literal(self.state_vars[i], !from[i]))
+                        .and(&self.mk_update_to(i, from[i]))
+                        .exists(&self.state_vars);
+                    free[i].and(&can_return)
+                })
+                .collect();
+            if next_candidates == candidates {
+                break;
+            }
+            candidates = next_candidates;
+        }
+
+        (0..num_vars)
+            .filter(|i| from[*i] != to[*i])
+            .fold(bdd_vars.mk_true(), |acc, i| acc.and(&candidates[i]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data_processing::create_inference_formulae::*;
+    use crate::errors::SketchError;
+    use crate::most_permissive::MostPermissive;
+    use biodivine_hctl_model_checker::mc_utils::get_extended_symbolic_graph;
+    use biodivine_hctl_model_checker::model_checking::model_check_formula_dirty;
+    use biodivine_lib_param_bn::biodivine_std::traits::Set;
+    use biodivine_lib_param_bn::BooleanNetwork;
+
+    /// Network with an incoherent feed-forward loop, where `c` is only activated while `a` is
+    /// switching on (under the most permissive semantics). The function of `c` is unknown.
+    const NETWORK: &str = "a -> b\nb -> c\na -| c\n$a: true\n$b: a\n";
+
+    #[test]
+    /// Test that reachability is evaluated for all colors, and that it is more permissive than
+    /// under the asynchronous semantics.
+    fn test_most_permissive_reachability() {
+        let bn = BooleanNetwork::try_from(NETWORK).unwrap();
+        let graph = get_extended_symbolic_graph(&bn, 1).unwrap();
+        let most_permissive = MostPermissive::new(&graph).unwrap();
+
+        // `c` can be `!a & b` or `!a | b` - the first one only activates `c` under mp semantics
+        let formula = mk_formula_reachability_chain(vec![
+            "~a & ~b & ~c".to_string(),
+            "a & b & c".to_string(),
+        ])
+        .unwrap();
+        let mp_result = most_permissive
+            .model_check_property(&formula, &graph)
+            .unwrap();
+        assert_eq!(mp_result.colors().approx_cardinality(), 2.);
+        let async_result = model_check_formula_dirty(formula.as_str(), &graph).unwrap();
+        assert_eq!(async_result.colors().approx_cardinality(), 1.);
+
+        // once `a` is active, `c` can only be activated by `!a | b` under both semantics
+        let formula = mk_formula_reachability_chain(vec![
+            "a & ~b & ~c".to_string(),
+            "a & b & ~c".to_string(),
+            "a & b & c".to_string(),
+        ])
+        .unwrap();
+        let mp_result = most_permissive
+            .model_check_property(&formula, &graph)
+            .unwrap();
+        assert_eq!(mp_result.colors().approx_cardinality(), 1.);
+
+        // partial states are not supported in reachability
+        let formula =
+            mk_formula_reachability_chain(vec!["~a".to_string(), "a & b".to_string()]).unwrap();
+        assert!(matches!(
+            most_permissive.model_check_property(&formula, &graph),
+            Err(SketchError::InvalidFormula(_))
+        ));
+    }
+
+    #[test]
+    /// Test evaluating attractor and fixed-point properties (attractors of the most permissive
+    /// semantics are minimal trap spaces), and rejecting unsupported formulae.
+    fn test_most_permissive_attractors() {
+        let bn = BooleanNetwork::try_from(format!("{NETWORK}$c: !a & b\n").as_str()).unwrap();
+        let graph = get_extended_symbolic_graph(&bn, 1).unwrap();
+        let most_permissive = MostPermissive::new(&graph).unwrap();
+        let holds = |formula: String| {
+            let result = most_permissive
+                .model_check_property(&formula, &graph)
+                .unwrap();
+            !result.colors().is_empty()
+        };
+
+        // the only minimal trap space is the fixed point `a & b & ~c`
+        assert!(holds(mk_formula_attractor("a & b".to_string()).unwrap()));
+        assert!(holds(
+            mk_formula_attractor_specific("a & b & ~c".to_string()).unwrap()
+        ));
+        assert!(!holds(mk_formula_attractor("~a".to_string()).unwrap()));
+        assert!(holds(
+            mk_formula_fixed_point("a & b & ~c".to_string()).unwrap()
+        ));
+        assert!(holds(
+            mk_formula_forbid_other_attractors(vec!["~c".to_string()]).unwrap()
+        ));
+        assert!(!holds(
+            mk_formula_forbid_other_attractors(vec!["c".to_string()]).unwrap()
+        ));
+        assert!(holds(
+            mk_formula_forbid_other_fixed_points(vec!["a & b & ~c".to_string()]).unwrap()
+        ));
+        assert!(!holds(
+            mk_formula_forbid_other_fixed_points(vec!["~a".to_string()]).unwrap()
+        ));
+
+        assert!(matches!(
+            most_permissive.model_check_property("EF a", &graph),
+            Err(SketchError::InvalidFormula(_))
+        ));
+    }
+}
@@ -0,0 +1,345 @@
+//! Contains functionality to split the colour space into disjoint partitions that can be
+//! processed independently (in sequence, or in separate worker processes).
+//!
+//! The partitions are given by fixing the values of selected parameter variables, and their
+//! results can be persisted on disk, so that an interrupted run can be resumed, and the results
+//! of separate workers can be merged.
+
+use crate::errors::SketchError;
+use crate::monitoring::Interruption;
+use crate::utils::{mk_restricted_graph, ConstrainedGraph};
+
+use biodivine_lib_bdd::{Bdd, BddVariable};
+use biodivine_lib_param_bn::biodivine_std::traits::Set;
+use biodivine_lib_param_bn::symbolic_async_graph::{GraphColors, SymbolicAsyncGraph};
+
+use std::fs::{create_dir_all, read_to_string, rename, write};
+use std::path::{Path, PathBuf};
+
+/// Name of the file describing the partitioned run whose results are stored in a directory.
+const MANIFEST_FILE: &str = "partitioning.txt";
+
+/// Select `num_variables` parameter variables splitting the colour space of the `graph` into
+/// halves that are as balanced as possible.
+///
+/// Each variable is scored independently (by the difference of cardinalities of the colour sets
+/// where it is true and false), ties are broken by the order of variables.
+pub fn select_partition_variables(
+    graph: &SymbolicAsyncGraph,
+    num_variables: usize,
+) -> Vec<BddVariable> {
+    let colors = graph.mk_unit_colors();
+    let mut scored_vars: Vec<(f64, BddVariable)> = graph
+        .symbolic_context()
+        .parameter_variables()
+        .iter()
+        .map(|var| {
+            let num_true = colors.as_bdd().var_select(*var, true).cardinality();
+            let num_false = colors.as_bdd().var_select(*var, false).cardinality();
+            ((num_true - num_false).abs(), *var)
+        })
+        .collect();
+    scored_vars.sort_by(|(x, _), (y, _)| x.total_cmp(y));
+    scored_vars
+        .into_iter()
+        .take(num_variables)
+        .map(|(_, var)| var)
+        .collect()
+}
+
+/// Split the colour space of the `graph` into `num_partitions` disjoint partitions covering it.
+///
+/// The values of `ceil(log2(num_partitions))` parameter variables are fixed (see
+/// `select_partition_variables`), and the resulting sub-spaces are distributed among the
+/// partitions in a round-robin fashion. Some partitions may be empty.
+pub fn mk_color_partitions(
+    graph: &SymbolicAsyncGraph,
+    num_partitions: usize,
+) -> Result<Vec<GraphColors>, SketchError> {
+    if num_partitions == 0 {
+        return Err(SketchError::InvalidArgument(
+            "Number of partitions must be positive.".to_string(),
+        ));
+    }
+    let num_variables = num_partitions.next_power_of_two().trailing_zeros() as usize;
+    if num_variables > graph.symbolic_context().num_parameter_variables() {
+        return Err(SketchError::InvalidArgument(format!(
+            "Cannot split {} parameter variables into {num_partitions} partitions.",
+            graph.symbolic_context().num_parameter_variables()
+        )));
+    }
+    let variables = select_partition_variables(graph, num_variables);
+
+    let unit_colors = graph.mk_unit_colors();
+    let mut partitions = vec![graph.mk_empty_colors(); num_partitions];
+    for i in 0..(1usize << num_variables) {
+        let valuation: Vec<(BddVariable, bool)> = variables
+            .iter()
+            .enumerate()
+            .map(|(bit, var)| (*var, (i >> bit) & 1 == 1))
+            .collect();
+        let sub_space = unit_colors.copy(unit_colors.as_bdd().select(&valuation));
+        let partition = &mut partitions[i % num_partitions];
+        *partition = partition.union(&sub_space);
+    }
+    Ok(partitions)
+}
+
+/// Compute a (stable) 64-bit FNV-1a hash of the given string.
+fn fingerprint(data: &str) -> u64 {
+    data.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Directory storing results of individual partitions of a partitioned run.
+///
+/// The directory contains a manifest with the number of partitions and a fingerprint of the
+/// sketch, so that results of different runs are never mixed.
+pub struct PartitionStore {
+    directory: PathBuf,
+}
+
+impl PartitionStore {
+    /// Open (or create) the store in `directory` for a run with `num_partitions` partitions of
+    /// the given `sketch` (its full description, such as the contents of the model file).
+    ///
+    /// Returns an error if the directory contains results of a different run.
+    pub fn open(
+        directory: &Path,
+        num_partitions: usize,
+        sketch: &str,
+    ) -> Result<PartitionStore, SketchError> {
+        let dir_name = directory.display().to_string();
+        create_dir_all(directory).map_err(|e| SketchError::io(&dir_name, e))?;
+
+        let manifest = format!(
+            "partitions: {num_partitions}\nsketch: {:016x}\n",
+            fingerprint(sketch)
+        );
+        let manifest_path = directory.join(MANIFEST_FILE);
+        if manifest_path.exists() {
+            let stored_manifest = read_to_string(&manifest_path)
+                .map_err(|e| SketchError::io(&manifest_path.display().to_string(), e))?;
+            if stored_manifest != manifest {
+                return Err(SketchError::InvalidArgument(format!(
+                    "Directory `{dir_name}` contains results of a different partitioned run."
+                )));
+            }
+        } else {
+            write(&manifest_path, manifest)
+                .map_err(|e| SketchError::io(&manifest_path.display().to_string(), e))?;
+        }
+        Ok(PartitionStore {
+            directory: directory.to_path_buf(),
+        })
+    }
+
+    /// Path to the file with the result of the partition with given index.
+    fn result_path(&self, index: usize) -> PathBuf {
+        self.directory.join(format!("partition_{index}.bdd"))
+    }
+
+    /// Load the stored result of the partition with given index, if there is one.
+    pub fn load_result(
+        &self,
+        index: usize,
+        graph: &SymbolicAsyncGraph,
+    ) -> Result<Option<GraphColors>, SketchError> {
+        let path = self.result_path(index);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let path_name = path.display().to_string();
+        let bdd_string = read_to_string(&path).map_err(|e| SketchError::io(&path_name, e))?;
+        let bdd = Bdd::read_as_string(&mut bdd_string.as_bytes())
+            .map_err(|e| SketchError::parse_at(e, Some(&path_name), None))?;
+        let expected_num_vars = graph.symbolic_context().bdd_variable_set().num_vars();
+        if bdd.num_vars() != expected_num_vars {
+            return Err(SketchError::SymbolicEncoding(format!(
+                "Result in `{path_name}` uses {} symbolic variables instead of {expected_num_vars}.",
+                bdd.num_vars()
+            )));
+        }
+        Ok(Some(graph.mk_unit_colors().copy(bdd)))
+    }
+
+    /// Store the result of the partition with given index.
+    ///
+    /// The result is written to a temporary file first, so that an interrupted write never
+    /// leaves an incomplete result behind.
+    pub fn save_result(&self, index: usize, colors: &GraphColors) -> Result<(), SketchError> {
+        let path = self.result_path(index);
+        let tmp_path = path.with_extension("bdd.tmp");
+        write(&tmp_path, colors.as_bdd().to_string())
+            .map_err(|e| SketchError::io(&tmp_path.display().to_string(), e))?;
+        rename(&tmp_path, &path).map_err(|e| SketchError::io(&path.display().to_string(), e))
+    }
+}
+
+/// Result of a partitioned inference.
+pub struct PartitionedResult {
+    /// Graph restricted to the union of results of all finished partitions.
+    pub graph: SymbolicAsyncGraph,
+    /// Number of partitions whose results were loaded from the store (instead of computed).
+    pub num_loaded: usize,
+    /// Index of the partition whose processing was interrupted, and the interruption details.
+    pub interruption: Option<(usize, Interruption)>,
+}
+
+/// Run the inference on each of the `partitions` (given by their `indices`) independently and
+/// merge the results.
+///
+/// The function `infer` applies the properties on a graph restricted to a single partition (it
+/// gets the partition index as well). If a `store` is given, results of already finished
+/// partitions are loaded from it, and newly computed results are saved to it.
+///
+/// If the inference on some partition is interrupted, the processing stops, and the result
+/// contains only the partitions finished so far.
+pub fn infer_by_partitions<F>(
+    graph: &SymbolicAsyncGraph,
+    partitions: &[GraphColors],
+    indices: &[usize],
+    store: Option<&PartitionStore>,
+    mut infer: F,
+) -> Result<PartitionedResult, SketchError>
+where
+    F: FnMut(usize, SymbolicAsyncGraph) -> Result<ConstrainedGraph, SketchError>,
+{
+    let mut merged_colors = graph.mk_empty_colors();
+    let mut num_loaded = 0;
+    let mut interruption = None;
+    for index in indices {
+        let Some(partition) = partitions.get(*index) else {
+            return Err(SketchError::InvalidArgument(format!(
+                "Partition index {index} out of range (there are {} partitions).",
+                partitions.len()
+            )));
+        };
+        let stored_colors = match store {
+            Some(store) => store.load_result(*index, graph)?,
+            None => None,
+        };
+        if let Some(colors) = stored_colors {
+            merged_colors = merged_colors.union(&colors);
+            num_loaded += 1;
+            continue;
+        }
+
+        let constrained = infer(*index, mk_restricted_graph(graph, partition)?)?;
+        if let Some(partition_interruption) = constrained.interruption {
+            interruption = Some((*index, partition_interruption));
+            break;
+        }
+        let colors = constrained.graph.mk_unit_colors();
+        if let Some(store) = store {
+            store.save_result(*index, &colors)?;
+        }
+        merged_colors = merged_colors.union(&colors);
+    }
+    Ok(PartitionedResult {
+        graph: mk_restricted_graph(graph, &merged_colors)?,
+        num_loaded,
+        interruption,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::monitoring::{CancellationToken, InferenceMonitor};
+    use crate::partitioning::{infer_by_partitions, mk_color_partitions, PartitionStore};
+    use crate::utils::apply_named_constraint_trees_and_restrict;
+    use biodivine_hctl_model_checker::mc_utils::get_extended_symbolic_graph;
+    use biodivine_hctl_model_checker::preprocessing::parser::parse_and_minimize_hctl_formula;
+    use biodivine_lib_param_bn::biodivine_std::traits::Set;
+    use biodivine_lib_param_bn::symbolic_async_graph::SymbolicAsyncGraph;
+    use biodivine_lib_param_bn::BooleanNetwork;
+
+    const TEST_MODEL: &str = r"
+v_3 -| v_1
+v_1 -?? v_2
+v_2 -? v_3
+v_1 -> v_3
+v_3 -?? v_3
+";
+
+    #[test]
+    /// Test that partitions are disjoint and cover the whole colour space.
+    fn test_partitions() {
+        let bn = BooleanNetwork::try_from(TEST_MODEL).unwrap();
+        let graph = get_extended_symbolic_graph(&bn, 1).unwrap();
+        let unit_colors = graph.mk_unit_colors();
+
+        let partitions = mk_color_partitions(&graph, 3).unwrap();
+        assert_eq!(partitions.len(), 3);
+        let mut covered = graph.mk_empty_colors();
+        for partition in &partitions {
+            assert!(partition.intersect(&covered).is_empty());
+            covered = covered.union(partition);
+        }
+        assert_eq!(covered.as_bdd(), unit_colors.as_bdd());
+
+        assert!(mk_color_partitions(&graph, 0).is_err());
+        assert!(mk_color_partitions(&graph, 1 << 20).is_err());
+    }
+
+    #[test]
+    /// Test that a partitioned inference gives the same result as the standard one, and that its
+    /// stored results are used when the run is repeated.
+    fn test_infer_by_partitions() {
+        let bn = BooleanNetwork::try_from(TEST_MODEL).unwrap();
+        let graph = get_extended_symbolic_graph(&bn, 1).unwrap();
+        let named_trees = vec![(
+            "attractor".to_string(),
+            parse_and_minimize_hctl_formula(
+                graph.symbolic_context(),
+                "3{x}: @{x}: v_1 & AG EF {x}",
+            )
+            .unwrap(),
+        )];
+        let mut monitor = InferenceMonitor::new(CancellationToken::new());
+        let expected = apply_named_constraint_trees_and_restrict(
+            named_trees.clone(),
+            graph.clone(),
+            &mut monitor,
+        )
+        .unwrap();
+
+        let directory = tempfile::tempdir().unwrap();
+        let store = PartitionStore::open(directory.path(), 4, TEST_MODEL).unwrap();
+        let partitions = mk_color_partitions(&graph, 4).unwrap();
+
+        let mut num_inferred = 0;
+        let mut infer = |_: usize, partition_graph: SymbolicAsyncGraph| {
+            num_inferred += 1;
+            apply_named_constraint_trees_and_restrict(
+                named_trees.clone(),
+                partition_graph,
+                &mut monitor,
+            )
+        };
+        let result =
+            infer_by_partitions(&graph, &partitions, &[0, 1, 2, 3], Some(&store), &mut infer)
+                .unwrap();
+        assert!(result.interruption.is_none());
+        assert_eq!(result.num_loaded, 0);
+        assert_eq!(
+            result.graph.mk_unit_colors().as_bdd(),
+            expected.graph.mk_unit_colors().as_bdd()
+        );
+
+        // second run only loads the stored results
+        let result =
+            infer_by_partitions(&graph, &partitions, &[0, 1, 2, 3], Some(&store), &mut infer)
+                .unwrap();
+        assert_eq!(result.num_loaded, 4);
+        assert_eq!(num_inferred, 4);
+        assert_eq!(
+            result.graph.mk_unit_colors().as_bdd(),
+            expected.graph.mk_unit_colors().as_bdd()
+        );
+
+        // store of a different run cannot be reused
+        assert!(PartitionStore::open(directory.path(), 2, TEST_MODEL).is_err());
+    }
+}
@@ -0,0 +1,357 @@
+//! Contains functionality regarding perturbed variants of (partially specified) networks.
+//!
+//! A perturbation fixes the value of some variables (knockout or over-expression). The perturbed
+//! graph shares the symbolic context of the original graph, so that the colors (candidate
+//! networks) of both graphs can be directly compared.
+//!
+//! Sketches can contain properties of perturbed networks, such as the loss of a fixed point
+//! after a knockout. These are given by the perturbation and an HCTL formula:
+//!
+//! ```text
+//! #! perturbed_property: healthy_lost: perturbation: Caspase=0
+//! #! perturbed_property: healthy_lost: formula: #`~(3{x}: (@{x}: (Apoptosis_ & (AX {x}))))`#
+//! ```
+
+use crate::errors::SketchError;
+use crate::monitoring::{InferenceMonitor, Interruption, PropertyProgress};
+use crate::most_permissive::MostPermissive;
+use crate::update_semantics::{UpdateSchedule, UpdateSemantics};
+use crate::utils::{get_network, mk_restricted_graph, ConstrainedGraph};
+
+use biodivine_hctl_model_checker::preprocessing::parser::parse_and_minimize_hctl_formula;
+
+use biodivine_lib_param_bn::biodivine_std::traits::Set;
+use biodivine_lib_param_bn::symbolic_async_graph::SymbolicAsyncGraph;
+use biodivine_lib_param_bn::{
+    BooleanNetwork, FnUpdate, ModelAnnotation, RegulatoryGraph, VariableId,
+};
+
+use std::collections::HashMap;
+use std::time::Instant;
+
+/// Dynamic property that must hold in the perturbed variant of each candidate network.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PerturbedProperty {
+    pub name: String,
+    pub perturbations: Vec<(VariableId, bool)>,
+    /// HCTL formula evaluated on the perturbed network.
+    pub formula: String,
+}
+
+/// Create a copy of the network `bn` in which each variable from `perturbations` has a constant
+/// update function with the given value.
+///
+/// Regulations targeting the perturbed variables are removed (their constraints can no longer be
+/// satisfied by a constant function). All parameters of the original network are kept, so the
+/// resulting network is compatible with the symbolic context of the original one.
+pub fn mk_perturbed_network(
+    bn: &BooleanNetwork,
+    perturbations: &[(VariableId, bool)],
+) -> Result<BooleanNetwork, SketchError> {
+    let fixed_values: HashMap<VariableId, bool> = perturbations.iter().cloned().collect();
+
+    let var_names = bn
+        .variables()
+        .map(|v| bn.get_variable_name(v).clone())
+        .collect();
+    let mut regulatory_graph = RegulatoryGraph::new(var_names);
+    for regulation in bn.as_graph().regulations() {
+        if fixed_values.contains_key(&regulation.get_target()) {
+            continue;
+        }
+        regulatory_graph
+            .add_regulation(
+                bn.get_variable_name(regulation.get_regulator()),
+                bn.get_variable_name(regulation.get_target()),
+                regulation.is_observable(),
+                regulation.get_monotonicity(),
+            )
+            .map_err(SketchError::SymbolicEncoding)?;
+    }
+
+    // parameters must be added in the same order, so that their IDs stay the same
+    let mut perturbed_bn = BooleanNetwork::new(regulatory_graph);
+    for param_id in bn.parameters() {
+        let parameter = bn.get_parameter(param_id);
+        perturbed_bn
+            .add_parameter(parameter.get_name(), parameter.get_arity())
+            .map_err(SketchError::SymbolicEncoding)?;
+    }
+    for var in bn.variables() {
+        let update_fn = match fixed_values.get(&var) {
+            Some(value) => Some(FnUpdate::Const(*value)),
+            None => bn.get_update_function(var).clone(),
+        };
+        perturbed_bn
+            .set_update_function(var, update_fn)
+            .map_err(SketchError::SymbolicEncoding)?;
+    }
+    Ok(perturbed_bn)
+}
+
+/// Create a graph of the perturbed variant of the network underlying the `graph`.
+///
+/// The perturbed graph uses the same symbolic context (including the extra HCTL variables) and
+/// the same unit color set as the original graph. Hence, a color of the perturbed graph
+/// represents the perturbed variant of the same candidate network.
+pub fn mk_perturbed_graph(
+    graph: &SymbolicAsyncGraph,
+    perturbations: &[(VariableId, bool)],
+) -> Result<SymbolicAsyncGraph, SketchError> {
+    let perturbed_bn = mk_perturbed_network(get_network(graph)?, perturbations)?;
+    SymbolicAsyncGraph::with_custom_context(
+        &perturbed_bn,
+        graph.symbolic_context().clone(),
+        graph.unit_colors().as_bdd().clone(),
+    )
+    .map_err(SketchError::SymbolicEncoding)
+}
+
+/// Parse perturbations given as a comma-separated list of `VAR=VALUE` items (value is 0 or 1),
+/// such as `Caspase=0, GPCR_=1`.
+pub fn parse_perturbations(
+    bn: &BooleanNetwork,
+    perturbation_str: &str,
+) -> Result<Vec<(VariableId, bool)>, SketchError> {
+    let mut perturbations = Vec::new();
+    for item in perturbation_str.split(',') {
+        let Some((var_name, value)) = item.split_once('=') else {
+            return Err(SketchError::parse(format!(
+                "Invalid perturbation `{}`.",
+                item.trim()
+            )));
+        };
+        let Some(var) = bn.find_variable(var_name.trim()) else {
+            return Err(SketchError::UnknownVariable(var_name.trim().to_string()));
+        };
+        let value = match value.trim() {
+            "1" | "true" => true,
+            "0" | "false" => false,
+            _ => {
+                return Err(SketchError::parse(format!(
+                    "Invalid value in perturbation `{}`.",
+                    item.trim()
+                )))
+            }
+        };
+        perturbations.push((var, value));
+    }
+    Ok(perturbations)
+}
+
+/// Read the perturbed properties from an `.aeon` model annotation object. Each property is given
+/// by `#! perturbed_property: NAME: perturbation: VAR=VALUE, ...` (see `parse_perturbations`,
+/// multiple lines are joined) and `#! perturbed_property: NAME: formula: FORMULA`.
+///
+/// The properties are returned in alphabetic order w.r.t. the property name.
+pub fn read_perturbed_properties(
+    bn: &BooleanNetwork,
+    annotations: &ModelAnnotation,
+) -> Result<Vec<PerturbedProperty>, SketchError> {
+    let Some(property_node) = annotations.get_child(&["perturbed_property"]) else {
+        return Ok(Vec::new());
+    };
+    let mut properties = Vec::with_capacity(property_node.children().len());
+    for (name, child) in property_node.children() {
+        let get_value = |key: &str| {
+            child.get_value(&[key]).ok_or_else(|| {
+                SketchError::parse(format!("Perturbed property `{name}` has no {key}."))
+            })
+        };
+        let perturbations =
+            parse_perturbations(bn, &get_value("perturbation")?.replace('\n', ","))?;
+        let formula = get_value("formula")?;
+        if formula.lines().count() > 1 {
+            return Err(SketchError::parse(format!(
+                "Found multiple formulae of perturbed property `{name}`."
+            )));
+        }
+        properties.push(PerturbedProperty {
+            name: name.clone(),
+            perturbations,
+            formula: formula.trim().to_string(),
+        });
+    }
+    properties.sort_by(|x, y| x.name.cmp(&y.name));
+    Ok(properties)
+}
+
+/// Apply the perturbed `properties` on the graph's colors, one by one. Each property is evaluated
+/// on the perturbed variant of the graph (see `mk_perturbed_graph`) under the update `schedule`,
+/// for all candidates at once. Otherwise, it works as `apply_named_constraint_trees_and_restrict`
+/// (the progress is reported via the `monitor`, which can also interrupt the run).
+pub fn apply_perturbed_properties(
+    properties: &[PerturbedProperty],
+    mut graph: SymbolicAsyncGraph,
+    schedule: &UpdateSchedule,
+    monitor: &mut InferenceMonitor,
+) -> Result<ConstrainedGraph, SketchError> {
+    for (i, property) in properties.iter().enumerate() {
+        let property_start = Instant::now();
+        let perturbed_graph = mk_perturbed_graph(&graph, &property.perturbations)?;
+        let result = if schedule.semantics == UpdateSemantics::MostPermissive {
+            let guard = monitor.mk_interrupt_guard();
+            match MostPermissive::new_interruptibly(&perturbed_graph, &guard)? {
+                Ok(most_permissive) => Ok(
+                    most_permissive.model_check_property(&property.formula, &perturbed_graph)?
+                ),
+                Err(reason) => Err(reason),
+            }
+        } else {
+            let tree = parse_and_minimize_hctl_formula(
+                perturbed_graph.symbolic_context(),
+                &property.formula,
+            )
+            .map_err(|e| {
+                SketchError::InvalidFormula(format!("perturbed property `{}`: {e}", property.name))
+            })?;
+            // the transitions of a custom schedule differ for the perturbed network
+            let transitions = schedule.mk_transition_relation(&perturbed_graph)?;
+            let original_transitions = monitor.replace_transition_relation(transitions);
+            let result = monitor.model_check_tree(tree, &perturbed_graph);
+            monitor.replace_transition_relation(original_transitions);
+            result?
+        };
+        let result = match result {
+            Ok(result) => result,
+            Err(reason) => {
+                let interruption = Interruption {
+                    property_name: property.name.clone(),
+                    reason,
+                };
+                return Ok(ConstrainedGraph {
+                    graph,
+                    interruption: Some(interruption),
+                });
+            }
+        };
+        // colors of the perturbed graph represent the same candidates
+        let inferred_colors = result.colors().intersect(graph.unit_colors());
+        graph = mk_restricted_graph(&graph, &inferred_colors)?;
+
+        monitor.report_progress(&PropertyProgress {
+            property_name: property.name.clone(),
+            property_index: i + 1,
+            num_properties: properties.len(),
+            property_time: property_start.elapsed(),
+            elapsed: monitor.elapsed(),
+            num_candidates: inferred_colors.approx_cardinality(),
+            bdd_size: inferred_colors.as_bdd().size(),
+        });
+    }
+    Ok(ConstrainedGraph {
+        graph,
+        interruption: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::monitoring::{CancellationToken, InferenceMonitor};
+    use crate::perturbations::{
+        apply_perturbed_properties, mk_perturbed_graph, mk_perturbed_network, parse_perturbations,
+        read_perturbed_properties, PerturbedProperty,
+    };
+    use crate::update_semantics::UpdateSchedule;
+    use biodivine_hctl_model_checker::mc_utils::get_extended_symbolic_graph;
+    use biodivine_lib_param_bn::{BooleanNetwork, FnUpdate, ModelAnnotation};
+
+    const TEST_MODEL: &str = r"
+v_1 -> v_2
+v_2 -| v_1
+v_2 -> v_3
+v_3 -? v_3
+";
+
+    #[test]
+    /// Test creating a perturbed network and parsing of perturbations.
+    fn test_perturbed_network() {
+        let bn = BooleanNetwork::try_from(TEST_MODEL).unwrap();
+        let perturbations = parse_perturbations(&bn, "v_2=0, v_3 = 1").unwrap();
+        let v_2 = bn.find_variable("v_2").unwrap();
+        let v_3 = bn.find_variable("v_3").unwrap();
+        assert_eq!(perturbations, vec![(v_2, false), (v_3, true)]);
+
+        let perturbed_bn = mk_perturbed_network(&bn, &perturbations).unwrap();
+        assert_eq!(
+            perturbed_bn.get_update_function(v_2),
+            &Some(FnUpdate::Const(false))
+        );
+        assert!(perturbed_bn.regulators(v_2).is_empty());
+        assert!(perturbed_bn.regulators(v_3).is_empty());
+
+        assert!(parse_perturbations(&bn, "v_2").is_err());
+        assert!(parse_perturbations(&bn, "v_4=1").is_err());
+        assert!(parse_perturbations(&bn, "v_2=2").is_err());
+    }
+
+    #[test]
+    /// Test that the perturbed graph shares the color space with the original one.
+    fn test_perturbed_graph() {
+        let bn = BooleanNetwork::try_from(TEST_MODEL).unwrap();
+        let graph = get_extended_symbolic_graph(&bn, 1).unwrap();
+        let v_1 = bn.find_variable("v_1").unwrap();
+
+        let perturbed_graph = mk_perturbed_graph(&graph, &[(v_1, true)]).unwrap();
+        assert_eq!(
+            perturbed_graph.mk_unit_colors().approx_cardinality(),
+            graph.mk_unit_colors().approx_cardinality()
+        );
+    }
+
+    #[test]
+    /// Test reading perturbed properties from annotations.
+    fn test_read_perturbed_properties() {
+        let bn = BooleanNetwork::try_from(TEST_MODEL).unwrap();
+        let aeon_string = "#! perturbed_property: p: perturbation: v_2=0\n#! perturbed_property: p: perturbation: v_3=1\n#! perturbed_property: p: formula: #`EF v_1`#\n";
+        let properties =
+            read_perturbed_properties(&bn, &ModelAnnotation::from_model_string(aeon_string))
+                .unwrap();
+        let expected = PerturbedProperty {
+            name: "p".to_string(),
+            perturbations: vec![
+                (bn.find_variable("v_2").unwrap(), false),
+                (bn.find_variable("v_3").unwrap(), true),
+            ],
+            formula: "EF v_1".to_string(),
+        };
+        assert_eq!(properties, vec![expected]);
+
+        let aeon_string = "#! perturbed_property: p: perturbation: v_2=0\n";
+        let result =
+            read_perturbed_properties(&bn, &ModelAnnotation::from_model_string(aeon_string));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    /// Test applying perturbed properties on all candidates at once. The unperturbed candidates
+    /// have no fixed points (due to the negative cycle), but after the knockout of `v_2`, there
+    /// is a fixed point with `~v_3` in 3 of 4 candidates (depending on the function of `v_3`).
+    fn test_apply_perturbed_properties() {
+        let bn = BooleanNetwork::try_from(TEST_MODEL).unwrap();
+        let graph = get_extended_symbolic_graph(&bn, 1).unwrap();
+        assert_eq!(graph.mk_unit_colors().approx_cardinality(), 4.);
+        let v_2 = bn.find_variable("v_2").unwrap();
+        let fixed_point = "3{x}: @{x}: (~v_3 & AX {x})";
+        let mk_property = |formula: String| PerturbedProperty {
+            name: "knockout".to_string(),
+            perturbations: vec![(v_2, false)],
+            formula,
+        };
+
+        let mut monitor = InferenceMonitor::new(CancellationToken::new());
+        let schedule = UpdateSchedule::default();
+        let properties = vec![mk_property(fixed_point.to_string())];
+        let constrained =
+            apply_perturbed_properties(&properties, graph.clone(), &schedule, &mut monitor)
+                .unwrap();
+        assert!(constrained.interruption.is_none());
+        assert_eq!(constrained.graph.mk_unit_colors().approx_cardinality(), 3.);
+
+        // the fixed point vanishes after the knockout
+        let properties = vec![mk_property(format!("~({fixed_point})"))];
+        let constrained =
+            apply_perturbed_properties(&properties, graph, &schedule, &mut monitor).unwrap();
+        assert_eq!(constrained.graph.mk_unit_colors().approx_cardinality(), 1.);
+    }
+}
@@ -0,0 +1,350 @@
+//! Contains functionality to schedule the evaluation of properties of a sketch.
+//!
+//! Properties are conjuncts and each of them restricts the colors for the following ones, so the
+//! order of evaluation strongly affects the runtime. Properties can be ordered by their estimated
+//! cost and selectivity - cheap and selective properties (such as existence of fixed points) go
+//! first, expensive and weakly selective ones (such as prohibiting other attractors) go last.
+//! The adaptive variant re-estimates the remaining properties after each evaluation, using the
+//! observed evaluation times and candidate reductions.
+
+use crate::errors::SketchError;
+
+use biodivine_hctl_model_checker::mc_utils::collect_unique_hctl_vars;
+use biodivine_hctl_model_checker::preprocessing::hctl_tree::{HctlTreeNode, NodeType};
+use biodivine_hctl_model_checker::preprocessing::operator_enums::{BinaryOp, UnaryOp};
+
+use std::collections::HashMap;
+use std::fmt;
+use std::time::Duration;
+
+/// Assumed fraction of candidates that remain after evaluating a property (before anything is
+/// observed).
+const DEFAULT_REMAINING_FRACTION: f64 = 0.5;
+/// Assumed fraction of remaining candidates for properties that are negations (they usually
+/// prohibit some behaviour and thus remove only few candidates).
+const NEGATED_REMAINING_FRACTION: f64 = 0.9;
+
+/// Enum of possible orders of property evaluation.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum PropertyOrder {
+    /// Keep the order in which the properties are given.
+    Fixed,
+    /// Order the properties by their estimated cost and selectivity (once, at the start).
+    Heuristic,
+    /// Order the properties by their estimated cost and selectivity, and update the estimates
+    /// after each evaluation using the observed times and candidate reductions.
+    Adaptive,
+}
+
+impl fmt::Display for PropertyOrder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PropertyOrder::Fixed => write!(f, "fixed"),
+            PropertyOrder::Heuristic => write!(f, "heuristic"),
+            PropertyOrder::Adaptive => write!(f, "adaptive"),
+        }
+    }
+}
+
+impl TryFrom<&str> for PropertyOrder {
+    type Error = SketchError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "fixed" => Ok(PropertyOrder::Fixed),
+            "heuristic" => Ok(PropertyOrder::Heuristic),
+            "adaptive" => Ok(PropertyOrder::Adaptive),
+            _ => Err(SketchError::parse(format!(
+                "Invalid property order `{value}`."
+            ))),
+        }
+    }
+}
+
+/// Enum of classes of properties, given by the maximal nesting of fixed-point operators.
+/// Properties of the same class are assumed to behave similarly during adaptive reordering.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum PropertyClass {
+    /// No fixed-point operators (only `EX`/`AX`), such as fixed-point properties.
+    Local,
+    /// Non-nested fixed-point operators, such as reachability.
+    Reachability,
+    /// Nested fixed-point operators, such as attractor properties.
+    Recurrence,
+}
+
+/// Estimated cost of a single operator of the HCTL formula.
+fn operator_cost(node_type: &NodeType) -> f64 {
+    match node_type {
+        NodeType::TerminalNode(_) => 1.,
+        NodeType::UnaryNode(UnaryOp::Not, _) => 1.,
+        NodeType::UnaryNode(UnaryOp::Ex | UnaryOp::Ax, _) => 4.,
+        NodeType::UnaryNode(_, _) => 16.,
+        NodeType::BinaryNode(BinaryOp::Eu | BinaryOp::Au | BinaryOp::Ew | BinaryOp::Aw, _, _) => {
+            16.
+        }
+        NodeType::BinaryNode(_, _, _) => 1.,
+        NodeType::HybridNode(..) => 4.,
+    }
+}
+
+/// Check whether the operator is evaluated as a fixed-point computation.
+fn is_fixed_point_operator(node_type: &NodeType) -> bool {
+    match node_type {
+        NodeType::UnaryNode(op, _) => !matches!(op, UnaryOp::Not | UnaryOp::Ex | UnaryOp::Ax),
+        NodeType::BinaryNode(op, _, _) => {
+            matches!(
+                op,
+                BinaryOp::Eu | BinaryOp::Au | BinaryOp::Ew | BinaryOp::Aw
+            )
+        }
+        _ => false,
+    }
+}
+
+/// Direct sub-formulae of the formula `tree`.
+fn children(tree: &HctlTreeNode) -> Vec<&HctlTreeNode> {
+    match &tree.node_type {
+        NodeType::TerminalNode(_) => Vec::new(),
+        NodeType::UnaryNode(_, child) => vec![child],
+        NodeType::BinaryNode(_, left, right) => vec![left, right],
+        NodeType::HybridNode(.., child) => vec![child],
+    }
+}
+
+/// Compute the sum of estimated costs of operators, and the maximal nesting of fixed-point
+/// operators in the formula `tree`.
+fn analyse_formula(tree: &HctlTreeNode) -> (f64, usize) {
+    let mut cost = operator_cost(&tree.node_type);
+    let mut nesting = 0;
+    for child in children(tree) {
+        let (child_cost, child_nesting) = analyse_formula(child);
+        cost += child_cost;
+        nesting = nesting.max(child_nesting);
+    }
+    if is_fixed_point_operator(&tree.node_type) {
+        nesting += 1;
+    }
+    (cost, nesting)
+}
+
+/// Estimate the cost of evaluating the formula `tree`.
+///
+/// Sub-formulae are evaluated one by one (bottom-up), so the costs of operators are summed. Each
+/// HCTL variable adds a copy of state variables to the symbolic representation, which is
+/// reflected by doubling the cost.
+pub fn estimate_cost(tree: &HctlTreeNode) -> f64 {
+    let num_hctl_vars = collect_unique_hctl_vars(tree.clone()).len();
+    analyse_formula(tree).0 * 2f64.powi(num_hctl_vars as i32)
+}
+
+/// Classify the formula `tree` by the nesting of its fixed-point operators.
+pub fn classify_property(tree: &HctlTreeNode) -> PropertyClass {
+    match analyse_formula(tree).1 {
+        0 => PropertyClass::Local,
+        1 => PropertyClass::Reachability,
+        _ => PropertyClass::Recurrence,
+    }
+}
+
+/// Property waiting to be evaluated, with its estimates.
+#[derive(Clone, Debug)]
+struct ScheduledProperty {
+    name: String,
+    tree: HctlTreeNode,
+    class: PropertyClass,
+    estimated_cost: f64,
+    prior_remaining_fraction: f64,
+}
+
+/// Statistics collected for evaluated properties of a class.
+#[derive(Clone, Copy, Debug, Default)]
+struct ClassStatistics {
+    num_evaluated: usize,
+    /// Sum of ratios between the observed evaluation time (in ms) and the estimated cost.
+    sum_time_per_cost: f64,
+    /// Sum of observed fractions of candidates that remained after the evaluation.
+    sum_remaining_fraction: f64,
+}
+
+/// Object deciding the order in which properties are evaluated.
+pub struct PropertyScheduler {
+    order: PropertyOrder,
+    pending: Vec<ScheduledProperty>,
+    last_taken: Option<ScheduledProperty>,
+    statistics: HashMap<PropertyClass, ClassStatistics>,
+}
+
+impl PropertyScheduler {
+    /// Create a scheduler for the named properties given by HCTL formula trees.
+    pub fn new(named_trees: Vec<(String, HctlTreeNode)>, order: PropertyOrder) -> Self {
+        let pending = named_trees
+            .into_iter()
+            .map(|(name, tree)| {
+                let is_negation = matches!(tree.node_type, NodeType::UnaryNode(UnaryOp::Not, _));
+                ScheduledProperty {
+                    name,
+                    class: classify_property(&tree),
+                    estimated_cost: estimate_cost(&tree),
+                    prior_remaining_fraction: if is_negation {
+                        NEGATED_REMAINING_FRACTION
+                    } else {
+                        DEFAULT_REMAINING_FRACTION
+                    },
+                    tree,
+                }
+            })
+            .collect();
+        let mut scheduler = PropertyScheduler {
+            order,
+            pending,
+            last_taken: None,
+            statistics: HashMap::new(),
+        };
+        if order != PropertyOrder::Fixed {
+            scheduler.sort_pending();
+        }
+        scheduler
+    }
+
+    /// Number of properties that were not evaluated yet.
+    pub fn num_pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Names of the properties that were not evaluated yet, in the currently planned order.
+    pub fn planned_order(&self) -> Vec<String> {
+        self.pending.iter().map(|p| p.name.clone()).collect()
+    }
+
+    /// Take the next property to evaluate.
+    pub fn next_property(&mut self) -> Option<(String, HctlTreeNode)> {
+        if self.order == PropertyOrder::Adaptive {
+            self.sort_pending();
+        }
+        if self.pending.is_empty() {
+            return None;
+        }
+        let property = self.pending.remove(0);
+        let result = (property.name.clone(), property.tree.clone());
+        self.last_taken = Some(property);
+        Some(result)
+    }
+
+    /// Record the observed evaluation time of the last taken property, and the fraction of
+    /// candidates that remained after its evaluation. Only used by the adaptive ordering.
+    pub fn record_evaluation(&mut self, time: Duration, remaining_fraction: f64) {
+        let Some(property) = self.last_taken.take() else {
+            return;
+        };
+        let stats = self.statistics.entry(property.class).or_default();
+        stats.num_evaluated += 1;
+        stats.sum_time_per_cost += time.as_secs_f64() * 1000. / property.estimated_cost;
+        stats.sum_remaining_fraction += remaining_fraction;
+    }
+
+    /// Estimate the cost of evaluating the property relative to how many candidates it removes
+    /// (lower is better).
+    fn rank(&self, property: &ScheduledProperty) -> f64 {
+        let (mut time_per_cost, mut remaining_fraction) = (1., property.prior_remaining_fraction);
+        if self.order == PropertyOrder::Adaptive {
+            // use statistics of the class, or of all the properties if the class has none
+            let (num_evaluated, sum_time_per_cost) =
+                self.statistics.values().fold((0, 0.), |(num, sum), stats| {
+                    (num + stats.num_evaluated, sum + stats.sum_time_per_cost)
+                });
+            if num_evaluated > 0 {
+                time_per_cost = sum_time_per_cost / num_evaluated as f64;
+            }
+            if let Some(stats) = self.statistics.get(&property.class) {
+                time_per_cost = stats.sum_time_per_cost / stats.num_evaluated as f64;
+                remaining_fraction = stats.sum_remaining_fraction / stats.num_evaluated as f64;
+            }
+        }
+        // small constant to handle properties that remove nothing
+        property.estimated_cost * time_per_cost / (1.01 - remaining_fraction)
+    }
+
+    /// Sort the pending properties by their rank (stable, to keep the given order of ties).
+    fn sort_pending(&mut self) {
+        let mut ranked: Vec<(f64, ScheduledProperty)> =
+            self.pending.drain(..).map(|p| (0., p)).collect();
+        for (rank, property) in ranked.iter_mut() {
+            *rank = self.rank(property);
+        }
+        ranked.sort_by(|(x, _), (y, _)| x.total_cmp(y));
+        self.pending = ranked.into_iter().map(|(_, p)| p).collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::property_scheduling::{
+        classify_property, PropertyClass, PropertyOrder, PropertyScheduler,
+    };
+    use biodivine_hctl_model_checker::preprocessing::hctl_tree::HctlTreeNode;
+    use biodivine_hctl_model_checker::preprocessing::parser::parse_hctl_formula;
+    use std::time::Duration;
+
+    fn mk_named_trees(properties: &[(&str, &str)]) -> Vec<(String, HctlTreeNode)> {
+        properties
+            .iter()
+            .map(|(name, formula)| (name.to_string(), parse_hctl_formula(formula).unwrap()))
+            .collect()
+    }
+
+    #[test]
+    /// Test classifying properties and ordering them heuristically.
+    fn test_heuristic_order() {
+        let named_trees = mk_named_trees(&[
+            ("a_forbid", "~(3{x}: @{x}: ~(AG EF (a & b)))"),
+            ("b_attractor", "3{x}: @{x}: (a & AG EF {x})"),
+            ("c_fixed_point", "3{x}: @{x}: (a & AX {x})"),
+        ]);
+        let classes: Vec<PropertyClass> = named_trees
+            .iter()
+            .map(|(_, tree)| classify_property(tree))
+            .collect();
+        assert_eq!(classes[0], PropertyClass::Recurrence);
+        assert_eq!(classes[2], PropertyClass::Local);
+        assert_eq!(
+            classify_property(&parse_hctl_formula("EF a").unwrap()),
+            PropertyClass::Reachability
+        );
+
+        let scheduler = PropertyScheduler::new(named_trees.clone(), PropertyOrder::Fixed);
+        assert_eq!(
+            scheduler.planned_order(),
+            vec!["a_forbid", "b_attractor", "c_fixed_point"]
+        );
+        let mut scheduler = PropertyScheduler::new(named_trees, PropertyOrder::Heuristic);
+        assert_eq!(
+            scheduler.planned_order(),
+            vec!["c_fixed_point", "b_attractor", "a_forbid"]
+        );
+        assert_eq!(scheduler.next_property().unwrap().0, "c_fixed_point");
+        assert_eq!(scheduler.num_pending(), 2);
+    }
+
+    #[test]
+    /// Test reordering properties based on observed evaluations.
+    fn test_adaptive_order() {
+        let named_trees = mk_named_trees(&[
+            ("attractor", "3{x}: @{x}: (a & AG EF {x})"),
+            ("fixed_point_1", "3{x}: @{x}: (a & AX {x})"),
+            ("fixed_point_2", "3{x}: @{x}: (b & AX {x})"),
+        ]);
+        let mut heuristic = PropertyScheduler::new(named_trees.clone(), PropertyOrder::Heuristic);
+        let mut adaptive = PropertyScheduler::new(named_trees, PropertyOrder::Adaptive);
+        assert_eq!(heuristic.next_property().unwrap().0, "fixed_point_1");
+        assert_eq!(adaptive.next_property().unwrap().0, "fixed_point_1");
+
+        // the fixed-point property removed no candidates, so the other one is postponed
+        heuristic.record_evaluation(Duration::from_millis(10), 1.);
+        adaptive.record_evaluation(Duration::from_millis(10), 1.);
+        assert_eq!(heuristic.next_property().unwrap().0, "fixed_point_2");
+        assert_eq!(adaptive.next_property().unwrap().0, "attractor");
+        assert_eq!(adaptive.next_property().unwrap().0, "fixed_point_2");
+        assert!(adaptive.next_property().is_none());
+    }
+}
@@ -0,0 +1,281 @@
+//! Contains the complete inference of candidates of a sketch given by an annotated `.aeon` model.
+//!
+//! This is useful when several variants of a sketch (such as a sketch with modified regulations
+//! or update functions) must be processed the same way. The properties are taken from the model
+//! annotations (see `read_model_properties`, `read_perturbed_properties`,
+//! `read_function_classes`, and `read_update_schedule`) and evaluated the same way as in the main
+//! inference binary (see `evaluate_sketch_properties`), one by one in a fixed order by default.
+
+use crate::errors::SketchError;
+use crate::function_classes::{apply_function_classes, read_function_classes, FunctionClass};
+use crate::monitoring::InferenceMonitor;
+use crate::perturbations::{
+    apply_perturbed_properties, read_perturbed_properties, PerturbedProperty,
+};
+use crate::property_scheduling::{PropertyOrder, PropertyScheduler};
+use crate::update_semantics::{read_update_schedule, UpdateSchedule, UpdateSemantics};
+use crate::utils::{
+    apply_constraint_trees_in_parallel, apply_constraint_trees_with_shared_subformulae,
+    apply_properties_most_permissive, apply_scheduled_constraint_trees_and_restrict,
+    ConstrainedGraph,
+};
+
+use biodivine_hctl_model_checker::mc_utils::{
+    collect_unique_hctl_vars, get_extended_symbolic_graph,
+};
+use biodivine_hctl_model_checker::preprocessing::hctl_tree::HctlTreeNode;
+use biodivine_hctl_model_checker::preprocessing::parser::parse_and_minimize_hctl_formula;
+
+use biodivine_lib_param_bn::symbolic_async_graph::{SymbolicAsyncGraph, SymbolicContext};
+use biodivine_lib_param_bn::{BooleanNetwork, ModelAnnotation, VariableId};
+
+use std::cmp::max;
+
+/// Read the list of named properties from an `.aeon` model annotation object.
+///
+/// The properties are expected to appear as `#!dynamic_property: NAME: FORMULA` model annotations.
+/// They are returned in alphabetic order w.r.t. the property name.
+pub fn read_model_properties(
+    annotations: &ModelAnnotation,
+) -> Result<Vec<(String, String)>, SketchError> {
+    let Some(property_node) = annotations.get_child(&["dynamic_property"]) else {
+        return Ok(Vec::new());
+    };
+    let mut properties = Vec::with_capacity(property_node.children().len());
+    for (name, child) in property_node.children() {
+        if !child.children().is_empty() {
+            return Err(SketchError::parse(format!(
+                "Property `{name}` contains nested values."
+            )));
+        }
+        let Some(value) = child.value() else {
+            return Err(SketchError::parse(format!(
+                "Found empty dynamic property `{name}`."
+            )));
+        };
+        if value.lines().count() > 1 {
+            return Err(SketchError::parse(format!(
+                "Found multiple properties named `{name}`."
+            )));
+        }
+        properties.push((name.clone(), value.clone()));
+    }
+    // Sort alphabetically to avoid possible non-determinism down the line.
+    properties.sort_by(|(x, _), (y, _)| x.cmp(y));
+    Ok(properties)
+}
+
+/// Components of a sketch given by the annotations of its model - the (plain and perturbed)
+/// dynamic properties, restrictions of update functions to classes, and the update schedule.
+pub struct SketchComponents {
+    /// Plain dynamic properties (name and formula), in alphabetic order.
+    pub named_properties: Vec<(String, String)>,
+    /// Syntax trees of the plain dynamic properties.
+    pub property_trees: Vec<(String, HctlTreeNode)>,
+    pub perturbed_properties: Vec<PerturbedProperty>,
+    pub function_classes: Vec<(VariableId, FunctionClass)>,
+    pub schedule: UpdateSchedule,
+    /// Number of HCTL variables needed to evaluate all (plain and perturbed) properties.
+    pub num_hctl_vars: usize,
+}
+
+impl SketchComponents {
+    /// Read all components of the sketch given by the PSBN `bn` and by the `annotations` of its
+    /// model (the annotations may also come from a different model with the same variables),
+    /// and parse its properties.
+    pub fn read(
+        bn: &BooleanNetwork,
+        annotations: &ModelAnnotation,
+    ) -> Result<SketchComponents, SketchError> {
+        let named_properties = read_model_properties(annotations)?;
+        let perturbed_properties = read_perturbed_properties(bn, annotations)?;
+        let function_classes = read_function_classes(bn, annotations)?;
+        let schedule = read_update_schedule(bn, annotations)?;
+
+        let plain_context = SymbolicContext::new(bn).map_err(SketchError::SymbolicEncoding)?;
+        let mut num_hctl_vars = 0;
+        let mut property_trees = Vec::with_capacity(named_properties.len());
+        for (name, formula) in &named_properties {
+            let tree = parse_and_minimize_hctl_formula(&plain_context, formula.as_str())
+                .map_err(|e| SketchError::InvalidFormula(format!("property `{name}`: {e}")))?;
+            num_hctl_vars = max(num_hctl_vars, collect_unique_hctl_vars(tree.clone()).len());
+            property_trees.push((name.clone(), tree));
+        }
+        for property in &perturbed_properties {
+            let tree = parse_and_minimize_hctl_formula(&plain_context, property.formula.as_str())
+                .map_err(|e| {
+                SketchError::InvalidFormula(format!("perturbed property `{}`: {e}", property.name))
+            })?;
+            num_hctl_vars = max(num_hctl_vars, collect_unique_hctl_vars(tree).len());
+        }
+
+        Ok(SketchComponents {
+            named_properties,
+            property_trees,
+            perturbed_properties,
+            function_classes,
+            schedule,
+            num_hctl_vars,
+        })
+    }
+
+    /// Number of the extra symbolic state variables (for each network variable) needed to
+    /// evaluate all properties under the update schedule.
+    pub fn num_extra_vars(&self) -> u16 {
+        self.num_hctl_vars as u16 + self.schedule.num_extra_state_variables()
+    }
+
+    /// Create the symbolic graph of the PSBN `bn` with enough (and at least `min_extra_vars`)
+    /// extra state variables, with colour space restricted by the function classes.
+    pub fn mk_graph(
+        &self,
+        bn: &BooleanNetwork,
+        min_extra_vars: u16,
+    ) -> Result<SymbolicAsyncGraph, SketchError> {
+        let num_extra_vars = max(self.num_extra_vars(), min_extra_vars);
+        let graph = get_extended_symbolic_graph(bn, num_extra_vars)
+            .map_err(SketchError::SymbolicEncoding)?;
+        apply_function_classes(graph, &self.function_classes)
+    }
+}
+
+/// Settings of the evaluation of the dynamic properties.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EvaluationSettings {
+    /// Order in which the properties are evaluated (with a single thread).
+    pub property_order: PropertyOrder,
+    /// Number of threads evaluating the properties in parallel (0 means all available).
+    pub threads: usize,
+    /// Evaluate properties sharing temporal sub-formulae together (with a single thread).
+    pub share_subformulae: bool,
+}
+
+impl Default for EvaluationSettings {
+    /// Properties are evaluated one by one in the fixed order.
+    fn default() -> Self {
+        EvaluationSettings {
+            property_order: PropertyOrder::Fixed,
+            threads: 1,
+            share_subformulae: false,
+        }
+    }
+}
+
+/// Compute the number of extra symbolic state variables (for each network variable) needed to
+/// evaluate all properties of the sketch under its update schedule.
+pub fn count_required_extra_variables(
+    bn: &BooleanNetwork,
+    annotations: &ModelAnnotation,
+) -> Result<u16, SketchError> {
+    Ok(SketchComponents::read(bn, annotations)?.num_extra_vars())
+}
+
+/// Apply the dynamic properties of the `sketch` on the graph's colors, using the evaluation
+/// strategy given by the `settings`. Under the most permissive semantics, the properties are
+/// evaluated one by one by dedicated procedures instead. The perturbed properties are applied
+/// last. The `monitor` must contain the transition relation of the sketch's update schedule
+/// (if it needs one).
+pub fn evaluate_sketch_properties(
+    sketch: &SketchComponents,
+    graph: SymbolicAsyncGraph,
+    settings: &EvaluationSettings,
+    monitor: &mut InferenceMonitor,
+) -> Result<ConstrainedGraph, SketchError> {
+    let property_trees = sketch.property_trees.clone();
+    let constrained = if sketch.schedule.semantics == UpdateSemantics::MostPermissive {
+        apply_properties_most_permissive(sketch.named_properties.clone(), graph, monitor)?
+    } else if settings.threads == 1 && settings.share_subformulae {
+        // evaluate groups of properties in the planned order (of their first properties)
+        let scheduler = PropertyScheduler::new(property_trees.clone(), settings.property_order);
+        let planned_trees: Vec<(String, HctlTreeNode)> = scheduler
+            .planned_order()
+            .into_iter()
+            .filter_map(|name| property_trees.iter().find(|(n, _)| *n == name).cloned())
+            .collect();
+        apply_constraint_trees_with_shared_subformulae(planned_trees, graph, monitor)?
+    } else if settings.threads == 1 {
+        let scheduler = PropertyScheduler::new(property_trees, settings.property_order);
+        apply_scheduled_constraint_trees_and_restrict(scheduler, graph, monitor)?
+    } else {
+        apply_constraint_trees_in_parallel(property_trees, graph, settings.threads, monitor)?
+    };
+    if constrained.interruption.is_some() || sketch.perturbed_properties.is_empty() {
+        return Ok(constrained);
+    }
+    apply_perturbed_properties(
+        &sketch.perturbed_properties,
+        constrained.graph,
+        &sketch.schedule,
+        monitor,
+    )
+}
+
+/// Compute all candidates of the sketch given by the PSBN `bn` and by the `annotations` of its
+/// model (the annotations may also come from a different model with the same variables).
+///
+/// The symbolic graph is created with (at least) `min_extra_vars` extra state variables, so that
+/// the candidates of several sketch variants can be encoded in the same way. The properties are
+/// evaluated with the default `EvaluationSettings`. The inference is a nested run of the
+/// `monitor` (see `mk_nested_monitor`), so it respects its cancellation token and resource
+/// limits.
+/// Returns a graph with colour space restricted only to the consistent candidates, or an error if
+/// the computation is interrupted.
+pub fn infer_sketch_candidates(
+    bn: &BooleanNetwork,
+    annotations: &ModelAnnotation,
+    min_extra_vars: u16,
+    monitor: &InferenceMonitor,
+) -> Result<SymbolicAsyncGraph, SketchError> {
+    let sketch = SketchComponents::read(bn, annotations)?;
+    let graph = sketch.mk_graph(bn, min_extra_vars)?;
+
+    let mut monitor = monitor.mk_nested_monitor();
+    if let Some(transition_relation) = sketch.schedule.mk_transition_relation(&graph)? {
+        monitor.set_transition_relation(transition_relation);
+    }
+    let constrained =
+        evaluate_sketch_properties(&sketch, graph, &EvaluationSettings::default(), &mut monitor)?;
+    match constrained.interruption {
+        Some(interruption) => Err(SketchError::Interrupted(interruption.to_string())),
+        None => Ok(constrained.graph),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::monitoring::{CancellationToken, InferenceMonitor};
+    use crate::sketch_inference::{
+        count_required_extra_variables, infer_sketch_candidates, read_model_properties,
+    };
+    use biodivine_lib_param_bn::{BooleanNetwork, ModelAnnotation};
+
+    const TEST_SKETCH: &str = r"
+#! dynamic_property: p2: #`3{x}: @{x}: (~v_1 & ~v_2 & (AX {x}))`#
+#! dynamic_property: p1: #`3{x}: @{x}: (v_1 & v_2 & (AX {x}))`#
+v_1 -> v_2
+v_2 -? v_1
+";
+
+    #[test]
+    /// Test that properties are read in the alphabetic order, and that the inference finds the
+    /// only candidate with both fixed points.
+    fn test_infer_sketch_candidates() {
+        let bn = BooleanNetwork::try_from(TEST_SKETCH).unwrap();
+        let annotations = ModelAnnotation::from_model_string(TEST_SKETCH);
+        let properties = read_model_properties(&annotations).unwrap();
+        let names: Vec<&str> = properties.iter().map(|(n, _)| n.as_str()).collect();
+        assert_eq!(names, vec!["p1", "p2"]);
+        assert_eq!(
+            count_required_extra_variables(&bn, &annotations).unwrap(),
+            1
+        );
+
+        let monitor = InferenceMonitor::new(CancellationToken::new());
+        let unconstrained =
+            infer_sketch_candidates(&bn, &ModelAnnotation::new(), 0, &monitor).unwrap();
+        let graph = infer_sketch_candidates(&bn, &annotations, 2, &monitor).unwrap();
+        // only `v_1 = v_2` admits both fixed points
+        assert_eq!(unconstrained.mk_unit_colors().approx_cardinality(), 2.);
+        assert_eq!(graph.mk_unit_colors().approx_cardinality(), 1.);
+    }
+}
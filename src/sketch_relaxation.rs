@@ -0,0 +1,252 @@
+//! Contains the search for relaxations of a sketch that has no consistent candidates.
+//!
+//! A relaxation weakens the regulations of the sketch - it makes the sign of a regulation
+//! unknown (e.g., `->` to `-?`), makes an essential regulation non-essential (e.g., `->` to
+//! `->?`), or adds a self-loop (with unknown sign, non-essential) to a variable. Sets of
+//! relaxations are examined by increasing size, and supersets of successful ones are skipped, so
+//! that only minimal relaxations restoring the consistency are reported.
+//!
+//! Note that the inference is run for every examined set of relaxations, so the search is only
+//! feasible for small sizes of relaxation sets.
+
+use crate::errors::SketchError;
+use crate::monitoring::InferenceMonitor;
+use crate::sketch_inference::infer_sketch_candidates;
+use crate::utils::{search_minimal_subsets, SubsetOutcome};
+
+use biodivine_lib_param_bn::{BooleanNetwork, ModelAnnotation, RegulatoryGraph, VariableId};
+
+use std::fmt;
+
+/// A single relaxation of a regulation of a sketch (given by variable names).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Relaxation {
+    /// Make the sign of the regulation unknown.
+    UnknownSign { regulator: String, target: String },
+    /// Make the regulation non-essential.
+    NonEssential { regulator: String, target: String },
+    /// Add a self-loop (with unknown sign, non-essential) to the variable.
+    SelfLoop(String),
+}
+
+impl fmt::Display for Relaxation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Relaxation::UnknownSign { regulator, target } => {
+                write!(f, "unknown sign of `{regulator}` -> `{target}`")
+            }
+            Relaxation::NonEssential { regulator, target } => {
+                write!(f, "non-essential `{regulator}` -> `{target}`")
+            }
+            Relaxation::SelfLoop(var) => write!(f, "self-loop on `{var}`"),
+        }
+    }
+}
+
+/// Structure to represent a (minimal) set of relaxations restoring the consistency of a sketch,
+/// and the number of candidates of the relaxed sketch.
+#[derive(Clone, Debug)]
+pub struct SketchRelaxation {
+    pub relaxations: Vec<Relaxation>,
+    pub num_candidates: f64,
+}
+
+impl fmt::Display for SketchRelaxation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let relaxations: Vec<String> = self.relaxations.iter().map(|r| r.to_string()).collect();
+        write!(
+            f,
+            "{}: {} candidates",
+            relaxations.join(", "),
+            self.num_candidates
+        )
+    }
+}
+
+/// Check whether the update function of `var` is (at least partially) unknown, so that
+/// relaxing its regulations can change the set of candidates.
+fn has_unknown_function(bn: &BooleanNetwork, var: VariableId) -> bool {
+    match bn.get_update_function(var) {
+        Some(update_fn) => !update_fn.collect_parameters().is_empty(),
+        None => true,
+    }
+}
+
+/// Collect all relaxations applicable to the regulations of the PSBN `bn`. Regulations of
+/// variables with fully specified update functions are not relaxed, and self-loops are only
+/// added to variables with unspecified update functions.
+pub fn collect_relaxations(bn: &BooleanNetwork) -> Vec<Relaxation> {
+    let mut relaxations = Vec::new();
+    for regulation in bn.as_graph().regulations() {
+        if !has_unknown_function(bn, regulation.get_target()) {
+            continue;
+        }
+        let regulator = bn.get_variable_name(regulation.get_regulator()).clone();
+        let target = bn.get_variable_name(regulation.get_target()).clone();
+        if regulation.get_monotonicity().is_some() {
+            relaxations.push(Relaxation::UnknownSign {
+                regulator: regulator.clone(),
+                target: target.clone(),
+            });
+        }
+        if regulation.is_observable() {
+            relaxations.push(Relaxation::NonEssential { regulator, target });
+        }
+    }
+    for var in bn.variables() {
+        let has_self_loop = bn.as_graph().find_regulation(var, var).is_some();
+        if !has_self_loop && bn.get_update_function(var).is_none() {
+            relaxations.push(Relaxation::SelfLoop(bn.get_variable_name(var).clone()));
+        }
+    }
+    relaxations
+}
+
+/// Create a copy of the PSBN `bn` with the given `relaxations` applied to its regulations.
+pub fn mk_relaxed_network(
+    bn: &BooleanNetwork,
+    relaxations: &[Relaxation],
+) -> Result<BooleanNetwork, SketchError> {
+    let var_names: Vec<String> = bn
+        .variables()
+        .map(|v| bn.get_variable_name(v).clone())
+        .collect();
+    let mut regulatory_graph = RegulatoryGraph::new(var_names);
+    for regulation in bn.as_graph().regulations() {
+        let regulator = bn.get_variable_name(regulation.get_regulator()).clone();
+        let target = bn.get_variable_name(regulation.get_target()).clone();
+        let mut monotonicity = regulation.get_monotonicity();
+        let mut observable = regulation.is_observable();
+        for relaxation in relaxations {
+            match relaxation {
+                Relaxation::UnknownSign {
+                    regulator: r,
+                    target: t,
+                } if *r == regulator && *t == target => monotonicity = None,
+                Relaxation::NonEssential {
+                    regulator: r,
+                    target: t,
+                } if *r == regulator && *t == target => observable = false,
+                _ => {}
+            }
+        }
+        regulatory_graph
+            .add_regulation(&regulator, &target, observable, monotonicity)
+            .map_err(SketchError::SymbolicEncoding)?;
+    }
+    for relaxation in relaxations {
+        if let Relaxation::SelfLoop(var) = relaxation {
+            regulatory_graph
+                .add_regulation(var, var, false, None)
+                .map_err(SketchError::SymbolicEncoding)?;
+        }
+    }
+
+    // parameters must be added in the same order, so that their IDs stay the same
+    let mut relaxed_bn = BooleanNetwork::new(regulatory_graph);
+    for param_id in bn.parameters() {
+        let parameter = bn.get_parameter(param_id);
+        relaxed_bn
+            .add_parameter(parameter.get_name(), parameter.get_arity())
+            .map_err(SketchError::SymbolicEncoding)?;
+    }
+    for var in bn.variables() {
+        relaxed_bn
+            .set_update_function(var, bn.get_update_function(var).clone())
+            .map_err(SketchError::SymbolicEncoding)?;
+    }
+    Ok(relaxed_bn)
+}
+
+/// Search for minimal sets of (at most `max_size`) relaxations of the regulations of the PSBN
+/// `bn`, such that the relaxed sketch (with properties given by `annotations`) has some
+/// consistent candidates. The inference of each examined relaxation is a nested run of the
+/// `monitor` (an interrupted search returns an error).
+///
+/// The result is sorted by the number of candidates of the relaxed sketches (ascending, so that
+/// the least permissive relaxations come first).
+pub fn search_relaxations(
+    bn: &BooleanNetwork,
+    annotations: &ModelAnnotation,
+    max_size: usize,
+    monitor: &InferenceMonitor,
+) -> Result<Vec<SketchRelaxation>, SketchError> {
+    let relaxations = collect_relaxations(bn);
+    let mut results =
+        search_minimal_subsets(&relaxations, max_size, |subset: &[Relaxation], _| {
+            let relaxed_bn = mk_relaxed_network(bn, subset)?;
+            let graph = infer_sketch_candidates(&relaxed_bn, annotations, 0, monitor)?;
+            let num_candidates = graph.mk_unit_colors().approx_cardinality();
+            if num_candidates == 0. {
+                return Ok(SubsetOutcome::Rejected);
+            }
+            Ok(SubsetOutcome::Minimal(SketchRelaxation {
+                relaxations: subset.to_vec(),
+                num_candidates,
+            }))
+        })?;
+
+    // stable sort, so that relaxations with the same number of candidates stay ordered by size
+    results.sort_by(|x, y| x.num_candidates.total_cmp(&y.num_candidates));
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::monitoring::{CancellationToken, InferenceMonitor};
+    use crate::sketch_relaxation::{
+        collect_relaxations, mk_relaxed_network, search_relaxations, Relaxation,
+    };
+    use biodivine_lib_param_bn::{BooleanNetwork, ModelAnnotation};
+
+    /// Sketch requiring a fixed point in which the activator of `v_1` is active, but `v_1` is
+    /// not. It has no candidates unless the regulation of `v_1` is relaxed.
+    const TEST_SKETCH: &str = r"
+#! dynamic_property: p: #`3{x}: @{x}: (~v_1 & v_2 & (AX {x}))`#
+v_2 -> v_1
+v_1 -| v_2
+$v_2: !v_1
+";
+
+    #[test]
+    /// Test collecting relaxations and applying them to the regulations.
+    fn test_relax_network() {
+        let bn = BooleanNetwork::try_from(TEST_SKETCH).unwrap();
+        let relaxations = collect_relaxations(&bn);
+        // regulation of `v_2` is not relaxed (its function is given)
+        assert_eq!(relaxations.len(), 3);
+        assert!(relaxations.contains(&Relaxation::SelfLoop("v_1".to_string())));
+
+        let relaxed_bn = mk_relaxed_network(&bn, &relaxations).unwrap();
+        let v_1 = relaxed_bn.find_variable("v_1").unwrap();
+        assert_eq!(relaxed_bn.regulators(v_1).len(), 2);
+        for regulation in relaxed_bn.as_graph().regulations() {
+            if regulation.get_target() == v_1 {
+                assert!(regulation.get_monotonicity().is_none());
+                assert!(!regulation.is_observable());
+            }
+        }
+    }
+
+    #[test]
+    /// Test search for minimal relaxations that make the sketch consistent.
+    fn test_search_relaxations() {
+        let bn = BooleanNetwork::try_from(TEST_SKETCH).unwrap();
+        let annotations = ModelAnnotation::from_model_string(TEST_SKETCH);
+        let monitor = InferenceMonitor::new(CancellationToken::new());
+        let results = search_relaxations(&bn, &annotations, 2, &monitor).unwrap();
+
+        // `v_1 = !v_2` (unknown sign) or `v_1 = false` (non-essential), or a function with
+        // a self-loop that is false in the fixed point
+        assert!(!results.is_empty());
+        assert!(results.iter().all(|r| r.relaxations.len() == 1));
+        assert!(results.iter().all(|r| r.num_candidates > 0.));
+        let unknown_sign = Relaxation::UnknownSign {
+            regulator: "v_2".to_string(),
+            target: "v_1".to_string(),
+        };
+        assert!(results
+            .iter()
+            .any(|r| r.relaxations == [unknown_sign.clone()]));
+    }
+}
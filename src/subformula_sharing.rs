@@ -0,0 +1,236 @@
+//! Contains functionality to find sub-formulae shared between properties of a sketch.
+//!
+//! Sketches often repeat large sub-formulae, such as `AG EF {x}` in attractor properties written
+//! as `3{x}: @{x}: (state & AG EF {x})`, or `AG EF (state)` in the (specific) attractor property
+//! of a single observed state and in the property forbidding other attractors. Properties sharing
+//! such sub-formulae can be evaluated together, so that the model checker computes each shared
+//! sub-formula only once.
+//!
+//! Sub-formulae are compared modulo renaming of HCTL variables - variables are renamed by the
+//! position of their quantifier, so that the same sub-formulae are recognised even if the
+//! properties use different variable names. Otherwise, the sub-formulae must be the same (up to
+//! parentheses), so `AG EF {x}` and `AG EF (state)` are not shared, even though both appear in
+//! attractor properties.
+
+use biodivine_hctl_model_checker::preprocessing::hctl_tree::{HctlTreeNode, NodeType};
+use biodivine_hctl_model_checker::preprocessing::operator_enums::{
+    Atomic, BinaryOp, HybridOp, UnaryOp,
+};
+
+use std::collections::{HashMap, HashSet};
+
+/// Check whether the operator is temporal (its evaluation is expensive, and thus worth sharing).
+fn is_temporal_operator(node_type: &NodeType) -> bool {
+    match node_type {
+        NodeType::UnaryNode(op, _) => *op != UnaryOp::Not,
+        NodeType::BinaryNode(op, _, _) => {
+            matches!(
+                op,
+                BinaryOp::Eu | BinaryOp::Au | BinaryOp::Ew | BinaryOp::Aw
+            )
+        }
+        _ => false,
+    }
+}
+
+/// Compute the canonical string of the formula `tree`, where the (bound) HCTL variables are
+/// renamed by the number of quantifiers above them. Canonical strings of temporal sub-formulae
+/// are collected to `temporal_subformulae`.
+fn canonicalize(
+    tree: &HctlTreeNode,
+    var_names: &HashMap<String, String>,
+    temporal_subformulae: &mut HashSet<String>,
+) -> String {
+    let rename = |var: &String| var_names.get(var).unwrap_or(var).clone();
+    let canonical = match &tree.node_type {
+        NodeType::TerminalNode(Atomic::Prop(name)) => name.clone(),
+        NodeType::TerminalNode(Atomic::Var(var)) => format!("{{{}}}", rename(var)),
+        NodeType::TerminalNode(Atomic::WildCardProp(name)) => format!("%{name}%"),
+        NodeType::TerminalNode(Atomic::True) => "true".to_string(),
+        NodeType::TerminalNode(Atomic::False) => "false".to_string(),
+        NodeType::UnaryNode(op, child) => {
+            let child = canonicalize(child, var_names, temporal_subformulae);
+            format!("({op:?} {child})")
+        }
+        NodeType::BinaryNode(op, left, right) => {
+            let left = canonicalize(left, var_names, temporal_subformulae);
+            let right = canonicalize(right, var_names, temporal_subformulae);
+            format!("({left} {op:?} {right})")
+        }
+        NodeType::HybridNode(HybridOp::Jump, var, .., child) => {
+            let child = canonicalize(child, var_names, temporal_subformulae);
+            format!("(@{{{}}}: {child})", rename(var))
+        }
+        NodeType::HybridNode(op, var, domain, child) => {
+            // quantifier introduces a new variable, named by the number of outer quantifiers
+            let canonical_var = format!("var{}", var_names.len());
+            let mut inner_var_names = var_names.clone();
+            inner_var_names.insert(var.clone(), canonical_var.clone());
+            let child = canonicalize(child, &inner_var_names, temporal_subformulae);
+            match domain {
+                Some(domain) => format!("({op:?} {{{canonical_var}}} in %{domain}%: {child})"),
+                None => format!("({op:?} {{{canonical_var}}}: {child})"),
+            }
+        }
+    };
+    if is_temporal_operator(&tree.node_type) {
+        temporal_subformulae.insert(canonical.clone());
+    }
+    canonical
+}
+
+/// Compute canonical strings of all temporal sub-formulae of the formula `tree`.
+pub fn canonical_temporal_subformulae(tree: &HctlTreeNode) -> HashSet<String> {
+    let mut temporal_subformulae = HashSet::new();
+    canonicalize(tree, &HashMap::new(), &mut temporal_subformulae);
+    temporal_subformulae
+}
+
+/// Count how many of the given properties contain each (canonical) temporal sub-formula.
+/// Only sub-formulae present in at least two properties are returned.
+pub fn count_shared_subformulae(trees: &[&HctlTreeNode]) -> HashMap<String, usize> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for tree in trees {
+        for subformula in canonical_temporal_subformulae(tree) {
+            *counts.entry(subformula).or_insert(0) += 1;
+        }
+    }
+    counts.retain(|_, count| *count > 1);
+    counts
+}
+
+/// Partition the named properties into groups, so that properties sharing some temporal
+/// sub-formula (even transitively) are in the same group.
+///
+/// Groups are ordered by their first property, and properties in each group keep their order.
+pub fn group_by_shared_subformulae(
+    named_trees: Vec<(String, HctlTreeNode)>,
+) -> Vec<Vec<(String, HctlTreeNode)>> {
+    // union-find structure over properties, joined through their sub-formulae
+    let mut parents: Vec<usize> = (0..named_trees.len()).collect();
+    fn find(parents: &mut [usize], i: usize) -> usize {
+        if parents[i] != i {
+            parents[i] = find(parents, parents[i]);
+        }
+        parents[i]
+    }
+
+    let mut owners: HashMap<String, usize> = HashMap::new();
+    for (i, (_, tree)) in named_trees.iter().enumerate() {
+        for subformula in canonical_temporal_subformulae(tree) {
+            let owner = *owners.entry(subformula).or_insert(i);
+            let (root_owner, root_i) = (find(&mut parents, owner), find(&mut parents, i));
+            // the smaller index is the root, so that groups are ordered by their first property
+            parents[root_owner.max(root_i)] = root_owner.min(root_i);
+        }
+    }
+
+    let mut groups: Vec<Vec<(String, HctlTreeNode)>> = Vec::new();
+    let mut group_indices: HashMap<usize, usize> = HashMap::new();
+    for (i, named_tree) in named_trees.into_iter().enumerate() {
+        let root = find(&mut parents, i);
+        let group_idx = *group_indices.entry(root).or_insert_with(|| {
+            groups.push(Vec::new());
+            groups.len() - 1
+        });
+        groups[group_idx].push(named_tree);
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data_processing::create_inference_formulae::{
+        mk_formula_attractor_specific, mk_formula_forbid_other_attractors,
+    };
+    use crate::subformula_sharing::{
+        canonical_temporal_subformulae, count_shared_subformulae, group_by_shared_subformulae,
+    };
+    use biodivine_hctl_model_checker::preprocessing::parser::parse_hctl_formula;
+
+    #[test]
+    /// Test recognising the same sub-formulae with differently named variables.
+    fn test_canonical_subformulae() {
+        let tree_1 = parse_hctl_formula("3{x}: @{x}: (a & AG EF {x})").unwrap();
+        let tree_2 = parse_hctl_formula("3{y}: @{y}: (b & AG EF {y})").unwrap();
+        let tree_3 = parse_hctl_formula("3{x}: @{x}: (a & AX {x})").unwrap();
+
+        let subformulae_1 = canonical_temporal_subformulae(&tree_1);
+        assert_eq!(subformulae_1.len(), 2);
+        assert_eq!(subformulae_1, canonical_temporal_subformulae(&tree_2));
+        assert!(canonical_temporal_subformulae(&tree_3).is_disjoint(&subformulae_1));
+
+        let shared = count_shared_subformulae(&[&tree_1, &tree_2, &tree_3]);
+        assert_eq!(shared.len(), 2);
+        assert!(shared.values().all(|count| *count == 2));
+
+        // quantifiers with different domains are not the same
+        let tree_4 = parse_hctl_formula("AG (3{x}: @{x}: EF {x})").unwrap();
+        let tree_5 = parse_hctl_formula("AG (3{x} in %s%: @{x}: EF {x})").unwrap();
+        let shared = count_shared_subformulae(&[&tree_4, &tree_5]);
+        assert_eq!(shared.len(), 1);
+    }
+
+    #[test]
+    /// Test grouping properties that share sub-formulae. The property forbidding other attractors
+    /// (`AG EF (states)`) shares nothing with the attractor properties (`AG EF {x}`).
+    fn test_grouping() {
+        let named_trees = vec![
+            ("a_attr", "3{x}: @{x}: (a & AG EF {x})"),
+            ("b_fixed", "3{x}: @{x}: (a & AX {x})"),
+            ("c_forbid", "~(3{x}: @{x}: ~(AG EF ((a & b) | (~a & ~b))))"),
+            ("d_attr", "3{z}: @{z}: (~a & AG EF {z})"),
+            ("e_reach", "EF (a & b)"),
+            ("f_reach", "3{x}: @{x}: (~a & EF (a & b))"),
+        ]
+        .into_iter()
+        .map(|(name, formula)| (name.to_string(), parse_hctl_formula(formula).unwrap()))
+        .collect();
+
+        let groups: Vec<Vec<String>> = group_by_shared_subformulae(named_trees)
+            .into_iter()
+            .map(|group| group.into_iter().map(|(name, _)| name).collect())
+            .collect();
+        assert_eq!(
+            groups,
+            vec![
+                vec!["a_attr".to_string(), "d_attr".to_string()],
+                vec!["b_fixed".to_string()],
+                vec!["c_forbid".to_string()],
+                vec!["e_reach".to_string(), "f_reach".to_string()],
+            ]
+        );
+    }
+
+    #[test]
+    /// Test that the generated properties of a single attractor state share `AG EF (state)`.
+    fn test_grouping_generated_properties() {
+        let states = vec!["a & ~b".to_string()];
+        let named_trees = vec![
+            (
+                "attractor",
+                mk_formula_attractor_specific(states[0].clone()).unwrap(),
+            ),
+            ("reach", "EF (a & b)".to_string()),
+            (
+                "forbid_other",
+                mk_formula_forbid_other_attractors(states).unwrap(),
+            ),
+        ]
+        .into_iter()
+        .map(|(name, formula)| (name.to_string(), parse_hctl_formula(&formula).unwrap()))
+        .collect();
+
+        let groups: Vec<Vec<String>> = group_by_shared_subformulae(named_trees)
+            .into_iter()
+            .map(|group| group.into_iter().map(|(name, _)| name).collect())
+            .collect();
+        assert_eq!(
+            groups,
+            vec![
+                vec!["attractor".to_string(), "forbid_other".to_string()],
+                vec!["reach".to_string()],
+            ]
+        );
+    }
+}
@@ -0,0 +1,577 @@
+//! Contains functionality regarding update semantics (schedules) other than the asynchronous one.
+//!
+//! Under the synchronous semantics, all variables are updated at once, so each state of a network
+//! has exactly one successor. Variables can be also assigned to ranked priority classes (such as
+//! fast signalling and slow transcription processes) - a variable can only be updated if no
+//! variable of a class with higher priority can change. Within a class, the variables are updated
+//! asynchronously (one at a time) or synchronously (all at once), based on the semantics. Both
+//! are given by sketch annotations (a lower rank means a higher priority, variables without
+//! a class form the last class):
+//!
+//! ```text
+//! #! semantics: synchronous
+//! #! priority_class: 1: Receptor, Kinase
+//! #! priority_class: 2: TF
+//! ```
+//!
+//! The transition relation of such a schedule is encoded symbolically over the state variables,
+//! their primed copies, and parameters (so that it covers all candidate networks at once). The
+//! primed copies are the last extra state variables of the symbolic context, the remaining extra
+//! variables are used for HCTL variables as usual (see `get_extended_symbolic_graph`).
+//!
+//! HCTL formulae are evaluated on the relation by the model checker of `hctl_evaluation`, since
+//! the model checking library only supports the asynchronous semantics. The results are colored
+//! vertex sets of the asynchronous graph, so that all the remaining computation (restricting
+//! colors, summaries of candidates) works the same for all schedules.
+
+use crate::errors::SketchError;
+use crate::hctl_evaluation::HctlEvaluator;
+use crate::monitoring::InterruptGuard;
+use crate::utils::{get_network, mk_update_fn_true};
+
+use biodivine_hctl_model_checker::preprocessing::hctl_tree::HctlTreeNode;
+use biodivine_hctl_model_checker::preprocessing::parser::parse_hctl_formula;
+
+use biodivine_lib_bdd::{Bdd, BddVariable};
+use biodivine_lib_param_bn::symbolic_async_graph::{GraphColoredVertices, SymbolicAsyncGraph};
+use biodivine_lib_param_bn::{BooleanNetwork, ModelAnnotation, VariableId};
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+/// Enum of supported update semantics.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum UpdateSemantics {
+    /// A single variable is updated in each step (the default).
+    #[default]
+    Asynchronous,
+    /// All variables are updated at once in each step.
+    Synchronous,
+    /// Changing variables can be read as both values until the change finishes (see the
+    /// `most_permissive` module). Only properties generated from data can be evaluated.
+    MostPermissive,
+}
+
+impl fmt::Display for UpdateSemantics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UpdateSemantics::Asynchronous => write!(f, "asynchronous"),
+            UpdateSemantics::Synchronous => write!(f, "synchronous"),
+            UpdateSemantics::MostPermissive => write!(f, "most_permissive"),
+        }
+    }
+}
+
+impl TryFrom<&str> for UpdateSemantics {
+    type Error = SketchError;
+
+    /// Parse the semantics from its string representation (as used in annotations).
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value.trim() {
+            "asynchronous" | "async" => Ok(UpdateSemantics::Asynchronous),
+            "synchronous" | "sync" => Ok(UpdateSemantics::Synchronous),
+            "most_permissive" | "mp" => Ok(UpdateSemantics::MostPermissive),
+            other => Err(SketchError::parse(format!(
+                "Invalid update semantics `{other}`."
+            ))),
+        }
+    }
+}
+
+impl UpdateSemantics {
+    /// Number of extra state variables (for each network variable) needed to encode the
+    /// semantics, in addition to the ones used for HCTL variables.
+    pub fn num_extra_state_variables(&self) -> u16 {
+        match self {
+            UpdateSemantics::Asynchronous => 0,
+            UpdateSemantics::Synchronous | UpdateSemantics::MostPermissive => 1,
+        }
+    }
+}
+
+/// Read the update semantics from an `.aeon` model annotation object (`#! semantics: VALUE`).
+/// Without the annotation, the asynchronous semantics is used.
+pub fn read_update_semantics(
+    annotations: &ModelAnnotation,
+) -> Result<UpdateSemantics, SketchError> {
+    let Some(value) = annotations.get_value(&["semantics"]) else {
+        return Ok(UpdateSemantics::Asynchronous);
+    };
+    if value.lines().count() > 1 {
+        return Err(SketchError::parse(format!(
+            "Found multiple update semantics `{value}`."
+        )));
+    }
+    UpdateSemantics::try_from(value.as_str())
+}
+
+/// Update schedule of a sketch - the semantics and (optional) priority classes.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct UpdateSchedule {
+    pub semantics: UpdateSemantics,
+    /// Variables of the priority classes, from the highest priority. Empty if there are no
+    /// priority classes.
+    pub priority_classes: Vec<Vec<VariableId>>,
+}
+
+impl fmt::Display for UpdateSchedule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.semantics)?;
+        if !self.priority_classes.is_empty() {
+            write!(f, " with {} priority classes", self.priority_classes.len())?;
+        }
+        Ok(())
+    }
+}
+
+impl UpdateSchedule {
+    /// Check whether the schedule is the plain asynchronous semantics (without priority classes).
+    pub fn is_asynchronous(&self) -> bool {
+        self.semantics == UpdateSemantics::Asynchronous && self.priority_classes.is_empty()
+    }
+
+    /// Check whether the schedule is encoded by a custom transition relation (everything but the
+    /// plain asynchronous and the most permissive semantics).
+    pub fn needs_transition_relation(&self) -> bool {
+        !self.is_asynchronous() && self.semantics != UpdateSemantics::MostPermissive
+    }
+
+    /// Number of extra state variables (for each network variable) needed to encode the
+    /// schedule, in addition to the ones used for HCTL variables. Priority classes are encoded
+    /// by a transition relation even under the asynchronous semantics.
+    pub fn num_extra_state_variables(&self) -> u16 {
+        if self.priority_classes.is_empty() {
+            self.semantics.num_extra_state_variables()
+        } else {
+            1
+        }
+    }
+
+    /// Encode the transition relation of the schedule for the network underlying the `graph`
+    /// (see `TransitionRelation::new`). Returns `None` for the plain asynchronous and the most
+    /// permissive semantics.
+    pub fn mk_transition_relation(
+        &self,
+        graph: &SymbolicAsyncGraph,
+    ) -> Result<Option<TransitionRelation>, SketchError> {
+        if !self.needs_transition_relation() {
+            return Ok(None);
+        }
+        TransitionRelation::new(graph, self.semantics, &self.priority_classes).map(Some)
+    }
+}
+
+/// Read the update schedule from an `.aeon` model annotation object - the semantics (see
+/// `read_update_semantics`) and priority classes (`#! priority_class: RANK: VAR_1, VAR_2`).
+/// Classes are ordered by their (numeric) ranks, each variable can be in at most one class.
+/// Priority classes can't be combined with the most permissive semantics.
+pub fn read_update_schedule(
+    bn: &BooleanNetwork,
+    annotations: &ModelAnnotation,
+) -> Result<UpdateSchedule, SketchError> {
+    let semantics = read_update_semantics(annotations)?;
+    let Some(class_node) = annotations.get_child(&["priority_class"]) else {
+        return Ok(UpdateSchedule {
+            semantics,
+            priority_classes: Vec::new(),
+        });
+    };
+
+    let mut ranked_classes = Vec::new();
+    let mut classified = HashSet::new();
+    for (rank, child) in class_node.children() {
+        let rank: usize = rank
+            .trim()
+            .parse()
+            .map_err(|_| SketchError::parse(format!("Invalid priority class rank `{rank}`.")))?;
+        let Some(value) = child.value() else {
+            return Err(SketchError::parse(format!(
+                "Found empty priority class `{rank}`."
+            )));
+        };
+        let mut class = Vec::new();
+        for var_name in value.split([',', '\n']).map(|name| name.trim()) {
+            if var_name.is_empty() {
+                continue;
+            }
+            let var = bn
+                .find_variable(var_name)
+                .ok_or(SketchError::UnknownVariable(var_name.to_string()))?;
+            if !classified.insert(var) {
+                return Err(SketchError::parse(format!(
+                    "Variable `{var_name}` is in multiple priority classes."
+                )));
+            }
+            class.push(var);
+        }
+        ranked_classes.push((rank, class));
+    }
+    if semantics == UpdateSemantics::MostPermissive && !ranked_classes.is_empty() {
+        return Err(SketchError::parse(
+            "Priority classes are not supported under the most permissive semantics.",
+        ));
+    }
+    ranked_classes.sort_by_key(|(rank, _)| *rank);
+    Ok(UpdateSchedule {
+        semantics,
+        priority_classes: ranked_classes.into_iter().map(|(_, class)| class).collect(),
+    })
+}
+
+/// Symbolic transition relation of all candidate networks of a graph, under a given update
+/// schedule. States without any enabled update have a self-loop, so that each state has
+/// a successor (as in the model checking library).
+///
+/// The relation only depends on the symbolic context of the graph, so it can be used with any
+/// graph that shares the context (such as graphs with restricted colors).
+#[derive(Clone)]
+pub struct TransitionRelation {
+    /// Relation over state variables, primed state variables, and parameters.
+    relation: Bdd,
+    /// Primed copy of each state variable (in the order of network variables).
+    primed_vars: Vec<BddVariable>,
+    /// Extra state variables used for HCTL variables (for each network variable).
+    hctl_vars: Vec<Vec<BddVariable>>,
+    /// BDD expressing that primed and unprimed state variables are equal.
+    primed_equality: Bdd,
+}
+
+impl TransitionRelation {
+    /// Encode the transition relation of the network underlying the `graph` under the given
+    /// `semantics` and `priority_classes` (from the highest priority, variables without a class
+    /// form the last one). The symbolic context of the graph must contain at least one extra
+    /// state variable for each network variable (used for the primed copies).
+    pub fn new(
+        graph: &SymbolicAsyncGraph,
+        semantics: UpdateSemantics,
+        priority_classes: &[Vec<VariableId>],
+    ) -> Result<TransitionRelation, SketchError> {
+        if semantics == UpdateSemantics::MostPermissive {
+            return Err(SketchError::InvalidArgument(
+                "The most permissive semantics can't be encoded by a transition relation."
+                    .to_string(),
+            ));
+        }
+        let context = graph.symbolic_context();
+        let bdd_vars = context.bdd_variable_set();
+        let bn = get_network(graph)?;
+        let mut primed_equality = bdd_vars.mk_true();
+        let mut primed_vars = Vec::new();
+        let mut hctl_vars = Vec::new();
+        // for each variable, the BDDs of `s'_v <=> f_v(s)` and `s_v != f_v(s)` (it can change)
+        let mut updates = HashMap::new();
+        for (var, state_var) in bn.variables().zip(context.state_variables()) {
+            let extra_vars = context.get_extra_state_variables(var);
+            let Some((primed_var, var_hctl_vars)) = extra_vars.split_last() else {
+                return Err(SketchError::SymbolicEncoding(format!(
+                    "The {semantics} semantics requires an extra state variable for each variable."
+                )));
+            };
+            let primed = bdd_vars.mk_var(*primed_var);
+            let state = bdd_vars.mk_var(*state_var);
+            let update_fn = mk_update_fn_true(graph, var)?;
+            updates.insert(var, (primed.iff(&update_fn), state.xor(&update_fn)));
+            primed_equality = primed_equality.and(&primed.iff(&state));
+            primed_vars.push(*primed_var);
+            hctl_vars.push(var_hctl_vars.to_vec());
+        }
+
+        let mut classes: Vec<Vec<VariableId>> = priority_classes.to_vec();
+        let unclassified: Vec<VariableId> = bn
+            .variables()
+            .filter(|var| !classes.iter().any(|class| class.contains(var)))
+            .collect();
+        if !unclassified.is_empty() {
+            classes.push(unclassified);
+        }
+        let primed_var = |var: &VariableId| primed_vars[var.to_index()];
+
+        // a class is blocked if some variable of a class with higher priority can change
+        let mut relation = bdd_vars.mk_false();
+        let mut blocked = bdd_vars.mk_false();
+        for class in classes {
+            let class_enabled = class
+                .iter()
+                .fold(bdd_vars.mk_false(), |acc, var| acc.or(&updates[var].1));
+            let step = match semantics {
+                // the most permissive semantics is rejected above
+                UpdateSemantics::Asynchronous | UpdateSemantics::MostPermissive => {
+                    class.iter().fold(bdd_vars.mk_false(), |acc, var| {
+                        let others_unchanged = primed_equality.var_exists(primed_var(var));
+                        let (next, enabled) = &updates[var];
+                        acc.or(&enabled.and(next).and(&others_unchanged))
+                    })
+                }
+                UpdateSemantics::Synchronous => {
+                    let class_primed: Vec<BddVariable> = class.iter().map(primed_var).collect();
+                    let others_unchanged = primed_equality.exists(&class_primed);
+                    class
+                        .iter()
+                        .fold(class_enabled.and(&others_unchanged), |acc, var| {
+                            acc.and(&updates[var].0)
+                        })
+                }
+            };
+            relation = relation.or(&step.and_not(&blocked));
+            blocked = blocked.or(&class_enabled);
+        }
+        // states where nothing can change have a self-loop
+        relation = relation.or(&primed_equality.and_not(&blocked));
+
+        Ok(TransitionRelation {
+            relation,
+            primed_vars,
+            hctl_vars,
+            primed_equality,
+        })
+    }
+
+    /// Encode the synchronous transition relation of the network underlying the `graph` (see
+    /// `new`).
+    pub fn synchronous(graph: &SymbolicAsyncGraph) -> Result<TransitionRelation, SketchError> {
+        Self::new(graph, UpdateSemantics::Synchronous, &[])
+    }
+
+    /// Number of HCTL variables that can be used in formulae evaluated on the relation.
+    pub fn num_hctl_vars(&self) -> usize {
+        self.hctl_vars.first().map_or(0, |vars| vars.len())
+    }
+
+    /// Compute the successors of the `set` (within the unit set of the `graph`).
+    pub fn post(
+        &self,
+        graph: &SymbolicAsyncGraph,
+        set: &GraphColoredVertices,
+    ) -> GraphColoredVertices {
+        self.wrap(graph, self.post_bdd(graph, set.as_bdd()))
+    }
+
+    /// Compute the predecessors of the `set` (within the unit set of the `graph`).
+    pub fn pre(
+        &self,
+        graph: &SymbolicAsyncGraph,
+        set: &GraphColoredVertices,
+    ) -> GraphColoredVertices {
+        self.wrap(graph, self.pre_bdd(graph, set.as_bdd()))
+    }
+
+    /// Compute the fixed points of all candidates of the `graph`.
+    pub fn fixed_points(&self, graph: &SymbolicAsyncGraph) -> GraphColoredVertices {
+        let fixed_points = self
+            .relation
+            .and(&self.primed_equality)
+            .exists(&self.primed_vars);
+        self.wrap(graph, fixed_points)
+    }
+
+    /// Compute the attractor states of all candidates of the `graph` (states of terminal strongly
+    /// connected components). Requires at least one HCTL variable.
+    pub fn attractor_states(
+        &self,
+        graph: &SymbolicAsyncGraph,
+    ) -> Result<GraphColoredVertices, String> {
+        self.model_check_formula("!{x}: AG EF {x}", graph)
+    }
+
+    /// Evaluate the HCTL formula on the relation (all candidates of the `graph`).
+    pub fn model_check_formula(
+        &self,
+        formula: &str,
+        graph: &SymbolicAsyncGraph,
+    ) -> Result<GraphColoredVertices, String> {
+        self.model_check_tree(&parse_hctl_formula(formula)?, graph)
+    }
+
+    /// Evaluate the HCTL formula `tree` on the relation (all candidates of the `graph`).
+    ///
+    /// HCTL variables are assigned to extra state variables by the number of quantifiers above
+    /// them, so the formula can nest at most `num_hctl_vars` quantifiers.
+    pub fn model_check_tree(
+        &self,
+        tree: &HctlTreeNode,
+        graph: &SymbolicAsyncGraph,
+    ) -> Result<GraphColoredVertices, String> {
+        let guard = InterruptGuard::none();
+        HctlEvaluator::new(graph, Some(self), &guard)
+            .evaluate(tree)
+            .map_err(|e| e.to_string())
+    }
+
+    /// Extra state variables used for HCTL variables (for each network variable).
+    pub(crate) fn hctl_vars(&self) -> &[Vec<BddVariable>] {
+        &self.hctl_vars
+    }
+
+    fn wrap(&self, graph: &SymbolicAsyncGraph, bdd: Bdd) -> GraphColoredVertices {
+        let bdd = bdd.and(graph.unit_colored_vertices().as_bdd());
+        GraphColoredVertices::new(bdd, graph.symbolic_context())
+    }
+
+    /// Rename unprimed state variables of the `set` to the primed ones, and vice versa.
+    fn swap_primed(&self, graph: &SymbolicAsyncGraph, set: &Bdd, to_primed: bool) -> Bdd {
+        let projected: &[BddVariable] = if to_primed {
+            graph.symbolic_context().state_variables()
+        } else {
+            &self.primed_vars
+        };
+        set.and(&self.primed_equality).exists(projected)
+    }
+
+    fn post_bdd(&self, graph: &SymbolicAsyncGraph, set: &Bdd) -> Bdd {
+        let state_vars = graph.symbolic_context().state_variables();
+        let primed_successors = set.and(&self.relation).exists(state_vars);
+        self.swap_primed(graph, &primed_successors, false)
+            .and(graph.unit_colored_vertices().as_bdd())
+    }
+
+    pub(crate) fn pre_bdd(&self, graph: &SymbolicAsyncGraph, set: &Bdd) -> Bdd {
+        let primed_set = self.swap_primed(graph, set, true);
+        primed_set
+            .and(&self.relation)
+            .exists(&self.primed_vars)
+            .and(graph.unit_colored_vertices().as_bdd())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::errors::SketchError;
+    use crate::update_semantics::{
+        read_update_schedule, read_update_semantics, TransitionRelation, UpdateSemantics,
+    };
+    use biodivine_hctl_model_checker::mc_utils::get_extended_symbolic_graph;
+    use biodivine_lib_param_bn::biodivine_std::traits::Set;
+    use biodivine_lib_param_bn::{BooleanNetwork, ModelAnnotation};
+
+    #[test]
+    /// Test parsing the semantics from annotations.
+    fn test_read_semantics() {
+        let annotations = ModelAnnotation::from_model_string("#! semantics: synchronous\n");
+        assert_eq!(
+            read_update_semantics(&annotations).unwrap(),
+            UpdateSemantics::Synchronous
+        );
+        let annotations = ModelAnnotation::from_model_string("a -> b\n");
+        assert_eq!(
+            read_update_semantics(&annotations).unwrap(),
+            UpdateSemantics::Asynchronous
+        );
+        let annotations = ModelAnnotation::from_model_string("#! semantics: mp\n");
+        assert_eq!(
+            read_update_semantics(&annotations).unwrap(),
+            UpdateSemantics::MostPermissive
+        );
+        let annotations = ModelAnnotation::from_model_string("#! semantics: parallel\n");
+        assert!(read_update_semantics(&annotations).is_err());
+    }
+
+    #[test]
+    /// Test attractors and properties under the synchronous semantics. The network swapping two
+    /// variables has fixed points `00` and `11` under both semantics, but the states `01` and `10`
+    /// form a cyclic attractor only under the synchronous semantics.
+    fn test_synchronous_dynamics() {
+        let bn = BooleanNetwork::try_from("a -> b\nb -> a\n$a: b\n$b: a\n").unwrap();
+        let graph = get_extended_symbolic_graph(&bn, 2).unwrap();
+        let transitions = TransitionRelation::synchronous(&graph).unwrap();
+        assert_eq!(transitions.num_hctl_vars(), 1);
+
+        assert_eq!(transitions.fixed_points(&graph).approx_cardinality(), 2.0);
+        assert_eq!(
+            transitions
+                .attractor_states(&graph)
+                .unwrap()
+                .approx_cardinality(),
+            4.0
+        );
+        let state = graph.mk_subspace(&[
+            (bn.find_variable("a").unwrap(), true),
+            (bn.find_variable("b").unwrap(), false),
+        ]);
+        let successor = transitions.post(&graph, &state);
+        assert_eq!(successor.approx_cardinality(), 1.0);
+        assert_eq!(transitions.pre(&graph, &successor), state);
+
+        let cyclic_attractor = "3{x}: @{x}: (a & ~b & AG EF {x} & ~AX {x})";
+        let result = transitions
+            .model_check_formula(cyclic_attractor, &graph)
+            .unwrap();
+        assert!(!result.is_empty());
+        let fixed_point = "3{x}: @{x}: (a & ~b & AX {x})";
+        let result = transitions
+            .model_check_formula(fixed_point, &graph)
+            .unwrap();
+        assert!(result.is_empty());
+        // too many nested quantifiers
+        let formula = "3{x}: 3{y}: @{x}: AX {y}";
+        assert!(transitions.model_check_formula(formula, &graph).is_err());
+    }
+
+    #[test]
+    /// Test parsing priority classes from annotations.
+    fn test_read_priority_classes() {
+        let bn = BooleanNetwork::try_from("a -> b\nb -> c\n").unwrap();
+        let (a, b) = (
+            bn.find_variable("a").unwrap(),
+            bn.find_variable("b").unwrap(),
+        );
+        let aeon_string = "#! priority_class: 2: b\n#! priority_class: 1: a\n";
+        let schedule =
+            read_update_schedule(&bn, &ModelAnnotation::from_model_string(aeon_string)).unwrap();
+        assert_eq!(schedule.semantics, UpdateSemantics::Asynchronous);
+        assert_eq!(schedule.priority_classes, vec![vec![a], vec![b]]);
+        assert!(schedule.needs_transition_relation());
+
+        let aeon_string = "#! priority_class: 1: a, d\n";
+        let result = read_update_schedule(&bn, &ModelAnnotation::from_model_string(aeon_string));
+        assert_eq!(result, Err(SketchError::UnknownVariable("d".to_string())));
+        let aeon_string = "#! priority_class: 1: a\n#! priority_class: 2: a, b\n";
+        let result = read_update_schedule(&bn, &ModelAnnotation::from_model_string(aeon_string));
+        assert!(result.is_err());
+        let schedule =
+            read_update_schedule(&bn, &ModelAnnotation::from_model_string("a -> b\n")).unwrap();
+        assert!(!schedule.needs_transition_relation());
+        assert!(schedule.is_asynchronous());
+
+        // the most permissive semantics has no transition relation, nor priority classes
+        let aeon_string = "#! semantics: most_permissive\n";
+        let schedule =
+            read_update_schedule(&bn, &ModelAnnotation::from_model_string(aeon_string)).unwrap();
+        assert!(!schedule.needs_transition_relation());
+        assert!(!schedule.is_asynchronous());
+        let aeon_string = "#! semantics: most_permissive\n#! priority_class: 1: a\n";
+        let result = read_update_schedule(&bn, &ModelAnnotation::from_model_string(aeon_string));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    /// Test dynamics with priority classes. In the network, `x` is switched on, which disables
+    /// `y`. Without priorities, `y` can be switched on before `x`, but not if `x` is faster.
+    fn test_priority_dynamics() {
+        let bn = BooleanNetwork::try_from("x -| y\n$x: true\n$y: !x\n").unwrap();
+        let x = bn.find_variable("x").unwrap();
+        let graph = get_extended_symbolic_graph(&bn, 2).unwrap();
+        let formula = "~x & ~y & EF (~x & y)";
+
+        let transitions =
+            TransitionRelation::new(&graph, UpdateSemantics::Asynchronous, &[]).unwrap();
+        assert!(!transitions
+            .model_check_formula(formula, &graph)
+            .unwrap()
+            .is_empty());
+        let transitions =
+            TransitionRelation::new(&graph, UpdateSemantics::Asynchronous, &[vec![x]]).unwrap();
+        assert!(transitions
+            .model_check_formula(formula, &graph)
+            .unwrap()
+            .is_empty());
+        // fixed points and attractors do not change
+        assert_eq!(transitions.fixed_points(&graph).approx_cardinality(), 1.0);
+        assert_eq!(
+            transitions
+                .attractor_states(&graph)
+                .unwrap()
+                .approx_cardinality(),
+            1.0
+        );
+    }
+}
@@ -1,18 +1,52 @@
 //! Contains several useful utilities for either the inference procedure or for post-processing
 //! the results.
 
-use biodivine_hctl_model_checker::model_checking::{
-    model_check_formula_dirty, model_check_tree_dirty,
-};
+use biodivine_hctl_model_checker::model_checking::model_check_formula_dirty;
 
 use biodivine_lib_param_bn::biodivine_std::traits::Set;
-use biodivine_lib_param_bn::symbolic_async_graph::{GraphColors, SymbolicAsyncGraph};
-use biodivine_lib_param_bn::{BooleanNetwork, FnUpdate};
+use biodivine_lib_param_bn::symbolic_async_graph::{
+    GraphColoredVertices, GraphColors, SymbolicAsyncGraph,
+};
+use biodivine_lib_param_bn::{BooleanNetwork, FnUpdate, VariableId};
+
+use crate::errors::SketchError;
+use crate::explanation::recognise_property;
+use crate::hctl_evaluation::{EvaluationError, HctlEvaluator};
+use crate::monitoring::{CancellationToken, InferenceMonitor, Interruption, PropertyProgress};
+use crate::most_permissive::MostPermissive;
+use crate::property_scheduling::{PropertyOrder, PropertyScheduler};
+use crate::subformula_sharing::group_by_shared_subformulae;
+use crate::update_semantics::TransitionRelation;
 
 use biodivine_hctl_model_checker::preprocessing::hctl_tree::HctlTreeNode;
-use biodivine_lib_bdd::BddPartialValuation;
+use biodivine_lib_bdd::{Bdd, BddPartialValuation};
 use rand::rngs::ThreadRng;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Get the network underlying the `graph`.
+/// Returns an error if the graph was not created from a network.
+pub fn get_network(graph: &SymbolicAsyncGraph) -> Result<&BooleanNetwork, SketchError> {
+    graph.as_network().ok_or(SketchError::SymbolicEncoding(
+        "Graph does not contain the underlying network.".to_string(),
+    ))
+}
+
+/// Create a copy of the `graph` with colour space restricted to `colors`.
+pub fn mk_restricted_graph(
+    graph: &SymbolicAsyncGraph,
+    colors: &GraphColors,
+) -> Result<SymbolicAsyncGraph, SketchError> {
+    SymbolicAsyncGraph::with_custom_context(
+        get_network(graph)?,
+        graph.symbolic_context().clone(),
+        colors.as_bdd().clone(),
+    )
+    .map_err(SketchError::SymbolicEncoding)
+}
 
 /// Apply properties (constraints) given by HCTL `formulae` on the graph's colors.
 /// Returns a graph with colour space restricted only to the suitable colors.
@@ -20,49 +54,411 @@ pub fn apply_constraints_and_restrict(
     formulae: Vec<String>,
     mut graph: SymbolicAsyncGraph,
     message: &str,
-) -> SymbolicAsyncGraph {
+) -> Result<SymbolicAsyncGraph, SketchError> {
     for formula in formulae {
         let inferred_colors = model_check_formula_dirty(&formula, &graph)
-            .unwrap()
+            .map_err(SketchError::ModelChecking)?
             .colors();
-        graph = SymbolicAsyncGraph::with_custom_context(
-            graph.as_network().unwrap(),
-            graph.symbolic_context().clone(),
-            inferred_colors.as_bdd().clone(),
-        )
-        .unwrap();
+        graph = mk_restricted_graph(&graph, &inferred_colors)?;
 
         if !message.is_empty() {
             println!("{message}")
         }
     }
-    graph
+    Ok(graph)
 }
 
 /// Apply properties (constraints) given by HCTL formulae `trees` on the graph's colors.
 /// Returns a graph with colour space restricted only to the suitable colors.
 pub fn apply_constraint_trees_and_restrict(
     formulae_trees: Vec<HctlTreeNode>,
-    mut graph: SymbolicAsyncGraph,
+    graph: SymbolicAsyncGraph,
     message: &str,
-) -> SymbolicAsyncGraph {
-    for formula_tree in formulae_trees {
-        // do it one by one now to track progress, even though it could be done at once
-        let inferred_colors = model_check_tree_dirty(formula_tree, &graph)
-            .unwrap()
-            .colors();
-        graph = SymbolicAsyncGraph::with_custom_context(
-            graph.as_network().unwrap(),
-            graph.symbolic_context().clone(),
-            inferred_colors.as_bdd().clone(),
-        )
-        .unwrap();
+) -> Result<SymbolicAsyncGraph, SketchError> {
+    apply_constraint_trees_with_transitions_and_restrict(formulae_trees, graph, None, message)
+}
 
-        if !message.is_empty() {
-            println!("{message}")
+/// Apply properties (constraints) given by HCTL formulae `trees` on the graph's colors, evaluated
+/// under the update schedule given by the `transition_relation` (or under the asynchronous
+/// semantics, if it is `None`). Otherwise, it works as `apply_constraint_trees_and_restrict`.
+pub fn apply_constraint_trees_with_transitions_and_restrict(
+    formulae_trees: Vec<HctlTreeNode>,
+    graph: SymbolicAsyncGraph,
+    transition_relation: Option<TransitionRelation>,
+    message: &str,
+) -> Result<SymbolicAsyncGraph, SketchError> {
+    let named_trees = formulae_trees
+        .into_iter()
+        .enumerate()
+        .map(|(i, tree)| (format!("property_{}", i + 1), tree))
+        .collect();
+    // the token is never cancelled, the run thus cannot be interrupted
+    let mut monitor = InferenceMonitor::new(CancellationToken::new());
+    if !message.is_empty() {
+        monitor.set_progress_callback(|_| println!("{message}"));
+    }
+    if let Some(transition_relation) = transition_relation {
+        monitor.set_transition_relation(transition_relation);
+    }
+    let constrained = apply_named_constraint_trees_and_restrict(named_trees, graph, &mut monitor)?;
+    Ok(constrained.graph)
+}
+
+/// Result of applying properties on the graph's colors. If the run was interrupted, the graph is
+/// restricted only by the properties processed before the interruption.
+pub struct ConstrainedGraph {
+    pub graph: SymbolicAsyncGraph,
+    pub interruption: Option<Interruption>,
+}
+
+/// Apply named properties (constraints) given by HCTL formulae `trees` on the graph's colors,
+/// one by one. The progress is reported via the `monitor` after each property, and the run stops
+/// if the `monitor` signals an interrupt (e.g., cancellation).
+///
+/// Returns a graph with colour space restricted only to the suitable colors (satisfying all the
+/// processed properties), and the interruption, if there was one.
+pub fn apply_named_constraint_trees_and_restrict(
+    named_trees: Vec<(String, HctlTreeNode)>,
+    graph: SymbolicAsyncGraph,
+    monitor: &mut InferenceMonitor,
+) -> Result<ConstrainedGraph, SketchError> {
+    let scheduler = PropertyScheduler::new(named_trees, PropertyOrder::Fixed);
+    apply_scheduled_constraint_trees_and_restrict(scheduler, graph, monitor)
+}
+
+/// Apply properties (constraints) on the graph's colors, one by one, in the order decided by the
+/// `scheduler` (which is informed about the time and candidate reduction of each property).
+/// Otherwise, it works as `apply_named_constraint_trees_and_restrict`.
+pub fn apply_scheduled_constraint_trees_and_restrict(
+    mut scheduler: PropertyScheduler,
+    mut graph: SymbolicAsyncGraph,
+    monitor: &mut InferenceMonitor,
+) -> Result<ConstrainedGraph, SketchError> {
+    let num_properties = scheduler.num_pending();
+    let mut property_index = 0;
+    while let Some((property_name, formula_tree)) = scheduler.next_property() {
+        property_index += 1;
+        let property_start = Instant::now();
+        let num_candidates_before = graph.mk_unit_colors().approx_cardinality();
+        let result = match monitor.model_check_tree(formula_tree, &graph)? {
+            Ok(result) => result,
+            Err(reason) => {
+                let interruption = Interruption {
+                    property_name,
+                    reason,
+                };
+                return Ok(ConstrainedGraph {
+                    graph,
+                    interruption: Some(interruption),
+                });
+            }
+        };
+        let inferred_colors = result.colors().intersect(graph.unit_colors());
+        graph = mk_restricted_graph(&graph, &inferred_colors)?;
+
+        let property_time = property_start.elapsed();
+        let num_candidates = inferred_colors.approx_cardinality();
+        if num_candidates_before > 0. {
+            scheduler.record_evaluation(property_time, num_candidates / num_candidates_before);
         }
+        monitor.report_progress(&PropertyProgress {
+            property_name,
+            property_index,
+            num_properties,
+            property_time,
+            elapsed: monitor.elapsed(),
+            num_candidates,
+            bdd_size: inferred_colors.as_bdd().size(),
+        });
     }
-    graph
+    Ok(ConstrainedGraph {
+        graph,
+        interruption: None,
+    })
+}
+
+/// Apply named properties (constraints) given by formulae generated from data on the graph's
+/// colors, evaluated under the most permissive semantics (see `MostPermissive`). All formulae
+/// are checked to be supported before the evaluation starts.
+///
+/// The properties are evaluated one by one, and the run stops if the `monitor` signals an
+/// interrupt before the next property (a single property can't be interrupted). The encoding of
+/// the dynamics (computed before the first property) can be interrupted as well. Otherwise, it
+/// works as `apply_named_constraint_trees_and_restrict`.
+pub fn apply_properties_most_permissive(
+    named_formulae: Vec<(String, String)>,
+    mut graph: SymbolicAsyncGraph,
+    monitor: &mut InferenceMonitor,
+) -> Result<ConstrainedGraph, SketchError> {
+    if let Some((name, _)) = named_formulae
+        .iter()
+        .find(|(_, formula)| recognise_property(formula).is_none())
+    {
+        return Err(SketchError::InvalidFormula(format!(
+            "property `{name}` is not supported under the most permissive semantics (only properties generated from data are)"
+        )));
+    }
+    let Some((first_property, _)) = named_formulae.first() else {
+        return Ok(ConstrainedGraph {
+            graph,
+            interruption: None,
+        });
+    };
+    // the encoding is reported as a part of the first property
+    let guard = monitor.mk_interrupt_guard();
+    let most_permissive = match MostPermissive::new_interruptibly(&graph, &guard)? {
+        Ok(most_permissive) => most_permissive,
+        Err(reason) => {
+            let interruption = Interruption {
+                property_name: first_property.clone(),
+                reason,
+            };
+            return Ok(ConstrainedGraph {
+                graph,
+                interruption: Some(interruption),
+            });
+        }
+    };
+    let num_properties = named_formulae.len();
+    for (i, (property_name, formula)) in named_formulae.into_iter().enumerate() {
+        let property_start = Instant::now();
+        let result = match monitor.check_interrupt() {
+            Some(reason) => Err(reason),
+            None => {
+                let result = most_permissive.model_check_property(&formula, &graph)?;
+                match monitor.check_bdd_size(result.as_bdd().size()) {
+                    Some(reason) => Err(reason),
+                    None => Ok(result),
+                }
+            }
+        };
+        let result = match result {
+            Ok(result) => result,
+            Err(reason) => {
+                let interruption = Interruption {
+                    property_name,
+                    reason,
+                };
+                return Ok(ConstrainedGraph {
+                    graph,
+                    interruption: Some(interruption),
+                });
+            }
+        };
+        let inferred_colors = result.colors().intersect(graph.unit_colors());
+        graph = mk_restricted_graph(&graph, &inferred_colors)?;
+
+        monitor.report_progress(&PropertyProgress {
+            property_name,
+            property_index: i + 1,
+            num_properties,
+            property_time: property_start.elapsed(),
+            elapsed: monitor.elapsed(),
+            num_candidates: inferred_colors.approx_cardinality(),
+            bdd_size: inferred_colors.as_bdd().size(),
+        });
+    }
+    Ok(ConstrainedGraph {
+        graph,
+        interruption: None,
+    })
+}
+
+/// Apply named properties (constraints) given by HCTL formulae `trees` on the graph's colors,
+/// evaluating properties that share some temporal sub-formulae together (see
+/// `group_by_shared_subformulae`), so that the shared sub-formulae are computed only once.
+///
+/// The groups are evaluated one by one (each restricting the colors for the following ones),
+/// and the progress is reported after each group. Otherwise, it works as
+/// `apply_named_constraint_trees_and_restrict`.
+pub fn apply_constraint_trees_with_shared_subformulae(
+    named_trees: Vec<(String, HctlTreeNode)>,
+    mut graph: SymbolicAsyncGraph,
+    monitor: &mut InferenceMonitor,
+) -> Result<ConstrainedGraph, SketchError> {
+    let num_properties = named_trees.len();
+    let mut num_processed = 0;
+    for group in group_by_shared_subformulae(named_trees) {
+        let group_start = Instant::now();
+        let (names, trees): (Vec<String>, Vec<HctlTreeNode>) = group.into_iter().unzip();
+        let group_name = names.join(", ");
+        let results = match trees.len() {
+            1 => monitor
+                .model_check_tree(trees[0].clone(), &graph)?
+                .map(|result| vec![result]),
+            _ => monitor.model_check_trees(trees, &graph)?,
+        };
+        let results = match results {
+            Ok(results) => results,
+            Err(reason) => {
+                let interruption = Interruption {
+                    property_name: group_name,
+                    reason,
+                };
+                return Ok(ConstrainedGraph {
+                    graph,
+                    interruption: Some(interruption),
+                });
+            }
+        };
+        let inferred_colors = results
+            .iter()
+            .fold(graph.mk_unit_colors(), |colors, result| {
+                colors.intersect(&result.colors())
+            });
+        graph = mk_restricted_graph(&graph, &inferred_colors)?;
+
+        num_processed += names.len();
+        monitor.report_progress(&PropertyProgress {
+            property_name: group_name,
+            property_index: num_processed,
+            num_properties,
+            property_time: group_start.elapsed(),
+            elapsed: monitor.elapsed(),
+            num_candidates: inferred_colors.approx_cardinality(),
+            bdd_size: inferred_colors.as_bdd().size(),
+        });
+    }
+    Ok(ConstrainedGraph {
+        graph,
+        interruption: None,
+    })
+}
+
+/// Message sent from a worker evaluating properties in parallel.
+enum WorkerMessage {
+    Started(usize),
+    Finished(usize, Result<GraphColoredVertices, EvaluationError>),
+}
+
+/// Apply named properties (constraints) given by HCTL formulae `trees` on the graph's colors,
+/// evaluating them in parallel on `num_threads` threads (if zero, use all available cores).
+///
+/// All properties are evaluated on the same input colors and the results are intersected. This
+/// does not profit from the restriction of colors by previous properties (as the sequential
+/// variant does), but many properties can be evaluated at once. Otherwise, it works as
+/// `apply_named_constraint_trees_and_restrict` (the progress is reported after each finished
+/// property, and the interruption lists the properties that were running).
+///
+/// Each worker checks the limits of the monitor (including the BDD size limit) while evaluating
+/// a property. When a property fails or is interrupted, the other workers are stopped via
+/// a shared flag, and all of them are joined before returning.
+pub fn apply_constraint_trees_in_parallel(
+    named_trees: Vec<(String, HctlTreeNode)>,
+    graph: SymbolicAsyncGraph,
+    num_threads: usize,
+    monitor: &mut InferenceMonitor,
+) -> Result<ConstrainedGraph, SketchError> {
+    let num_threads = match num_threads {
+        0 => thread::available_parallelism().map_or(1, |n| n.get()),
+        n => n,
+    };
+    let num_properties = named_trees.len();
+    let names: Vec<String> = named_trees.iter().map(|(name, _)| name.clone()).collect();
+    let queue: VecDeque<(usize, HctlTreeNode)> = named_trees
+        .into_iter()
+        .map(|(_, tree)| tree)
+        .enumerate()
+        .collect();
+    let queue = Mutex::new(queue);
+    let stop_flag = Arc::new(AtomicBool::new(false));
+    let guard = monitor
+        .mk_interrupt_guard()
+        .with_stop_flag(stop_flag.clone());
+    let transitions = monitor.transition_relation().cloned();
+
+    let mut running: HashMap<usize, Instant> = HashMap::new();
+    let mut valid_colors = graph.mk_unit_colors();
+    let mut num_finished = 0;
+    let mut interruption = None;
+    let mut error = None;
+    thread::scope(|scope| {
+        let (sender, receiver) = mpsc::channel();
+        for _ in 0..num_threads.min(num_properties) {
+            let (queue, graph, transitions, guard, stop_flag, sender) = (
+                &queue,
+                &graph,
+                transitions.as_ref(),
+                &guard,
+                &stop_flag,
+                sender.clone(),
+            );
+            scope.spawn(move || {
+                while !stop_flag.load(Ordering::SeqCst) {
+                    let Some((i, tree)) = queue.lock().ok().and_then(|mut q| q.pop_front()) else {
+                        break;
+                    };
+                    if sender.send(WorkerMessage::Started(i)).is_err() {
+                        break;
+                    }
+                    // the property timeout is measured from the start of this property
+                    let guard = guard.for_next_property();
+                    let result = HctlEvaluator::new(graph, transitions, &guard).evaluate(&tree);
+                    if sender.send(WorkerMessage::Finished(i, result)).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(sender);
+
+        // receiving ends once all workers finished (or were stopped)
+        for message in receiver {
+            let (i, result) = match message {
+                WorkerMessage::Started(i) => {
+                    running.insert(i, Instant::now());
+                    continue;
+                }
+                WorkerMessage::Finished(i, result) => (i, result),
+            };
+            if interruption.is_some() || error.is_some() {
+                // results of workers stopped by the flag are not needed
+                continue;
+            }
+            let result = match result {
+                Ok(result) => result,
+                Err(EvaluationError::Interrupted(reason)) => {
+                    stop_flag.store(true, Ordering::SeqCst);
+                    interruption = Some((running.keys().copied().collect(), reason));
+                    continue;
+                }
+                Err(EvaluationError::Failed(message)) => {
+                    stop_flag.store(true, Ordering::SeqCst);
+                    error = Some(SketchError::ModelChecking(message));
+                    continue;
+                }
+            };
+            let property_time = running.remove(&i).map_or(Duration::ZERO, |t| t.elapsed());
+            valid_colors = valid_colors.intersect(&result.colors());
+            num_finished += 1;
+            monitor.report_progress(&PropertyProgress {
+                property_name: names[i].clone(),
+                property_index: num_finished,
+                num_properties,
+                property_time,
+                elapsed: monitor.elapsed(),
+                num_candidates: valid_colors.approx_cardinality(),
+                bdd_size: valid_colors.as_bdd().size(),
+            });
+        }
+    });
+    if let Some(error) = error {
+        return Err(error);
+    }
+
+    let graph = mk_restricted_graph(&graph, &valid_colors)?;
+    let interruption = interruption.map(|(mut indices, reason): (Vec<usize>, _)| {
+        indices.sort();
+        let running_names: Vec<String> = indices.iter().map(|i| names[*i].clone()).collect();
+        Interruption {
+            property_name: running_names.join(", "),
+            reason,
+        }
+    });
+    Ok(ConstrainedGraph {
+        graph,
+        interruption,
+    })
 }
 
 /// Check if `inferred_colors` contain the color of the specific network
@@ -71,57 +467,118 @@ pub fn check_if_result_contains_goal(
     graph: SymbolicAsyncGraph,
     goal_aeon_string: Option<String>,
     inferred_colors: GraphColors,
-) {
+) -> Result<(), SketchError> {
     // if the goal network was supplied, check whether it is part of the solution set
     if let Some(goal_model) = goal_aeon_string {
-        let goal_bn = BooleanNetwork::try_from(goal_model.as_str()).unwrap();
-        match graph.mk_subnetwork_colors(&goal_bn) {
-            Ok(goal_colors) => {
-                // we will need intersection of goal colors with the ones from the result
-                // if the goal is subset of result, it is ok
-                if goal_colors.intersect(&inferred_colors).approx_cardinality()
-                    == goal_colors.approx_cardinality()
-                {
-                    println!("OK - goal network is included in the candidate set.")
-                } else {
-                    println!("NOK - goal network is NOT included in the candidate set.")
-                }
-            }
-            Err(e) => println!("{e}"),
+        if check_if_result_contains_goal_unsafe(graph, goal_model, inferred_colors)? {
+            println!("OK - goal network is included in the candidate set.")
+        } else {
+            println!("NOK - goal network is NOT included in the candidate set.")
         }
     } else {
         println!("Goal network not provided.")
     }
+    Ok(())
 }
 
 /// Check if the resulting set `inferred_colors` contain the color of the specific network
 /// represented by `goal_aeon_string`.
 ///
-/// This is an unsafe version of function `check_if_result_contains_goal`, - there are no input
-/// validity checks, just returns a value (and also no prints).
+/// This is a version of function `check_if_result_contains_goal` that just returns a value (and
+/// does not print anything). An invalid goal model, or a goal model that can't be encoded as
+/// a sub-network of the graph's network, results in an error.
 pub fn check_if_result_contains_goal_unsafe(
     graph: SymbolicAsyncGraph,
     goal_aeon_string: String,
     inferred_colors: GraphColors,
-) -> bool {
-    let goal_bn = BooleanNetwork::try_from(goal_aeon_string.as_str()).unwrap();
-    match graph.mk_subnetwork_colors(&goal_bn) {
-        Ok(goal_colors) => {
-            goal_colors.intersect(&inferred_colors).approx_cardinality()
-                == goal_colors.approx_cardinality()
-        }
-        Err(_) => false,
+) -> Result<bool, SketchError> {
+    let goal_bn =
+        BooleanNetwork::try_from(goal_aeon_string.as_str()).map_err(SketchError::parse)?;
+    let goal_colors = graph
+        .mk_subnetwork_colors(&goal_bn)
+        .map_err(SketchError::SymbolicEncoding)?;
+    // if the goal is a subset of the result, it is included
+    Ok(goal_colors.minus(&inferred_colors).is_empty())
+}
+
+/// Create a BDD (over state and parameter variables) that is true exactly when the update function
+/// of the variable `var` evaluates to true (for both explicit and implicit update functions).
+pub fn mk_update_fn_true(graph: &SymbolicAsyncGraph, var: VariableId) -> Result<Bdd, SketchError> {
+    let context = graph.symbolic_context();
+    let bn = get_network(graph)?;
+    Ok(match bn.get_update_function(var) {
+        Some(update_fn) => context.mk_fn_update_true(update_fn),
+        None => context.mk_implicit_function_is_true(var, &bn.regulators(var)),
+    })
+}
+
+/// Compute the row-wise encoding of the update function of `var`. For each combination of
+/// regulator values (row), return a BDD over parameters describing when the function is true.
+/// Row index `r` assigns to the `i`-th regulator the value of the `i`-th bit of `r`.
+pub fn mk_update_fn_rows(
+    graph: &SymbolicAsyncGraph,
+    var: VariableId,
+) -> Result<Vec<Bdd>, SketchError> {
+    let context = graph.symbolic_context();
+    let bn = get_network(graph)?;
+    let fn_true = mk_update_fn_true(graph, var)?;
+    let regulator_vars: Vec<_> = bn
+        .regulators(var)
+        .into_iter()
+        .map(|r| context.get_state_variable(r))
+        .collect();
+
+    let rows = (0..(1usize << regulator_vars.len()))
+        .map(|row| {
+            let valuation: Vec<_> = regulator_vars
+                .iter()
+                .enumerate()
+                .map(|(i, bdd_var)| (*bdd_var, row & (1 << i) != 0))
+                .collect();
+            fn_true.restrict(&valuation)
+        })
+        .collect();
+    Ok(rows)
+}
+
+/// Enumerate (at most `limit`) distinct update functions of `var` among the candidates `colors`.
+pub fn enumerate_update_functions(
+    graph: &SymbolicAsyncGraph,
+    colors: &GraphColors,
+    var: VariableId,
+    limit: usize,
+) -> Result<Vec<String>, SketchError> {
+    let context = graph.symbolic_context();
+    let bn = get_network(graph)?;
+    let fn_true = mk_update_fn_true(graph, var)?;
+    let param_vars = context.parameter_variables();
+    let state_vars = context.state_variables();
+
+    let mut functions = Vec::new();
+    let mut remaining = colors.clone();
+    while !remaining.is_empty() && functions.len() < limit {
+        let witness = remaining.pick_singleton();
+        let function_bdd = fn_true.and(witness.as_bdd()).exists(param_vars);
+        functions.push(FnUpdate::build_from_bdd(context, &function_bdd).to_string(bn));
+        // remove all candidates with the same function
+        let same_fn = fn_true.iff(&function_bdd).for_all(state_vars);
+        remaining = remaining.minus(&graph.mk_unit_colors().copy(same_fn));
     }
+    Ok(functions)
 }
 
-/// Randomly select a color from the given set of colors.
+/// Randomly select a color from the given (non-empty) set of colors.
 /// This is a workaround that should be modified in future.
 pub fn pick_random_color(
     rng: &mut ThreadRng,
     graph: &SymbolicAsyncGraph,
     color_set: &GraphColors,
-) -> GraphColors {
-    let random_witness = color_set.as_bdd().random_valuation(rng).unwrap();
+) -> Result<GraphColors, SketchError> {
+    let Some(random_witness) = color_set.as_bdd().random_valuation(rng) else {
+        return Err(SketchError::InvalidArgument(
+            "Cannot pick a color from an empty set.".to_string(),
+        ));
+    };
 
     let bdd_vars = graph.symbolic_context().bdd_variable_set();
     let mut partial_valuation = BddPartialValuation::empty();
@@ -141,7 +598,96 @@ pub fn pick_random_color(
     // variables from the random valuation (although the `pick_witness` method shouldn't
     // really care about extra variables in the BDD at all).
     let singleton_set = graph.unit_colors().copy(singleton_bdd);
-    singleton_set
+    Ok(singleton_set)
+}
+
+/// Iterator over all subsets of `items` with a given size (in lexicographic order). The subsets
+/// are generated lazily, one at a time.
+pub struct Subsets<'a, T> {
+    items: &'a [T],
+    /// Indices of the items of the next subset (`None` once all subsets were generated).
+    indices: Option<Vec<usize>>,
+}
+
+impl<'a, T: Clone> Subsets<'a, T> {
+    pub fn new(items: &'a [T], size: usize) -> Self {
+        let indices = if size <= items.len() {
+            Some((0..size).collect())
+        } else {
+            None
+        };
+        Self { items, indices }
+    }
+}
+
+impl<T: Clone> Iterator for Subsets<'_, T> {
+    type Item = Vec<T>;
+
+    fn next(&mut self) -> Option<Vec<T>> {
+        let indices = self.indices.as_mut()?;
+        let subset = indices.iter().map(|i| self.items[*i].clone()).collect();
+        // advance the last index that can be increased, and reset the following ones
+        let size = indices.len();
+        match (0..size)
+            .rev()
+            .find(|k| indices[*k] < self.items.len() - size + k)
+        {
+            Some(k) => {
+                indices[k] += 1;
+                for j in k + 1..size {
+                    indices[j] = indices[j - 1] + 1;
+                }
+            }
+            None => self.indices = None,
+        }
+        Some(subset)
+    }
+}
+
+/// Outcome of examining a subset during `search_minimal_subsets`.
+pub enum SubsetOutcome<R> {
+    /// The subset is not reported.
+    Rejected,
+    /// The subset is reported, but its supersets are still examined.
+    Reported(R),
+    /// The subset is reported, and its supersets are skipped.
+    Minimal(R),
+}
+
+/// Examine the subsets of `items` (with at most `max_size` elements) by increasing size, and
+/// collect the reported results (in the order of examination). Supersets of subsets with
+/// a `Minimal` outcome are skipped, so that only minimal subsets are reported. The subsets are
+/// generated lazily.
+///
+/// The `evaluate` function gets the examined subset, and the results reported for its subsets.
+pub fn search_minimal_subsets<T: Clone + PartialEq, R>(
+    items: &[T],
+    max_size: usize,
+    mut evaluate: impl FnMut(&[T], &[&R]) -> Result<SubsetOutcome<R>, SketchError>,
+) -> Result<Vec<R>, SketchError> {
+    let is_subset = |smaller: &[T], subset: &[T]| smaller.iter().all(|x| subset.contains(x));
+    let mut reported: Vec<(Vec<T>, R, bool)> = Vec::new();
+    for size in 1..=max_size {
+        for subset in Subsets::new(items, size) {
+            let is_superset = reported
+                .iter()
+                .any(|(smaller, _, is_minimal)| *is_minimal && is_subset(smaller, &subset));
+            if is_superset {
+                continue;
+            }
+            let reported_subsets: Vec<&R> = reported
+                .iter()
+                .filter(|(smaller, ..)| is_subset(smaller, &subset))
+                .map(|(_, result, _)| result)
+                .collect();
+            match evaluate(&subset, &reported_subsets)? {
+                SubsetOutcome::Rejected => {}
+                SubsetOutcome::Reported(result) => reported.push((subset, result, false)),
+                SubsetOutcome::Minimal(result) => reported.push((subset, result, true)),
+            }
+        }
+    }
+    Ok(reported.into_iter().map(|(_, result, _)| result).collect())
 }
 
 /// Naively go through all candidates given by their `colors` and summarize their update fns.
@@ -153,14 +699,12 @@ pub fn summarize_candidates_naively(
     graph: &SymbolicAsyncGraph,
     mut colors: GraphColors,
     print_exact_fns: bool,
-) {
+) -> Result<(), SketchError> {
+    let network = get_network(graph)?;
     // prepare the map for capturing update fn variants <VarName: <UpdateFn: Count>>
     let mut update_fns: HashMap<String, HashMap<FnUpdate, i32>> = HashMap::new();
-    for v in graph.as_network().unwrap().variables() {
-        update_fns.insert(
-            graph.as_network().unwrap().get_variable_name(v).clone(),
-            HashMap::new(),
-        );
+    for v in network.variables() {
+        update_fns.insert(network.get_variable_name(v).clone(), HashMap::new());
     }
 
     // iterate through candidates (colors)
@@ -173,21 +717,14 @@ pub fn summarize_candidates_naively(
         let bn = graph.pick_witness(&c);
 
         for v in bn.variables() {
-            let var_name = bn.get_variable_name(v).as_str();
-            // let update_str = format!("{:?}", bn.get_update_function(v).clone().unwrap());
-            let update_fn = bn.get_update_function(v).clone().unwrap();
-            if update_fns[var_name].contains_key(&update_fn) {
-                *update_fns
-                    .get_mut(var_name)
-                    .unwrap()
-                    .get_mut(&update_fn)
-                    .unwrap() += 1;
-            } else {
-                update_fns
-                    .get_mut(var_name)
-                    .unwrap()
-                    .insert(update_fn.clone(), 1);
-            }
+            // witness networks always have all update functions specified
+            let Some(update_fn) = bn.get_update_function(v).clone() else {
+                continue;
+            };
+            let var_fns = update_fns
+                .entry(bn.get_variable_name(v).clone())
+                .or_default();
+            *var_fns.entry(update_fn).or_insert(0) += 1;
         }
         colors = colors.minus(&c);
     }
@@ -207,10 +744,7 @@ pub fn summarize_candidates_naively(
 
         for (update_fn, num) in fn_map {
             if print_exact_fns {
-                print!(
-                    "\"{}\" ${num}$  ",
-                    update_fn.to_string(graph.as_network().unwrap())
-                );
+                print!("\"{}\" ${num}$  ", update_fn.to_string(network));
             } else {
                 print!("{num} ");
             }
@@ -232,12 +766,24 @@ pub fn summarize_candidates_naively(
         vars_with_unique_fns.len(),
         vars_with_unique_fns
     );
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::utils::pick_random_color;
+    use crate::errors::SketchError;
+    use crate::monitoring::{CancellationToken, InferenceMonitor, InterruptReason};
+    use crate::update_semantics::{TransitionRelation, UpdateSemantics};
+    use crate::utils::{
+        apply_constraint_trees_and_restrict, apply_constraint_trees_in_parallel,
+        apply_constraint_trees_with_shared_subformulae,
+        apply_constraint_trees_with_transitions_and_restrict,
+        apply_named_constraint_trees_and_restrict, check_if_result_contains_goal_unsafe,
+        pick_random_color, search_minimal_subsets, SubsetOutcome, Subsets,
+    };
     use biodivine_hctl_model_checker::mc_utils::get_extended_symbolic_graph;
+    use biodivine_hctl_model_checker::preprocessing::parser::parse_and_minimize_hctl_formula;
+    use biodivine_lib_param_bn::biodivine_std::traits::Set;
     use biodivine_lib_param_bn::BooleanNetwork;
 
     const TEST_MODEL: &str = r"
@@ -249,6 +795,51 @@ v_1 -> v_3
 v_3 -?? v_3
 ";
 
+    #[test]
+    /// Test applying properties under a schedule with priority classes. Fixed points do not
+    /// depend on the schedule, but the reachability does.
+    fn test_apply_properties_with_priority_classes() {
+        let bn = BooleanNetwork::try_from(TEST_MODEL).unwrap();
+        // one HCTL variable, and one extra variable for the transition relation
+        let graph = get_extended_symbolic_graph(&bn, 2).unwrap();
+        let v_3 = bn.find_variable("v_3").unwrap();
+        let transitions =
+            TransitionRelation::new(&graph, UpdateSemantics::Asynchronous, &[vec![v_3]]).unwrap();
+        let parse = |formula: &str| {
+            parse_and_minimize_hctl_formula(graph.symbolic_context(), formula).unwrap()
+        };
+
+        let fixed_point = vec![parse("3{x}: @{x}: (v_1 & AX {x})")];
+        let async_result =
+            apply_constraint_trees_and_restrict(fixed_point.clone(), graph.clone(), "").unwrap();
+        let priority_result = apply_constraint_trees_with_transitions_and_restrict(
+            fixed_point,
+            graph.clone(),
+            Some(transitions.clone()),
+            "",
+        )
+        .unwrap();
+        assert_eq!(
+            async_result.mk_unit_colors(),
+            priority_result.mk_unit_colors()
+        );
+
+        // `v_1` (with update function `!v_3`) can be always switched on in states with `~v_3`,
+        // but only if `v_3` cannot change when `v_3` is faster
+        let blocked = vec![parse("3{x}: @{x}: (~v_1 & ~v_3 & ~EX (v_1 & ~v_3))")];
+        let async_result =
+            apply_constraint_trees_and_restrict(blocked.clone(), graph.clone(), "").unwrap();
+        assert!(async_result.mk_unit_colors().is_empty());
+        let priority_result = apply_constraint_trees_with_transitions_and_restrict(
+            blocked,
+            graph,
+            Some(transitions),
+            "",
+        )
+        .unwrap();
+        assert!(!priority_result.mk_unit_colors().is_empty());
+    }
+
     #[test]
     /// Test basic properties of random color selection.
     fn test_pick_color() {
@@ -257,7 +848,202 @@ v_3 -?? v_3
         let stg = get_extended_symbolic_graph(&bn, 1).unwrap();
         let color_set = stg.mk_unit_colors();
 
-        let singleton_set = pick_random_color(&mut rng, &stg, &color_set);
+        let singleton_set = pick_random_color(&mut rng, &stg, &color_set).unwrap();
         assert_eq!(singleton_set.approx_cardinality(), 1.0); // only one color is selected
+
+        let empty_set = stg.mk_empty_colors();
+        assert!(pick_random_color(&mut rng, &stg, &empty_set).is_err());
+    }
+
+    #[test]
+    /// Test applying named properties with progress reporting and cancellation.
+    fn test_apply_properties_with_monitor() {
+        let bn = BooleanNetwork::try_from(TEST_MODEL).unwrap();
+        let graph = get_extended_symbolic_graph(&bn, 1).unwrap();
+        let num_colors = graph.mk_unit_colors().approx_cardinality();
+        let context = graph.symbolic_context();
+        let named_trees = vec![
+            ("attractor".to_string(), "3{x}: @{x}: AG EF {x}"),
+            ("fixed_point".to_string(), "3{x}: @{x}: v_1 & AX {x}"),
+        ]
+        .into_iter()
+        .map(|(name, formula)| {
+            (
+                name,
+                parse_and_minimize_hctl_formula(context, formula).unwrap(),
+            )
+        })
+        .collect::<Vec<_>>();
+
+        let mut progress_list = Vec::new();
+        let mut monitor = InferenceMonitor::new(CancellationToken::new());
+        monitor.set_progress_callback(|progress| progress_list.push(progress.clone()));
+        let result = apply_named_constraint_trees_and_restrict(
+            named_trees.clone(),
+            graph.clone(),
+            &mut monitor,
+        )
+        .unwrap();
+        drop(monitor);
+        assert!(result.interruption.is_none());
+        assert_eq!(progress_list.len(), 2);
+        assert_eq!(progress_list[0].property_name, "attractor");
+        assert_eq!(progress_list[0].num_candidates, num_colors);
+        assert_eq!(
+            progress_list[1].num_candidates,
+            result.graph.mk_unit_colors().approx_cardinality()
+        );
+
+        // cancelled run stops before the first property and keeps all candidates
+        let token = CancellationToken::new();
+        token.cancel();
+        let mut monitor = InferenceMonitor::new(token);
+        let result =
+            apply_named_constraint_trees_and_restrict(named_trees, graph, &mut monitor).unwrap();
+        let interruption = result.interruption.unwrap();
+        assert_eq!(interruption.property_name, "attractor");
+        assert_eq!(interruption.reason, InterruptReason::Cancelled);
+        assert_eq!(
+            result.graph.mk_unit_colors().approx_cardinality(),
+            num_colors
+        );
+    }
+
+    #[test]
+    /// Test that evaluating properties in parallel gives the same result as the sequential way.
+    fn test_apply_properties_in_parallel() {
+        let bn = BooleanNetwork::try_from(TEST_MODEL).unwrap();
+        let graph = get_extended_symbolic_graph(&bn, 1).unwrap();
+        let context = graph.symbolic_context();
+        let named_trees = vec![
+            ("fixed_point".to_string(), "3{x}: @{x}: v_1 & AX {x}"),
+            ("reachability".to_string(), "3{x}: @{x}: ~v_2 & EF v_2"),
+            ("attractor".to_string(), "3{x}: @{x}: v_3 & AG EF {x}"),
+        ]
+        .into_iter()
+        .map(|(name, formula)| {
+            (
+                name,
+                parse_and_minimize_hctl_formula(context, formula).unwrap(),
+            )
+        })
+        .collect::<Vec<_>>();
+
+        let mut monitor = InferenceMonitor::new(CancellationToken::new());
+        let sequential = apply_named_constraint_trees_and_restrict(
+            named_trees.clone(),
+            graph.clone(),
+            &mut monitor,
+        )
+        .unwrap();
+
+        for num_threads in [0, 1, 2, 4] {
+            let mut num_reported = 0;
+            let mut monitor = InferenceMonitor::new(CancellationToken::new());
+            monitor.set_progress_callback(|_| num_reported += 1);
+            let parallel = apply_constraint_trees_in_parallel(
+                named_trees.clone(),
+                graph.clone(),
+                num_threads,
+                &mut monitor,
+            )
+            .unwrap();
+            drop(monitor);
+            assert!(parallel.interruption.is_none());
+            assert_eq!(num_reported, 3);
+            assert_eq!(
+                parallel.graph.unit_colors().as_bdd(),
+                sequential.graph.unit_colors().as_bdd()
+            );
+        }
+    }
+
+    #[test]
+    /// Test that evaluating properties with shared sub-formulae together gives the same result as
+    /// the one-by-one evaluation.
+    fn test_apply_properties_with_shared_subformulae() {
+        let bn = BooleanNetwork::try_from(TEST_MODEL).unwrap();
+        let graph = get_extended_symbolic_graph(&bn, 1).unwrap();
+        let context = graph.symbolic_context();
+        let named_trees = vec![
+            ("attractor_1".to_string(), "3{x}: @{x}: v_1 & AG EF {x}"),
+            ("fixed_point".to_string(), "3{x}: @{x}: v_1 & AX {x}"),
+            ("attractor_2".to_string(), "3{y}: @{y}: ~v_1 & AG EF {y}"),
+        ]
+        .into_iter()
+        .map(|(name, formula)| {
+            (
+                name,
+                parse_and_minimize_hctl_formula(context, formula).unwrap(),
+            )
+        })
+        .collect::<Vec<_>>();
+
+        let mut monitor = InferenceMonitor::new(CancellationToken::new());
+        let sequential = apply_named_constraint_trees_and_restrict(
+            named_trees.clone(),
+            graph.clone(),
+            &mut monitor,
+        )
+        .unwrap();
+
+        let mut reported = Vec::new();
+        let mut monitor = InferenceMonitor::new(CancellationToken::new());
+        monitor.set_progress_callback(|progress| reported.push(progress.property_name.clone()));
+        let shared =
+            apply_constraint_trees_with_shared_subformulae(named_trees, graph, &mut monitor)
+                .unwrap();
+        drop(monitor);
+        assert!(shared.interruption.is_none());
+        assert_eq!(reported, vec!["attractor_1, attractor_2", "fixed_point"]);
+        assert_eq!(
+            shared.graph.unit_colors().as_bdd(),
+            sequential.graph.unit_colors().as_bdd()
+        );
+    }
+
+    #[test]
+    /// Test lazy generation of subsets, and the search for minimal subsets.
+    fn test_search_minimal_subsets() {
+        let subsets: Vec<Vec<usize>> = Subsets::new(&[1, 2, 3, 4], 2).collect();
+        assert_eq!(
+            subsets,
+            vec![
+                vec![1, 2],
+                vec![1, 3],
+                vec![1, 4],
+                vec![2, 3],
+                vec![2, 4],
+                vec![3, 4]
+            ]
+        );
+        assert_eq!(Subsets::new(&[1, 2], 3).count(), 0);
+
+        // subsets with `1` are minimal, subsets with `2` are reported (only alone), others not
+        let results = search_minimal_subsets(&[1, 2, 3], 3, |subset, reported| {
+            Ok(if subset.contains(&1) {
+                SubsetOutcome::Minimal(subset.to_vec())
+            } else if subset.contains(&2) && reported.is_empty() {
+                SubsetOutcome::Reported(subset.to_vec())
+            } else {
+                SubsetOutcome::Rejected
+            })
+        })
+        .unwrap();
+        assert_eq!(results, vec![vec![1], vec![2]]);
+    }
+
+    #[test]
+    /// Test checking the goal network, and that a goal that can't be encoded results in an error.
+    fn test_check_goal() {
+        let bn = BooleanNetwork::try_from("a -> b\n").unwrap();
+        let graph = get_extended_symbolic_graph(&bn, 1).unwrap();
+        let colors = graph.mk_unit_colors();
+        let goal = "a -> b\n$a: true\n$b: a\n".to_string();
+        assert!(check_if_result_contains_goal_unsafe(graph.clone(), goal, colors.clone()).unwrap());
+
+        let goal = "a -> c\n$a: true\n$c: a\n".to_string();
+        let result = check_if_result_contains_goal_unsafe(graph, goal, colors);
+        assert!(matches!(result, Err(SketchError::SymbolicEncoding(_))));
     }
 }